@@ -19,6 +19,8 @@ impl From<&FinanalizeError> for UserError {
         match e {
             FinanalizeError::Unauthorized(e) => UserError(e.to_string()),
             FinanalizeError::NotFound => UserError("Not found".to_string()),
+            FinanalizeError::UnsupportedDocument(_) => UserError(e.to_string()),
+            FinanalizeError::NoTextLayer(_) => UserError(e.to_string()),
             FinanalizeError::InternalServerError => UserError("Internal server error".to_string()),
             _ => UserError("Internal server error".to_string()),
         }
@@ -30,6 +32,10 @@ impl ResponseError for FinanalizeError {
         match self {
             FinanalizeError::Unauthorized(_) => actix_web::http::StatusCode::UNAUTHORIZED,
             FinanalizeError::NotFound => actix_web::http::StatusCode::NOT_FOUND,
+            FinanalizeError::UnsupportedDocument(_) => {
+                actix_web::http::StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            FinanalizeError::NoTextLayer(_) => actix_web::http::StatusCode::UNPROCESSABLE_ENTITY,
             _ => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -0,0 +1,31 @@
+use crate::api::ApiResponse;
+use crate::models::SurrealDBUser;
+use crate::prelude::*;
+use crate::uploads::{self, FrontendDocument};
+use actix_multipart::form::{tempfile::TempFile, MultipartForm};
+use actix_web::{post, Responder};
+use log::debug;
+
+#[derive(Debug, MultipartForm)]
+pub struct DocumentUpload {
+    #[multipart(limit = "50MB")]
+    files: Vec<TempFile>,
+}
+
+/// Stores PDFs, spreadsheets, CSV exports and text files to be used as report sources.
+#[post("/documents")]
+pub async fn upload_documents(
+    user: SurrealDBUser,
+    MultipartForm(form): MultipartForm<DocumentUpload>,
+) -> Result<impl Responder> {
+    let mut documents = vec![];
+    for file in form.files {
+        let name = uploads::file_name(file.file_name.as_deref());
+        debug!("Uploading {} ({} bytes)", name, file.size);
+        let content_type = file.content_type.as_ref().map(|mime| mime.essence_str());
+        let document =
+            uploads::store(user.id.clone(), name, content_type, file.file.path()).await?;
+        documents.push(FrontendDocument::from(document));
+    }
+    Ok(ApiResponse::new(documents))
+}
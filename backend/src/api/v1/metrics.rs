@@ -0,0 +1,11 @@
+use crate::api::ApiResponse;
+use crate::llm::SCHEDULER;
+use crate::models::SurrealDBUser;
+use crate::prelude::*;
+use actix_web::{get, Responder};
+
+/// Queue depth and token usage of the LLM scheduler, per model
+#[get("/metrics/llm")]
+pub async fn get_llm_metrics(_user: SurrealDBUser) -> Result<impl Responder> {
+    Ok(ApiResponse::new(SCHEDULER.metrics()))
+}
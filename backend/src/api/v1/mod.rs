@@ -1,2 +1,4 @@
 pub mod auth;
+pub mod documents;
+pub mod metrics;
 pub mod report;
@@ -8,6 +8,8 @@ use crate::models::{
 use crate::prelude::FinanalizeError;
 use crate::prelude::*;
 use crate::rabbitmq::PUBLISHER;
+use crate::search::options::SearchFilters;
+use crate::uploads::{self, Upload};
 use crate::workflow::{JobType, SDBWorkflowState, WorkflowState};
 use actix_files::NamedFile;
 use actix_web::web::{self, Data, Json, Path};
@@ -54,6 +56,22 @@ impl ReportSize {
             ReportSize::Large => 6,
         }
     }
+
+    pub fn search_result_amount(&self) -> usize {
+        match self {
+            ReportSize::Small => 3,
+            ReportSize::Medium => 5,
+            ReportSize::Large => 8,
+        }
+    }
+
+    pub fn search_page_amount(&self) -> usize {
+        match self {
+            ReportSize::Small => 1,
+            ReportSize::Medium => 1,
+            ReportSize::Large => 2,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,11 +84,62 @@ pub enum ReportModel {
     OpenAI,
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportLanguage {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "nl")]
+    Dutch,
+    #[serde(rename = "fr")]
+    French,
+}
+
+impl ReportLanguage {
+    /// The name of the language as given to the prompts
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportLanguage::English => "English",
+            ReportLanguage::Dutch => "Dutch",
+            ReportLanguage::French => "French",
+        }
+    }
+
+    /// ISO 639-1 code, as used by the search providers
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReportLanguage::English => "en",
+            ReportLanguage::Dutch => "nl",
+            ReportLanguage::French => "fr",
+        }
+    }
+
+    /// The `babel` option for the LaTeX template, which also selects the hyphenation patterns
+    pub fn babel(&self) -> &'static str {
+        match self {
+            ReportLanguage::English => "english",
+            ReportLanguage::Dutch => "dutch",
+            ReportLanguage::French => "french",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ReportCreationLight {
     user_input: String,
     size: ReportSize,
     model: ReportModel,
+    #[serde(default)]
+    language: ReportLanguage,
+    /// Ids of uploaded documents to use as sources
+    #[serde(default)]
+    documents: Vec<String>,
+    /// Leave out sources older than this many days
+    #[serde(default)]
+    max_source_age: Option<u32>,
+    /// Search depth and filters, unset ones follow the report size and configuration
+    #[serde(default)]
+    search_filters: Option<SearchFilters>,
 }
 
 #[post("/reports")]
@@ -79,10 +148,15 @@ pub async fn create_report(
     db: Data<SurrealDb>,
     report_creation: Json<ReportCreationLight>,
 ) -> Result<impl Responder> {
+    let documents = uploads::owned(&user.id, &report_creation.documents).await?;
     let report_creation = ReportCreation::new(
         report_creation.user_input.clone(),
         report_creation.size.clone(),
         report_creation.model.clone(),
+        report_creation.language.clone(),
+        documents.iter().map(Upload::from).collect(),
+        report_creation.max_source_age,
+        report_creation.search_filters.clone(),
     );
     let report: FullSDBReport = db
         .create("report")
@@ -178,6 +252,7 @@ pub async fn get_report(
         status: workflow_state.state.status,
         size: workflow_state.state.size,
         model: workflow_state.state.model,
+        language: workflow_state.state.language,
         title: workflow_state.state.title,
         valid: Some(valid),
         error,
@@ -365,6 +440,7 @@ pub async fn get_live_report(
                 status: report.state.status,
                 size: report.state.size.clone(),
                 model: report.state.model.clone(),
+                language: report.state.language.clone(),
                 title: report.state.title.clone(),
                 valid: Some(report.state.validation.clone().unwrap().valid),
                 error: None,
@@ -45,9 +45,9 @@ pub async fn persist(file: PathBuf) -> Result<PersistedBlob> {
     let uuid = Uuid::new_v4();
     let persistance_dir = get_persistance_dir()?;
     let new_dir = persistance_dir.join(uuid.to_string());
-    std::fs::create_dir(&new_dir)?;
+    tokio::fs::create_dir(&new_dir).await?;
     let new_file = new_dir.join(file.file_name().unwrap());
-    std::fs::copy(&file, &new_file)?;
+    tokio::fs::copy(&file, &new_file).await?;
     let created: PersistedBlob = DB
         .get()
         .unwrap()
@@ -1,12 +1,17 @@
-use super::{Column, Data, DataExtract};
+use super::{table::typed_column, Column, Data, Document, Extract, RawDocument};
+use crate::api::v1::report::ReportLanguage;
 use crate::tasks::TaskResult;
 use crate::workflow::job::classify_sources::models::ClassifySourcesInput;
 use crate::{llm::API, prelude::*, prompting, tasks::Task};
 use async_trait::async_trait;
 use log::debug;
-use polars::{io::SerReader, prelude::CsvReadOptions};
+use polars::{
+    io::SerReader,
+    prelude::{CsvReadOptions, DataFrame, DataType},
+};
 use schemars::{schema_for, JsonSchema};
 use serde::{Deserialize, Serialize};
+use std::io::Cursor;
 
 pub struct CsvExtractor;
 
@@ -24,11 +29,17 @@ pub struct ClassifierColumn {
 }
 
 #[async_trait]
-impl DataExtract for CsvExtractor {
-    async fn extract(&self, file: &str) -> Result<Data> {
-        let df = CsvReadOptions::default()
-            .try_into_reader_with_file_path(Some(file.into()))?
-            .finish()?;
+impl Extract for CsvExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["text/csv", "application/csv"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    async fn extract(&self, file: &RawDocument) -> Result<Document> {
+        let df = data_frame(&file.body)?;
 
         // TODO: Make head into markdown table
         let mut markdown_table = String::new();
@@ -60,6 +71,7 @@ impl DataExtract for CsvExtractor {
         //Use ClassifySourceInput and put the table in it
         let input = ClassifySourcesInput {
             input: markdown_table,
+            language: ReportLanguage::default().name().into(),
         };
 
         //Start job run structured data classification
@@ -75,28 +87,16 @@ impl DataExtract for CsvExtractor {
         let output = res.output;
 
         // After getting your output from the task.run_structured call
-        let mut columns = vec![];
-        for column in df.get_columns() {
-            let column = column.cast(&polars::prelude::DataType::String)?;
-            let column_name = column.name().as_str();
-
-            // Find the matching description from the classifier output
-            let description = output
-                .columns
-                .iter()
-                .find(|c| c.title == column_name)
-                .map(|c| c.description.clone())
-                .unwrap_or_else(String::new);
-
-            columns.push(Column {
-                name: column_name.into(),
-                description, // Use the found description instead of an empty string
-                values: column
-                    .str()?
-                    .into_iter()
-                    .map(|v| v.unwrap_or("").into())
-                    .collect(),
-            });
+        // Scales such as "in millions" are often only in the title
+        let context = format!("{} {}", output.title, output.description);
+        let mut columns = typed_columns(&df, &context)?;
+        for column in columns.iter_mut() {
+            // The classifier's description beats the inferred unit
+            if let Some(classified) = output.columns.iter().find(|c| c.title == column.name) {
+                if !classified.description.is_empty() {
+                    column.description = classified.description.clone();
+                }
+            }
         }
         debug!("Columns: {:?}", columns);
         // TODO: Generate actual title and description from DataFrame metadata
@@ -107,50 +107,84 @@ impl DataExtract for CsvExtractor {
             title: output.title,
             description: output.description,
             columns,
+            source: None,
+            page: None,
         };
         debug!("Data: {:?}", data);
-        // Return as Vec<Content> by converting Data into Content::Csv
-        Ok(data)
+        Ok(Document {
+            tables: vec![data],
+            ..Default::default()
+        })
+    }
+}
+
+fn data_frame(body: &[u8]) -> Result<DataFrame> {
+    Ok(CsvReadOptions::default()
+        .into_reader_with_file_handle(Cursor::new(body.to_vec()))
+        .finish()?)
+}
+
+/// Every column of the file with its type, units and scales inferred from its values.
+fn typed_columns(df: &DataFrame, context: &str) -> Result<Vec<Column>> {
+    let mut columns = vec![];
+    for column in df.get_columns() {
+        let column = column.cast(&DataType::String)?;
+        let values = column
+            .str()?
+            .into_iter()
+            .map(|v| v.unwrap_or("").into())
+            .collect();
+        columns.push(typed_column(column.name().to_string(), values, context));
     }
+    Ok(columns)
+}
+
+/// Reads a CSV file without asking the classifier, the columns are described by the types
+/// inferred from their values.
+pub fn csv_data(title: String, body: &[u8]) -> Result<Data> {
+    let df = data_frame(body)?;
+    Ok(Data {
+        columns: typed_columns(&df, &title)?,
+        title,
+        description: String::new(),
+        source: None,
+        page: None,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
-    use std::io::Write;
-    use tempfile::tempdir;
+    use crate::extractors::ColumnType;
+
+    #[test]
+    fn test_csv_data() {
+        let csv = b"segment,revenue\nCloud,$1.2M\nDevices,$800K\n";
+        let data = csv_data("Revenue by segment".into(), csv).unwrap();
+        assert_eq!(data.title, "Revenue by segment");
+        assert_eq!(data.columns[0].values, ["Cloud", "Devices"]);
+        assert_eq!(data.columns[1].kind, ColumnType::Currency);
+    }
 
     #[tokio::test]
     #[ignore = "Uses Ollama for generation"]
     async fn test_extract() {
         dotenvy::from_filename(".env").ok();
-        // Create a temporary directory
-        let dir = tempdir().unwrap();
-        let file_path = dir.path().join("sample.csv");
-        let csv_file = file_path.to_str().unwrap();
-
-        // Write sample CSV data to the file
-        let mut file = File::create(&file_path).unwrap();
-        writeln!(file, "name,age,city").unwrap();
-        writeln!(file, "Alice,30,New York").unwrap();
-        writeln!(file, "Bob,25,Los Angeles").unwrap();
-        writeln!(file, "Charlie,35,Chicago").unwrap();
+        let csv = "name,age,city\nAlice,30,New York\nBob,25,Los Angeles\nCharlie,35,Chicago\n";
 
         // Create an instance of CsvExtractor
         let extractor = CsvExtractor;
 
         // Call the extract function
-        let result = extractor.extract(csv_file).await;
+        let result = extractor
+            .extract(&RawDocument::new("sample.csv", None, csv.into()))
+            .await;
 
         // Assert that the result is Ok
         let content = result.unwrap();
 
         // Make sure we have at least one content in the result (for CSV)
-        assert!(!content.columns.is_empty());
+        assert!(!content.tables[0].columns.is_empty());
         dbg!(content);
-
-        // Clean up the temporary directory
-        dir.close().unwrap();
     }
 }
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde_json::Value;
+
+use super::table::parse_date;
+
+/// Meta tags publishers put the publication date in, most specific first.
+const DATE_META: &[&str] = &[
+    r#"meta[property="article:published_time"]"#,
+    r#"meta[property="og:published_time"]"#,
+    r#"meta[itemprop="datePublished"]"#,
+    r#"meta[name="parsely-pub-date"]"#,
+    r#"meta[name="sailthru.date"]"#,
+    r#"meta[name="pubdate"]"#,
+    r#"meta[name="publish-date"]"#,
+    r#"meta[name="publication_date"]"#,
+    r#"meta[name="citation_publication_date"]"#,
+    r#"meta[name="dc.date.issued" i]"#,
+    r#"meta[name="dc.date" i]"#,
+    r#"meta[name="date"]"#,
+];
+
+/// `/2025/01/30/`, `/2025-01-30-` or `/2025/01/` in a URL path.
+static URL_DATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:^|/)((?:19|20)\d{2})[/_-](\d{1,2})(?:[/_-](\d{1,2}))?(?:[/_-]|\.html?|$)")
+        .unwrap()
+});
+
+/// Older dates are misread numbers more often than they are sources.
+const EARLIEST_YEAR: i32 = 1990;
+
+/// Whether a date could be a publication date, allowing for time zones ahead of UTC.
+fn plausible(date: NaiveDate) -> bool {
+    date.year() >= EARLIEST_YEAR && date <= Utc::now().date_naive() + Duration::days(1)
+}
+
+/// Parses a publication date as written in metadata, usually ISO 8601 with a time of day.
+pub fn parse_published(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    let date = DateTime::parse_from_rfc3339(text)
+        .map(|date| date.date_naive())
+        .or_else(|_| DateTime::parse_from_rfc2822(text).map(|date| date.date_naive()))
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f").map(|d| d.date()))
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok())
+        .or_else(|| {
+            // Compact dates such as `20250130` only when that is the whole value
+            (text.len() == 8 && text.chars().all(|c| c.is_ascii_digit()))
+                .then(|| NaiveDate::parse_from_str(text, "%Y%m%d").ok())
+                .flatten()
+        })
+        .or_else(|| parse_date(text))?;
+    plausible(date).then_some(date)
+}
+
+/// The first `datePublished` in a JSON-LD value, looking through graphs and lists.
+fn json_ld_date(value: &Value) -> Option<NaiveDate> {
+    match value {
+        Value::Object(object) => object
+            .get("datePublished")
+            .and_then(Value::as_str)
+            .and_then(parse_published)
+            .or_else(|| object.values().find_map(json_ld_date)),
+        Value::Array(values) => values.iter().find_map(json_ld_date),
+        _ => None,
+    }
+}
+
+/// The publication date a page declares in its meta tags, JSON-LD or `<time>` elements.
+pub fn html_date(document: &Html) -> Option<NaiveDate> {
+    let meta = DATE_META.iter().find_map(|selector| {
+        let selector = Selector::parse(selector).ok()?;
+        document
+            .select(&selector)
+            .filter_map(|element| element.value().attr("content"))
+            .find_map(parse_published)
+    });
+    if meta.is_some() {
+        return meta;
+    }
+    let scripts = Selector::parse(r#"script[type="application/ld+json"]"#).ok()?;
+    let json_ld = document.select(&scripts).find_map(|script| {
+        let json: Value = serde_json::from_str(script.text().collect::<String>().trim()).ok()?;
+        json_ld_date(&json)
+    });
+    if json_ld.is_some() {
+        return json_ld;
+    }
+    let time =
+        Selector::parse(r#"time[itemprop="datePublished"][datetime], time[pubdate][datetime]"#)
+            .ok()?;
+    document
+        .select(&time)
+        .filter_map(|element| element.value().attr("datetime"))
+        .find_map(parse_published)
+}
+
+/// The date in the path of a URL, news sites often file articles by day or month. Months
+/// without a day are dated to their first day.
+pub fn url_date(url: &str) -> Option<NaiveDate> {
+    let path = Url::parse(url).ok()?.path().to_string();
+    let captures = URL_DATE.captures(&path)?;
+    let year = captures[1].parse().ok()?;
+    let month = captures[2].parse().ok()?;
+    let day = captures
+        .get(3)
+        .map_or(Some(1), |day| day.as_str().parse().ok())?;
+    NaiveDate::from_ymd_opt(year, month, day).filter(|date| plausible(*date))
+}
+
+/// The publication date of a page from its markup, or else from its URL.
+pub fn published_date(document: &Html, url: &str) -> Option<NaiveDate> {
+    html_date(document).or_else(|| url_date(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()
+    }
+
+    #[test]
+    fn test_parse_published() {
+        let jan_30 = date("2025-01-30");
+        assert_eq!(parse_published("2025-01-30T16:30:05-05:00"), jan_30);
+        assert_eq!(parse_published("Thu, 30 Jan 2025 21:30:05 GMT"), jan_30);
+        assert_eq!(parse_published("2025-01-30T16:30:05.123"), jan_30);
+        assert_eq!(parse_published("2025-01-30 16:30"), jan_30);
+        assert_eq!(parse_published("20250130"), jan_30);
+        assert_eq!(parse_published("January 30, 2025"), jan_30);
+        assert_eq!(parse_published("1970-01-01T00:00:00Z"), None);
+        assert_eq!(parse_published("2999-01-01"), None);
+        assert_eq!(parse_published("last Thursday"), None);
+    }
+
+    #[test]
+    fn test_html_date() {
+        let meta = Html::parse_document(
+            r#"<html><head>
+            <meta name="DC.Date" content="2024-12-01">
+            <meta property="article:published_time" content="2025-01-30T16:30:05-05:00">
+            </head></html>"#,
+        );
+        assert_eq!(html_date(&meta), date("2025-01-30"));
+        let json_ld = Html::parse_document(
+            r#"<html><head><script type="application/ld+json">
+            {"@context": "https://schema.org", "@graph": [
+                {"@type": "WebSite", "name": "CNBC"},
+                {"@type": "NewsArticle", "datePublished": "2025-01-30T21:30:05+0000"}
+            ]}
+            </script></head></html>"#,
+        );
+        assert_eq!(html_date(&json_ld), date("2025-01-30"));
+        let time = Html::parse_document(
+            r#"<html><body><time datetime="2025-01-30" itemprop="datePublished">Jan 30</time></body></html>"#,
+        );
+        assert_eq!(html_date(&time), date("2025-01-30"));
+        let dc = Html::parse_document(
+            r#"<html><head><meta name="dc.date" content="2024-12-01"></head></html>"#,
+        );
+        assert_eq!(html_date(&dc), date("2024-12-01"));
+        assert_eq!(html_date(&Html::parse_document("<html></html>")), None);
+    }
+
+    #[test]
+    fn test_url_date() {
+        assert_eq!(
+            url_date("https://www.cnbc.com/2025/01/30/apple-aapl-q1-earnings-2025.html"),
+            date("2025-01-30")
+        );
+        assert_eq!(
+            url_date("https://blog.tbrc.info/2025/02/apples-market-demand/"),
+            date("2025-02-01")
+        );
+        assert_eq!(
+            url_date("https://example.com/news/2024-11-05-results"),
+            date("2024-11-05")
+        );
+        assert_eq!(
+            url_date("https://www.nasdaq.com/articles/what-lies-ahead-2025"),
+            None
+        );
+        assert_eq!(url_date("https://example.com/products/2025/99/"), None);
+    }
+}
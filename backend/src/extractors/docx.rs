@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use log::debug;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use tokio::task;
+
+use super::{
+    office::{attribute, core_metadata, package, part},
+    table::{markdown_table, rows_data},
+    Data, Document, Extract, RawDocument, TextBlock,
+};
+use crate::prelude::*;
+
+/// Extracts the headings, paragraphs and tables of a Word document.
+pub struct DocxExtractor;
+
+#[async_trait]
+impl Extract for DocxExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &[
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            "application/vnd.ms-word.document.macroenabled.12",
+        ]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["docx", "docm"]
+    }
+
+    async fn extract(&self, file: &RawDocument) -> Result<Document> {
+        debug!("Extracting content from Word document...");
+        let body = file.body.clone();
+        let name = file.name.clone();
+        let document = task::spawn_blocking(move || read(&body, &name)).await??;
+        debug!(
+            "Extracted {} blocks and {} tables from {}",
+            document.blocks.len(),
+            document.tables.len(),
+            file.name
+        );
+        if document.blocks.is_empty() {
+            return Err(FinanalizeError::NotFound);
+        }
+        Ok(document)
+    }
+}
+
+fn read(body: &[u8], name: &str) -> Result<Document> {
+    let mut package = package(body)?;
+    let xml = part(&mut package, "word/document.xml")?
+        .ok_or_else(|| FinanalizeError::UnsupportedDocument(name.to_string()))?;
+    let styles = match part(&mut package, "word/styles.xml")? {
+        Some(styles) => heading_styles(&styles)?,
+        None => HashMap::new(),
+    };
+    let mut metadata = core_metadata(&mut package)?;
+    let (blocks, tables) = structure(&xml, &styles)?;
+    if metadata.title.is_none() {
+        metadata.title = blocks
+            .iter()
+            .find_map(|block| block.text.strip_prefix("# "))
+            .map(String::from);
+    }
+    Ok(Document {
+        metadata,
+        blocks,
+        tables,
+        ..Default::default()
+    })
+}
+
+/// The heading level of a style by its name, or by its id when the styles are missing.
+fn style_level(style: &str) -> Option<usize> {
+    let style = style.to_lowercase().replace(' ', "");
+    if style == "title" {
+        return Some(1);
+    }
+    let level: usize = style.strip_prefix("heading")?.parse().ok()?;
+    Some(level.clamp(1, 6))
+}
+
+/// The heading level of a `w:outlineLvl`. Levels 0-8 are headings, 9 is body text.
+fn outline_level(value: &str) -> Option<usize> {
+    let level: usize = value.parse().ok()?;
+    (level <= 8).then(|| (level + 1).min(6))
+}
+
+/// Heading levels of the paragraph styles by style id. The ids are localized, so headings
+/// are found by their name or outline level.
+fn heading_styles(xml: &str) -> Result<HashMap<String, usize>> {
+    let mut reader = Reader::from_str(xml);
+    let mut levels = HashMap::new();
+    let mut style: Option<(String, Option<usize>)> = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) if element.local_name().as_ref() == b"style" => {
+                style = attribute(&element, b"styleId").map(|id| (id, None));
+            }
+            Event::Start(element) | Event::Empty(element) => {
+                let Some((_, level)) = style.as_mut() else {
+                    continue;
+                };
+                let value = attribute(&element, b"val");
+                match element.local_name().as_ref() {
+                    b"name" => *level = level.or(value.as_deref().and_then(style_level)),
+                    b"outlineLvl" => {
+                        *level = level.or(value.as_deref().and_then(outline_level));
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(element) if element.local_name().as_ref() == b"style" => {
+                if let Some((id, Some(level))) = style.take() {
+                    levels.insert(id, level);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(levels)
+}
+
+#[derive(Default)]
+struct Paragraph {
+    text: String,
+    level: Option<usize>,
+    list: bool,
+}
+
+/// Reads `document.xml` in order, keeping headings, list items and tables.
+struct Body<'a> {
+    styles: &'a HashMap<String, usize>,
+    blocks: Vec<TextBlock>,
+    tables: Vec<Data>,
+    /// Text boxes hold paragraphs inside paragraphs
+    paragraphs: Vec<Paragraph>,
+    /// Rows of the tables being read, nested tables last
+    open_tables: Vec<Vec<Vec<String>>>,
+    heading: Option<String>,
+    in_text: bool,
+    /// Inside the fallback of content that has an alternative, which repeats its text
+    fallback: usize,
+}
+
+impl Body<'_> {
+    fn start(&mut self, element: &BytesStart) {
+        match element.local_name().as_ref() {
+            b"p" => self.paragraphs.push(Paragraph::default()),
+            b"t" => self.in_text = true,
+            b"numPr" => {
+                if let Some(paragraph) = self.paragraphs.last_mut() {
+                    paragraph.list = true;
+                }
+            }
+            b"tbl" => self.open_tables.push(vec![]),
+            b"tr" => {
+                if let Some(rows) = self.open_tables.last_mut() {
+                    rows.push(vec![]);
+                }
+            }
+            b"tc" => {
+                if let Some(row) = self.open_tables.last_mut().and_then(|rows| rows.last_mut()) {
+                    row.push(String::new());
+                }
+            }
+            b"Fallback" => self.fallback += 1,
+            _ => self.empty(element),
+        }
+    }
+
+    fn empty(&mut self, element: &BytesStart) {
+        let Some(paragraph) = self.paragraphs.last_mut() else {
+            return;
+        };
+        match element.local_name().as_ref() {
+            b"pStyle" => {
+                paragraph.level = attribute(element, b"val").and_then(|style| {
+                    self.styles
+                        .get(&style)
+                        .copied()
+                        .or_else(|| style_level(&style))
+                });
+            }
+            b"outlineLvl" if paragraph.level.is_none() => {
+                paragraph.level = attribute(element, b"val")
+                    .as_deref()
+                    .and_then(outline_level);
+            }
+            b"tab" | b"br" => paragraph.text.push(' '),
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if !self.in_text || self.fallback > 0 {
+            return;
+        }
+        if let Some(paragraph) = self.paragraphs.last_mut() {
+            paragraph.text.push_str(text);
+        }
+    }
+
+    /// Adds text to the cell being read, separated from what it already holds.
+    fn cell_text(&mut self, text: &str) -> bool {
+        let Some(cell) = self
+            .open_tables
+            .last_mut()
+            .and_then(|rows| rows.last_mut())
+            .and_then(|row| row.last_mut())
+        else {
+            return false;
+        };
+        if !cell.is_empty() {
+            cell.push(' ');
+        }
+        cell.push_str(text);
+        true
+    }
+
+    fn end(&mut self, name: &[u8]) {
+        match name {
+            b"t" => self.in_text = false,
+            b"Fallback" => self.fallback = self.fallback.saturating_sub(1),
+            b"p" => {
+                let Some(paragraph) = self.paragraphs.pop() else {
+                    return;
+                };
+                let text = paragraph
+                    .text
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if text.is_empty() || self.cell_text(&text) {
+                    return;
+                }
+                let text = match (paragraph.level, paragraph.list) {
+                    (Some(level), _) => {
+                        self.heading = Some(text.clone());
+                        format!("{} {}", "#".repeat(level), text)
+                    }
+                    (None, true) => format!("- {}", text),
+                    (None, false) => text,
+                };
+                self.blocks.push(TextBlock::new(text));
+            }
+            b"tbl" => {
+                let Some(rows) = self.open_tables.pop() else {
+                    return;
+                };
+                let rows: Vec<Vec<String>> = rows
+                    .into_iter()
+                    .filter(|row| row.iter().any(|cell| !cell.is_empty()))
+                    .collect();
+                // A table inside a cell is kept as the text of the cell
+                if !self.open_tables.is_empty() {
+                    let text = rows.iter().flatten().cloned().collect::<Vec<_>>().join(" ");
+                    self.cell_text(&text);
+                    return;
+                }
+                let width = rows.iter().map(|row| row.len()).max().unwrap_or_default();
+                if width == 0 {
+                    return;
+                }
+                let rows: Vec<Vec<String>> = rows
+                    .into_iter()
+                    .map(|mut row| {
+                        row.resize(width, String::new());
+                        row
+                    })
+                    .collect();
+                self.blocks.push(TextBlock::new(markdown_table(&rows)));
+                let title = self
+                    .heading
+                    .clone()
+                    .unwrap_or_else(|| format!("Table {}", self.tables.len() + 1));
+                if let Some(data) = rows_data(rows, title, None) {
+                    self.tables.push(data);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Turns the body of the document into Markdown blocks and tables, tables are titled by the
+/// heading above them.
+fn structure(xml: &str, styles: &HashMap<String, usize>) -> Result<(Vec<TextBlock>, Vec<Data>)> {
+    let mut reader = Reader::from_str(xml);
+    let mut body = Body {
+        styles,
+        blocks: vec![],
+        tables: vec![],
+        paragraphs: vec![],
+        open_tables: vec![],
+        heading: None,
+        in_text: false,
+        fallback: 0,
+    };
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => body.start(&element),
+            Event::Empty(element) => body.empty(&element),
+            Event::Text(text) => body.text(&text.unescape()?),
+            Event::End(element) => body.end(element.local_name().as_ref()),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((body.blocks, body.tables))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extractors::ColumnType;
+
+    #[tokio::test]
+    async fn test_extract() {
+        let file = RawDocument::new(
+            "report.docx",
+            None,
+            include_bytes!("../../tests/sample.docx").to_vec(),
+        );
+        let document = DocxExtractor.extract(&file).await.unwrap();
+        assert_eq!(
+            document.metadata.title.as_deref(),
+            Some("Q3 Business Review")
+        );
+        assert_eq!(document.metadata.author.as_deref(), Some("Finance Team"));
+        assert_eq!(
+            document.metadata.date,
+            chrono::NaiveDate::from_ymd_opt(2024, 10, 28)
+        );
+
+        let text = document.text();
+        assert!(text.contains("# Q3 Business Review"));
+        assert!(text.contains("## Segment results"));
+        assert!(text.contains("- Cloud revenue grew 24% & margins widened."));
+        // Text in the fallback of a text box isn't repeated
+        assert_eq!(text.matches("Record quarter").count(), 1);
+        assert!(text.contains("| Segment | Revenue |"));
+
+        let [table] = document.tables.as_slice() else {
+            panic!("Expected one table, got {}", document.tables.len());
+        };
+        assert_eq!(table.title, "Segment results");
+        assert_eq!(table.columns[0].values, ["Cloud", "Devices"]);
+        assert_eq!(table.columns[1].kind, ColumnType::Currency);
+    }
+
+    #[test]
+    fn test_style_level() {
+        assert_eq!(style_level("heading 2"), Some(2));
+        assert_eq!(style_level("Heading1"), Some(1));
+        assert_eq!(style_level("Title"), Some(1));
+        assert_eq!(style_level("Normal"), None);
+        assert_eq!(style_level("Heading"), None);
+    }
+
+    #[test]
+    fn test_outline_levels() {
+        let styles = r#"<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+            <w:style w:styleId="Kop2"><w:name w:val="Kop 2"/><w:pPr><w:outlineLvl w:val="1"/></w:pPr></w:style>
+            <w:style w:styleId="Body"><w:name w:val="Body Text"/><w:pPr><w:outlineLvl w:val="9"/></w:pPr></w:style>
+            <w:style w:styleId="Deep"><w:name w:val="Deep"/><w:pPr><w:outlineLvl w:val="8"/></w:pPr></w:style>
+        </w:styles>"#;
+        let levels = heading_styles(styles).unwrap();
+        assert_eq!(levels.get("Kop2"), Some(&2));
+        assert_eq!(levels.get("Deep"), Some(&6));
+        assert_eq!(levels.get("Body"), None);
+        assert_eq!(outline_level("9"), None);
+        assert_eq!(outline_level("0"), Some(1));
+    }
+}
@@ -1,86 +1,332 @@
 use crate::prelude::*;
 use async_trait::async_trait;
-use calamine::{open_workbook, Reader, Xlsx};
-use std::io::Write;
-use tempfile::NamedTempFile;
+use calamine::{
+    open_workbook_auto_from_rs, Data as Cell, Dimensions, Range, Reader, SheetType, SheetVisible,
+    Sheets,
+};
+use chrono::NaiveTime;
+use log::debug;
+use std::io::{Cursor, Read, Seek};
 
-use super::{csv::CsvExtractor, Data, DataExtract};
+use super::{
+    table::{header_names, is_label, typed_column},
+    Column, Data, Document, Extract, RawDocument,
+};
 
+/// Most rows of stacked column headers above the data.
+const MAX_HEADER_ROWS: usize = 3;
+
+/// Extracts every table of every visible sheet of an `.xlsx`, `.xls`, `.xlsb` or `.ods`
+/// workbook.
 pub struct ExcelExtractor;
 
 #[async_trait]
-impl DataExtract for ExcelExtractor {
-    async fn extract(&self, file_path: &str) -> Result<Data> {
-        // Convert Excel to CSV
-        let mut workbook: Xlsx<_> = open_workbook(file_path)?;
-        // TODO: Fix error
-        let sheet_names = workbook.sheet_names();
-        let sheet_name = sheet_names
-            .first()
-            .ok_or(FinanalizeError::InternalServerError)?;
-        let range = workbook.worksheet_range(sheet_name)?;
-
-        // Create a temporary CSV file
-        let mut temp_csv = NamedTempFile::new()?;
-        for row in range.rows() {
-            let row_str = row
+impl Extract for ExcelExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &[
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "application/vnd.ms-excel.sheet.macroenabled.12",
+            "application/vnd.ms-excel.sheet.binary.macroenabled.12",
+            "application/vnd.ms-excel",
+            "application/vnd.oasis.opendocument.spreadsheet",
+        ]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["xlsx", "xlsm", "xlsb", "xls", "ods"]
+    }
+
+    async fn extract(&self, file: &RawDocument) -> Result<Document> {
+        // Downloads don't always have the right extension, so the format is detected
+        let mut workbook = open_workbook_auto_from_rs(Cursor::new(file.body.clone()))?;
+        let sheets: Vec<String> = workbook
+            .sheets_metadata()
+            .iter()
+            .filter(|sheet| {
+                sheet.typ == SheetType::WorkSheet && sheet.visible == SheetVisible::Visible
+            })
+            .map(|sheet| sheet.name.clone())
+            .collect();
+        let mut tables = vec![];
+        for sheet in sheets {
+            let range = workbook.worksheet_range(&sheet)?;
+            let merged = merged_cells(&mut workbook, &sheet);
+            tables.extend(sheet_tables(&sheet, grid(&range, &merged)));
+        }
+        debug!("Extracted {} tables from {}", tables.len(), file.name);
+        if tables.is_empty() {
+            return Err(FinanalizeError::NotFound);
+        }
+        Ok(Document {
+            tables,
+            ..Default::default()
+        })
+    }
+}
+
+fn merged_cells<RS: Read + Seek>(workbook: &mut Sheets<RS>, sheet: &str) -> Vec<Dimensions> {
+    match workbook {
+        Sheets::Xlsx(xlsx) => xlsx
+            .worksheet_merge_cells(sheet)
+            .and_then(|cells| cells.ok())
+            .unwrap_or_default(),
+        Sheets::Xls(xls) => xls.worksheet_merge_cells(sheet).unwrap_or_default(),
+        // The xlsb and ods readers don't expose merged cells
+        _ => vec![],
+    }
+}
+
+/// Formats a cell by its type, with dates in ISO 8601 and numbers without formatting.
+fn cell_text(cell: &Cell) -> String {
+    match cell {
+        Cell::Int(value) => value.to_string(),
+        Cell::Float(value) => value.to_string(),
+        Cell::String(value) => value.split_whitespace().collect::<Vec<_>>().join(" "),
+        Cell::Bool(value) => value.to_string(),
+        Cell::DateTime(value) => match value.as_datetime() {
+            Some(datetime) if value.is_datetime() && datetime.time() == NaiveTime::MIN => {
+                datetime.date().to_string()
+            }
+            Some(datetime) if value.is_datetime() => {
+                datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+            }
+            _ => value.as_f64().to_string(),
+        },
+        Cell::DateTimeIso(value) | Cell::DurationIso(value) => value.clone(),
+        Cell::Error(_) | Cell::Empty => String::new(),
+    }
+}
+
+/// The cells of a sheet as text, with merged cells repeating their value over the region.
+fn grid(range: &Range<Cell>, merged: &[Dimensions]) -> Vec<Vec<String>> {
+    let (top, left) = range.start().unwrap_or_default();
+    let (bottom, right) = range.end().unwrap_or_default();
+    let mut rows: Vec<Vec<String>> = range
+        .rows()
+        .map(|row| row.iter().map(cell_text).collect())
+        .collect();
+    for region in merged {
+        let Some(value) = range.get_value(region.start).map(cell_text) else {
+            continue;
+        };
+        // Regions can be declared far past the cells, even over the whole sheet
+        for row in region.start.0..=region.end.0.min(bottom) {
+            for column in region.start.1..=region.end.1.min(right) {
+                let cell = row
+                    .checked_sub(top)
+                    .zip(column.checked_sub(left))
+                    .and_then(|(row, column)| rows.get_mut(row as usize)?.get_mut(column as usize));
+                if let Some(cell) = cell {
+                    cell.clone_from(&value);
+                }
+            }
+        }
+    }
+    rows
+}
+
+/// The text of a row spanning a single value, such as a title or a note.
+fn single_value(row: &[String]) -> Option<&str> {
+    let mut values = row.iter().filter(|cell| !cell.is_empty());
+    let first = values.next()?;
+    values.all(|value| value == first).then_some(first.as_str())
+}
+
+/// Splits a sheet into tables at its empty rows. A title row above a table, or a block of
+/// titles before it, names the table and notes below it describe it.
+fn sheet_tables(sheet: &str, rows: Vec<Vec<String>>) -> Vec<Data> {
+    let mut blocks: Vec<Vec<Vec<String>>> = vec![];
+    let mut previous_empty = true;
+    for row in rows {
+        let empty = row.iter().all(|cell| cell.is_empty());
+        if !empty {
+            if previous_empty {
+                blocks.push(vec![]);
+            }
+            if let Some(block) = blocks.last_mut() {
+                block.push(row);
+            }
+        }
+        previous_empty = empty;
+    }
+
+    let mut tables = vec![];
+    let mut titles: Vec<String> = vec![];
+    for mut block in blocks {
+        // Columns left empty, such as spacers between labels and data, are dropped
+        let width = block.iter().map(|row| row.len()).max().unwrap_or_default();
+        let filled: Vec<usize> = (0..width)
+            .filter(|&i| {
+                block
+                    .iter()
+                    .any(|row| row.get(i).is_some_and(|c| !c.is_empty()))
+            })
+            .collect();
+        for row in block.iter_mut() {
+            *row = filled
                 .iter()
-                .map(|cell| cell.to_string())
-                .collect::<Vec<_>>()
-                .join(",");
-            writeln!(temp_csv, "{}", row_str)?;
+                .map(|&i| row.get(i).cloned().unwrap_or_default())
+                .collect();
+        }
+        // A lone value is a title, in a wider block it spans the table
+        let lone = block.len() == 1;
+        let is_note = |row: &Vec<String>| {
+            (lone || filled.len() > 1) && single_value(row).is_some_and(is_label)
+        };
+
+        let leading = block.iter().take_while(|row| is_note(row)).count();
+        titles.extend(
+            block
+                .drain(..leading)
+                .map(|row| single_value(&row).unwrap_or_default().to_string()),
+        );
+        if block.is_empty() {
+            continue;
+        }
+        let mut notes = vec![];
+        while block.last().is_some_and(is_note) {
+            let row = block.pop().unwrap();
+            notes.insert(0, single_value(&row).unwrap_or_default().to_string());
+        }
+
+        let labels = block
+            .iter()
+            .take_while(|row| row.iter().all(|cell| cell.is_empty() || is_label(cell)))
+            .count();
+        let header_len = if labels >= block.len() {
+            1
+        } else {
+            labels.min(MAX_HEADER_ROWS)
+        };
+        let (header, body) = block.split_at(header_len.min(block.len() - 1));
+        let context = titles
+            .iter()
+            .chain(&notes)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let columns: Vec<Column> = header_names(header, filled.len())
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                let values: Vec<String> = body.iter().map(|row| row[i].clone()).collect();
+                if values.iter().all(|value| value.is_empty()) {
+                    return None;
+                }
+                Some(typed_column(name, values, &context))
+            })
+            .collect();
+        if columns.is_empty() {
+            titles.clear();
+            continue;
+        }
+
+        let mut titles = std::mem::take(&mut titles).into_iter();
+        let title = titles
+            .next()
+            .unwrap_or_else(|| format!("{} table {}", sheet, tables.len() + 1));
+        tables.push(Data {
+            title,
+            description: titles.chain(notes).collect::<Vec<_>>().join("\n"),
+            columns,
+            source: None,
+            page: None,
+        });
+    }
+    // A sheet holding one untitled table is named after the sheet
+    if let [table] = tables.as_mut_slice() {
+        if table.title == format!("{} table 1", sheet) {
+            table.title = sheet.to_string();
         }
-        debug!("Temp CSV file: {}", temp_csv.path().display());
+    }
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_extract() {
+        let file = RawDocument::new(
+            "sample.xlsx",
+            None,
+            include_bytes!("../../tests/sample.xlsx").to_vec(),
+        );
+        let tables = ExcelExtractor.extract(&file).await.unwrap().tables;
+        let titles: Vec<&str> = tables.iter().map(|t| t.title.as_str()).collect();
+        // The hidden sheet is left out
+        assert_eq!(titles, ["Revenue by segment", "Segments table 2", "Prices"]);
+
+        let segments = &tables[0];
+        let names: Vec<&str> = segments.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["Segment", "2023 H1", "2023 H2", "2024 H1", "2024 H2"]
+        );
+        assert_eq!(segments.columns[0].values, ["iPhone, iPad", "Services"]);
+        assert_eq!(segments.columns[1].values, ["100.5", "40"]);
+        assert_eq!(segments.description, "Figures in USD billions");
+
+        let prices = &tables[2];
+        assert_eq!(prices.columns[0].values, ["2024-01-01", "2024-01-02"]);
+        assert_eq!(prices.columns[1].values, ["185.64", "184.25"]);
+    }
+
+    #[test]
+    fn test_grid_merged_cells() {
+        let mut range = Range::new((2, 1), (3, 3));
+        range.set_value((2, 1), Cell::String("Segment".into()));
+        range.set_value((2, 2), Cell::Int(2024));
+        range.set_value((3, 2), Cell::Float(1.5));
+        range.set_value((3, 3), Cell::Bool(true));
+        let merged = [
+            Dimensions::new((2, 1), (3, 1)),
+            Dimensions::new((2, 2), (2, 3)),
+        ];
+        assert_eq!(
+            grid(&range, &merged),
+            [
+                row(&["Segment", "2024", "2024"]),
+                row(&["Segment", "1.5", "true"])
+            ]
+        );
+        // A merge over the whole sheet only covers the cells that exist
+        let merged = [Dimensions::new((2, 1), (1_048_575, 16_383))];
+        assert_eq!(
+            grid(&range, &merged),
+            [
+                row(&["Segment", "Segment", "Segment"]),
+                row(&["Segment", "Segment", "Segment"])
+            ]
+        );
+    }
 
-        // Use CsvExtractor to process the CSV file
-        let csv_extractor = CsvExtractor;
-        csv_extractor
-            .extract(temp_csv.path().to_str().unwrap())
-            .await
+    #[test]
+    fn test_sheet_tables() {
+        let rows = vec![
+            row(&["Quarterly results", "", ""]),
+            row(&["", "", ""]),
+            row(&["Quarter", "", "Revenue"]),
+            row(&["Q1", "", "$1,200"]),
+            row(&["Q2", "", "$1,350"]),
+            row(&["", "", ""]),
+            row(&["Name", "City", ""]),
+            row(&["Alice", "New York", ""]),
+            row(&["Bob", "Chicago", ""]),
+        ];
+        let tables = sheet_tables("Summary", rows);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].title, "Quarterly results");
+        assert_eq!(tables[0].columns[1].name, "Revenue");
+        assert_eq!(tables[0].columns[1].values, ["1200", "1350"]);
+        assert_eq!(tables[0].columns[1].description, "Values in USD");
+        // A table of text has a single header row
+        assert_eq!(tables[1].title, "Summary table 2");
+        assert_eq!(tables[1].columns[1].values, ["New York", "Chicago"]);
     }
 }
-//
-// #[cfg(test)]
-// mod tests {
-//     use std::fs::File;
-//
-//     use super::*;
-//     use tempfile::tempdir;
-//
-//     #[tokio::test]
-//     async fn test_extract() {
-//         // Create a temporary directory
-//         let dir = tempdir().unwrap();
-//         let file_path = dir.path().join("sample.xlsx");
-//
-//         // Write sample Excel data to the file
-//         let mut workbook =
-//             calamine::Xlsx::<std::fs::File>::new(File::create(&file_path).unwrap()).unwrap();
-//         let mut sheet = workbook.new_sheet("Sheet1").unwrap();
-//         sheet.write_string(0, 0, "name").unwrap();
-//         sheet.write_string(0, 1, "age").unwrap();
-//         sheet.write_string(0, 2, "city").unwrap();
-//         sheet.write_string(1, 0, "Alice").unwrap();
-//         sheet.write_string(1, 1, "30").unwrap();
-//         sheet.write_string(1, 2, "New York").unwrap();
-//         sheet.write_string(2, 0, "Bob").unwrap();
-//         sheet.write_string(2, 1, "25").unwrap();
-//         sheet.write_string(2, 2, "Los Angeles").unwrap();
-//         sheet.write_string(3, 0, "Charlie").unwrap();
-//         sheet.write_string(3, 1, "35").unwrap();
-//         sheet.write_string(3, 2, "Chicago").unwrap();
-//         workbook.close().unwrap();
-//
-//         // Create an instance of ExcelExtractor
-//         let extractor = ExcelExtractor;
-//
-//         // Call the extract function
-//         let result = extractor.extract(file_path.to_str().unwrap()).await;
-//
-//         // Assert that the result is Ok
-//         assert!(result.is_ok());
-//
-//         // Clean up the temporary directory
-//         dir.close().unwrap();
-//     }
-// }
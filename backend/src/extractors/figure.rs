@@ -1,67 +1,72 @@
 use super::Figure; // Import the existing Figure struct
-use crate::prelude::*;
-use async_trait::async_trait;
-use scraper::{Html, Selector};
-use tokio::task;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
 
-use super::Content;
-use super::ContentExtract;
-use super::FileType;
+/// The URL of an image, preferring the attributes lazy loading scripts read over a
+/// placeholder in `src`.
+fn image_url(img: ElementRef<'_>) -> Option<&str> {
+    let img = img.value();
+    let srcset = img
+        .attr("data-srcset")
+        .or(img.attr("srcset"))
+        // The last candidate is usually the largest
+        .and_then(|srcset| srcset.rsplit(',').next())
+        .and_then(|candidate| candidate.split_whitespace().next());
+    ["data-src", "data-original", "src"]
+        .into_iter()
+        .filter_map(|attribute| img.attr(attribute))
+        .chain(srcset)
+        .map(str::trim)
+        .find(|url| !url.is_empty() && !url.starts_with("data:"))
+}
 
-pub struct FigureExtractor;
+/// The `<figure>`s of a document with their image, alt text and caption. Relative image URLs
+/// are resolved against `page_url`, which is kept as the figure's source.
+pub fn figures(document: &Html, page_url: Option<&str>) -> Vec<Figure> {
+    let figure_selector = Selector::parse("figure").unwrap();
+    let img_selector = Selector::parse("img").unwrap();
+    let caption_selector = Selector::parse("figcaption").unwrap();
+    let base = page_url.and_then(|url| Url::parse(url).ok());
 
-#[async_trait]
-impl ContentExtract for FigureExtractor {
-    async fn extract(&self, file: FileType) -> Result<Vec<Content>> {
-        let FileType::Html(input) = file else {
-            return Err(FinanalizeError::ParseError(
-                "Invalid input type".to_string(),
-            ));
+    let mut figures = Vec::new();
+    for figure_element in document.select(&figure_selector) {
+        let Some(img) = figure_element.select(&img_selector).next() else {
+            continue; // Continue to the next iteration if no image is found
         };
 
-        let extracted_figures = task::spawn_blocking(move || {
-            let document = Html::parse_document(&input);
-            let figure_selector = Selector::parse("figure")?;
-            let img_selector = Selector::parse("img")?;
-            let caption_selector = Selector::parse("figcaption")?;
-
-            let mut figures = Vec::new();
-
-            for figure_element in document.select(&figure_selector) {
-                let Some(img) = figure_element.select(&img_selector).next() else {
-                    continue; // Continue to the next iteration if no image is found
-                };
-
-                let Some(url) = img.value().attr("src") else {
-                    continue; // Skip figures without source URLs
-                };
-
-                let alt_text = img.value().attr("alt").map(String::from);
-                let caption = figure_element
-                    .select(&caption_selector)
-                    .next()
-                    .map(|caption| caption.text().collect::<Vec<_>>().join(" "));
-
-                figures.push(Figure {
-                    url: url.to_string(),
-                    alt_text,
-                    caption,
-                });
-            }
+        let Some(url) = image_url(img) else {
+            continue; // Skip figures without source URLs
+        };
+        let url = match &base {
+            Some(base) => match base.join(url) {
+                Ok(url) => url.to_string(),
+                Err(_) => continue,
+            },
+            None => url.to_string(),
+        };
 
-            Ok(figures) as Result<Vec<Figure>>
-        })
-        .await??;
+        let alt_text = img.value().attr("alt").map(String::from);
+        let caption = figure_element
+            .select(&caption_selector)
+            .next()
+            .map(|caption| caption.text().collect::<Vec<_>>().join(" "));
 
-        Ok(vec![Content::Figures(extracted_figures)])
+        figures.push(Figure {
+            url,
+            alt_text,
+            caption,
+            source: page_url.map(String::from),
+        });
     }
+    figures
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[tokio::test]
-    async fn test_figure_extraction() {
+    #[test]
+    fn test_figure_extraction() {
         let test_html = r#"
         <html>
             <body>
@@ -78,31 +83,42 @@ mod tests {
         </html>
         "#;
 
-        let file = FileType::Html(test_html.to_string());
-        let extractor = FigureExtractor;
+        let figures = figures(&Html::parse_document(test_html), None);
+        assert_eq!(figures.len(), 2);
+        assert_eq!(figures[0].url, "image1.jpg");
+        assert_eq!(figures[0].alt_text, Some("Image 1 Description".to_string()));
+        assert_eq!(figures[0].caption, Some("Figure 1 Caption".to_string()));
+        assert_eq!(figures[1].url, "image2.png");
+        assert_eq!(figures[1].alt_text, None);
+        assert_eq!(figures[1].caption, Some("Figure 2 Caption".to_string()));
+        assert!(
+            figures.iter().all(|f| !f.url.is_empty()),
+            "All figures must have a valid URL"
+        );
+    }
 
-        match extractor.extract(file).await {
-            Ok(result) => {
-                println!("Extraction result: {:?}", result);
-                if let Content::Figures(figures) = &result[0] {
-                    assert_eq!(figures.len(), 2);
-                    assert_eq!(figures[0].url, "image1.jpg");
-                    assert_eq!(figures[0].alt_text, Some("Image 1 Description".to_string()));
-                    assert_eq!(figures[0].caption, Some("Figure 1 Caption".to_string()));
-                    assert_eq!(figures[1].url, "image2.png");
-                    assert_eq!(figures[1].alt_text, None);
-                    assert_eq!(figures[1].caption, Some("Figure 2 Caption".to_string()));
-                    assert!(
-                        figures.iter().all(|f| !f.url.is_empty()),
-                        "All figures must have a valid URL"
-                    );
-                } else {
-                    panic!("Expected Content::Figures variant");
-                }
-            }
-            Err(err) => {
-                panic!("Test failed with error: {:?}", err);
-            }
-        }
+    #[test]
+    fn test_figures_resolve_lazy_images() {
+        let html = r#"
+        <figure>
+            <img src="data:image/gif;base64,R0lGOD" data-src="/charts/revenue.png" alt="Revenue">
+        </figure>
+        <figure>
+            <img srcset="/img/sales-480.jpg 480w, /img/sales-1200.jpg 1200w">
+            <figcaption>Sales by region</figcaption>
+        </figure>
+        "#;
+        let document = Html::parse_document(html);
+        let figures = figures(
+            &document,
+            Some("https://www.cnbc.com/2025/01/30/apple.html"),
+        );
+        assert_eq!(figures.len(), 2);
+        assert_eq!(figures[0].url, "https://www.cnbc.com/charts/revenue.png");
+        assert_eq!(
+            figures[0].source.as_deref(),
+            Some("https://www.cnbc.com/2025/01/30/apple.html")
+        );
+        assert_eq!(figures[1].url, "https://www.cnbc.com/img/sales-1200.jpg");
     }
 }
@@ -1,96 +1,98 @@
-use super::{Content, ContentExtract, FileType};
-use crate::extractors::figure::FigureExtractor; // Import FigureExtractor
-use crate::extractors::md::MarkdownExtractor;
+use super::{
+    date::published_date, figure::figures, readability::main_content, Document, Extract, Metadata,
+    RawDocument, TextBlock,
+};
 use crate::prelude::*;
 use async_trait::async_trait;
 use log::debug;
-use scraper::{ElementRef, Html, Selector};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{Html, Selector};
 use tokio::task;
 
 pub struct HTMLExtractor;
 
-#[async_trait]
-impl ContentExtract for HTMLExtractor {
-    async fn extract(&self, file: FileType) -> Result<Vec<Content>> {
-        debug!("Extracting content from HTML file");
-        // Ensure the input is of the correct type
-        let FileType::Html(input) = file else {
-            return Err(FinanalizeError::ParseError(
-                "Invalid input type".to_string(),
-            ));
-        };
-        debug!("Input type is HTML");
-        // Step 1: Extract figures using FigureExtractor
-        let figure_extractor = FigureExtractor;
-        let figure_content = figure_extractor
-            .extract(FileType::Html(input.clone()))
-            .await
-            .unwrap_or_else(|_| vec![]); // Safely handle errors by returning an empty vector
+static SPAN: Lazy<Regex> = Lazy::new(|| Regex::new("(?i)</?span[^>]*>").unwrap());
 
-        // Step 2: Extract text for Markdown conversion
-        let extracted_texts = task::spawn_blocking(move || {
-            debug!("Parsing HTML content");
-            let document = Html::parse_document(&input);
+/// The `content` of the first `<meta>` matching one of the selectors.
+fn meta(document: &Html, selectors: &[&str]) -> Option<String> {
+    selectors.iter().find_map(|selector| {
+        let selector = Selector::parse(selector).ok()?;
+        let content = document.select(&selector).next()?.value().attr("content")?;
+        Some(content.trim().to_string()).filter(|content| !content.is_empty())
+    })
+}
 
-            // Select all `div` and `article` elements
-            let selector = Selector::parse("div, article")
-                .map_err(|err| FinanalizeError::ParseError(format!("{:?}", err)))?;
+/// The title, author and publication date a page announces, the date falls back on the URL.
+pub fn metadata(document: &Html, url: &str) -> Metadata {
+    let title = meta(document, &[r#"meta[property="og:title"]"#]).or_else(|| {
+        let selector = Selector::parse("title").ok()?;
+        let title = document
+            .select(&selector)
+            .next()?
+            .text()
+            .collect::<String>();
+        Some(title.trim().to_string()).filter(|title| !title.is_empty())
+    });
+    Metadata {
+        title,
+        author: meta(
+            document,
+            &[
+                r#"meta[name="author"]"#,
+                r#"meta[property="article:author"]"#,
+            ],
+        ),
+        date: published_date(document, url),
+    }
+}
 
-            let mut texts = vec![];
+#[async_trait]
+impl Extract for HTMLExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["text/html", "application/xhtml+xml"]
+    }
 
-            for element in document.select(&selector) {
-                // Check if the element is NOT inside ignored tags
-                if !is_inside_ignored_section(element) {
-                    let text = element.text().collect::<Vec<_>>().join(" ");
-                    debug!("Extracted text: {}", text); // Debug log
-                    if !text.is_empty() {
-                        texts.push(text);
-                    }
-                } else {
-                    debug!("Ignored element: {:?}", element.value().name()); // Debug log
-                }
-            }
+    fn extensions(&self) -> &'static [&'static str] {
+        &["html", "htm"]
+    }
 
-            if texts.is_empty() {
-                return Err(FinanalizeError::NotFound);
+    async fn extract(&self, file: &RawDocument) -> Result<Document> {
+        debug!("Extracting content from HTML file");
+        let input = file.text();
+        let url = file.name.clone();
+        let document = task::spawn_blocking(move || {
+            debug!("Parsing HTML content");
+            let document = Html::parse_document(&input);
+            let metadata = metadata(&document, &url);
+            // Navigation, banners and related articles would drown out the article itself
+            let filtered = main_content(document);
+            // Only figures in the article itself, not thumbnails of other stories
+            let figures = figures(&Html::parse_fragment(&filtered), Some(&url));
+            let markdown = SPAN
+                .replace_all(&mdka::from_html(&filtered), "")
+                .to_string();
+            let markdown = markdown
+                .trim()
+                .lines()
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            Document {
+                metadata,
+                blocks: vec![TextBlock::new(markdown)],
+                tables: vec![],
+                figures,
             }
-
-            Ok(texts)
         })
         .await
-        .map_err(|_| FinanalizeError::InternalServerError)??;
-        debug!("Extracted texts: {:?}", extracted_texts);
-        let markdown_extractor = MarkdownExtractor;
-
-        // Convert extracted HTML content to Markdown using MarkdownExtractor
-        let markdown_content = markdown_extractor
-            .extract(FileType::MarkDown(extracted_texts.join("\n")))
-            .await
-            .unwrap_or_else(|_| vec![]); // Safely handle errors by returning an empty vector
-
-        // Combine figure content and markdown content
-        let mut result = figure_content;
-        result.extend(markdown_content);
-
-        Ok(result)
-    }
-}
-
-/// Function to check if an element is inside a header, nav, aside, or footer
-fn is_inside_ignored_section(element: ElementRef) -> bool {
-    let mut parent = element.parent();
-
-    while let Some(node) = parent {
-        if let Some(el) = ElementRef::wrap(node) {
-            match el.value().name() {
-                "nav" | "aside" | "header" | "footer" => return true,
-                _ => {}
-            }
+        .map_err(|_| FinanalizeError::InternalServerError)?;
+        debug!("Extracted markdown: {}", document.text());
+        if document.text().trim().is_empty() {
+            return Err(FinanalizeError::NotFound);
         }
-        parent = node.parent();
+        Ok(document)
     }
-
-    false
 }
 
 #[cfg(test)]
@@ -116,51 +118,23 @@ mod tests {
         </html>
         "#;
 
-        let file = FileType::Html(test_html.to_string());
-        let extractor = HTMLExtractor;
-
-        match extractor.extract(file).await {
-            Ok(result) => {
-                println!("Extraction result: {:?}", result);
-
-                // Ensure the result contains both figures and Markdown
-                assert!(result
-                    .iter()
-                    .any(|content| matches!(content, Content::Figures(_))));
-                assert!(result
-                    .iter()
-                    .any(|content| matches!(content, Content::MarkDown(_))));
+        let file = RawDocument::new("https://www.cnbc.com/apple.html", None, test_html.into());
+        let result = HTMLExtractor.extract(&file).await.unwrap();
 
-                // Check the figure content
-                if let Some(Content::Figures(figures)) = result
-                    .iter()
-                    .find(|content| matches!(content, Content::Figures(_)))
-                {
-                    assert_eq!(figures.len(), 1);
-                    let figure = &figures[0];
-                    assert_eq!(figure.url, "image1.jpg");
-                    assert_eq!(figure.alt_text.as_deref(), Some("Image 1"));
-                    assert_eq!(figure.caption.as_deref(), Some("Caption for Image 1"));
-                }
+        assert_eq!(result.figures.len(), 1);
+        let figure = &result.figures[0];
+        assert_eq!(figure.url, "https://www.cnbc.com/image1.jpg");
+        assert_eq!(figure.alt_text.as_deref(), Some("Image 1"));
+        assert_eq!(figure.caption.as_deref(), Some("Caption for Image 1"));
 
-                // Check the Markdown content
-                if let Some(Content::MarkDown(markdown)) = result
-                    .iter()
-                    .find(|content| matches!(content, Content::MarkDown(_)))
-                {
-                    assert!(markdown.contains("Main Content"));
-                    assert!(markdown.contains("Another Important Section"));
+        let markdown = result.text();
+        assert!(markdown.contains("Main Content"));
+        assert!(markdown.contains("Another Important Section"));
 
-                    // Ensure ignored sections are not included
-                    assert!(!markdown.contains("Header Title"));
-                    assert!(!markdown.contains("Navigation Links"));
-                    assert!(!markdown.contains("Sidebar Content"));
-                    assert!(!markdown.contains("Footer Information"));
-                }
-            }
-            Err(err) => {
-                panic!("Test failed with error: {:?}", err);
-            }
-        }
+        // Ensure ignored sections are not included
+        assert!(!markdown.contains("Header Title"));
+        assert!(!markdown.contains("Navigation Links"));
+        assert!(!markdown.contains("Sidebar Content"));
+        assert!(!markdown.contains("Footer Information"));
     }
 }
@@ -1,5 +1,4 @@
-use super::FileType;
-use super::{Content, ContentExtract};
+use super::{Document, Extract, Metadata, RawDocument, TextBlock};
 use crate::prelude::*;
 use async_trait::async_trait;
 use log::debug;
@@ -8,16 +7,28 @@ use tokio::task;
 
 pub struct MarkdownExtractor;
 
+/// The text of the first top level heading.
+fn title(markdown: &str) -> Option<String> {
+    markdown
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
 #[async_trait]
-impl ContentExtract for MarkdownExtractor {
-    async fn extract(&self, file: FileType) -> Result<Vec<Content>> {
+impl Extract for MarkdownExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["text/markdown", "text/x-markdown"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["md", "markdown"]
+    }
+
+    async fn extract(&self, file: &RawDocument) -> Result<Document> {
         debug!("Extracting Markdown content...");
-        let FileType::MarkDown(buffer) = file else {
-            return Err(FinanalizeError::ParseError(
-                "Invalid input type".to_string(),
-            ));
-        };
-        debug!("Received valid Markdown input");
+        let buffer = file.text();
 
         // Perform the HTML-to-Markdown conversion in a blocking thread
         let markdown = task::spawn_blocking(move || {
@@ -56,8 +67,14 @@ impl ContentExtract for MarkdownExtractor {
             return Err(FinanalizeError::NotFound);
         }
 
-        // Wrap the generated Markdown in `Content::MarkDown`
-        Ok(vec![Content::MarkDown(markdown)])
+        Ok(Document {
+            metadata: Metadata {
+                title: title(&markdown),
+                ..Default::default()
+            },
+            blocks: vec![TextBlock::new(markdown)],
+            ..Default::default()
+        })
     }
 }
 
@@ -97,35 +114,33 @@ mod tests {
         let extractor = MarkdownExtractor;
 
         let result = extractor
-            .extract(FileType::MarkDown(buffer))
+            .extract(&RawDocument::new("test.html", None, buffer.into()))
             .await
             .expect("Markdown extraction failed");
 
-        assert_eq!(result.len(), 1);
-        if let Content::MarkDown(markdown) = &result[0] {
-            assert!(
-                markdown.contains("# Title"),
-                "Markdown should contain '# Title'"
-            );
-            assert!(
-                markdown.contains("## Subtitle"),
-                "Markdown should contain '## Subtitle'"
-            );
-            assert!(
-                markdown.contains("- Item 1"),
-                "Markdown should contain '- Item 1'"
-            );
-            assert!(
-                markdown.contains("- Item 2"),
-                "Markdown should contain '- Item 2'"
-            );
-            assert!(
-                markdown.contains("This is a paragraph."),
-                "Markdown should contain the paragraph text"
-            );
-        } else {
-            panic!("Expected Content::MarkDown variant");
-        }
+        assert_eq!(result.blocks.len(), 1);
+        assert_eq!(result.metadata.title.as_deref(), Some("Title"));
+        let markdown = &result.blocks[0].text;
+        assert!(
+            markdown.contains("# Title"),
+            "Markdown should contain '# Title'"
+        );
+        assert!(
+            markdown.contains("## Subtitle"),
+            "Markdown should contain '## Subtitle'"
+        );
+        assert!(
+            markdown.contains("- Item 1"),
+            "Markdown should contain '- Item 1'"
+        );
+        assert!(
+            markdown.contains("- Item 2"),
+            "Markdown should contain '- Item 2'"
+        );
+        assert!(
+            markdown.contains("This is a paragraph."),
+            "Markdown should contain the paragraph text"
+        );
     }
 
     #[tokio::test]
@@ -135,16 +150,16 @@ mod tests {
         let extractor = MarkdownExtractor;
 
         let result = extractor
-            .extract(FileType::MarkDown(sample_text.to_string()))
+            .extract(&RawDocument::new("notes.md", None, sample_text.into()))
             .await
             .expect("Markdown extraction failed for plain text");
 
-        assert_eq!(result.len(), 1);
-        if let Content::MarkDown(markdown) = &result[0] {
-            assert_eq!(markdown, sample_text, "Plain text should be returned as-is");
-        } else {
-            panic!("Expected Content::MarkDown variant");
-        }
+        assert_eq!(result.blocks.len(), 1);
+        assert_eq!(
+            result.blocks[0].text, sample_text,
+            "Plain text should be returned as-is"
+        );
+        assert_eq!(result.metadata.title, None);
     }
 
     #[tokio::test]
@@ -154,7 +169,7 @@ mod tests {
         let extractor = MarkdownExtractor;
 
         let result = extractor
-            .extract(FileType::MarkDown(sample_text.to_string()))
+            .extract(&RawDocument::new("notes.md", None, sample_text.into()))
             .await;
 
         assert!(result.is_err(), "Empty input should return an error");
@@ -1,37 +1,106 @@
 use crate::prelude::*;
 use async_trait::async_trait;
+use chrono::NaiveDate;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub mod csv;
+pub mod date;
+pub mod docx;
+pub mod excel;
 pub mod figure;
 pub mod html;
 pub mod md;
+pub mod office;
 pub mod pdf;
+pub mod pptx;
+pub mod readability;
+pub mod registry;
+pub mod table;
 pub mod text;
 
+/// Reads one or more formats into a [`Document`]. Extractors are picked by the
+/// [`registry`], so a new format only needs an implementation and a registration.
 #[async_trait]
-pub trait ContentExtract {
-    async fn extract(&self, file_type: FileType) -> Result<Vec<Content>>;
+pub trait Extract: Send + Sync {
+    /// MIME types of the formats read, without parameters
+    fn mime_types(&self) -> &'static [&'static str];
+    /// File extensions of the formats read, lowercase and without the dot
+    fn extensions(&self) -> &'static [&'static str];
+    async fn extract(&self, file: &RawDocument) -> Result<Document>;
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub enum Content {
-    Html(String),
-    Text(String),
-    Figures(Vec<Figure>),
-    Csv(String),
-    MarkDown(String),
-    Pdf(Vec<u8>),
+/// A file as downloaded or uploaded, before extraction.
+#[derive(Debug, Clone, Default)]
+pub struct RawDocument {
+    /// URL or file name, figures are resolved against it and its extension can decide the
+    /// format
+    pub name: String,
+    /// `Content-Type` of the file, if known
+    pub mime: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl RawDocument {
+    pub fn new(name: impl Into<String>, mime: Option<&str>, body: Vec<u8>) -> Self {
+        RawDocument {
+            name: name.into(),
+            mime: mime.map(String::from),
+            body,
+        }
+    }
+
+    /// The body as text, replacing invalid UTF-8
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// What every extractor produces, whatever the format.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Document {
+    pub metadata: Metadata,
+    /// Text as Markdown, in reading order
+    pub blocks: Vec<TextBlock>,
+    pub tables: Vec<Data>,
+    pub figures: Vec<Figure>,
+}
+
+impl Document {
+    /// The text of every block, separated by blank lines
+    pub fn text(&self) -> String {
+        self.blocks
+            .iter()
+            .map(|block| block.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// What a document says about itself, such as the `<title>` of a page or the info
+/// dictionary of a PDF.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// When the document was published, or created when that is all it says
+    pub date: Option<NaiveDate>,
 }
-#[derive(Debug, Serialize, Deserialize)]
-pub enum FileType {
-    Html(String),
-    Text(String),
-    Figures(Vec<Figure>),
-    Csv(String),
-    MarkDown(String),
-    Pdf(Vec<u8>),
+
+/// A run of text, with the page it is on so it can be cited by page number.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextBlock {
+    pub text: String,
+    pub page: Option<u32>,
+}
+
+impl TextBlock {
+    pub fn new(text: impl Into<String>) -> Self {
+        TextBlock {
+            text: text.into(),
+            page: None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Clone, Deserialize)]
@@ -39,6 +108,9 @@ pub struct Figure {
     pub url: String,
     pub alt_text: Option<String>,
     pub caption: Option<String>,
+    /// URL of the page the figure was published on
+    #[serde(default)]
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, JsonSchema)]
@@ -46,16 +118,42 @@ pub struct Data {
     pub title: String,
     pub description: String,
     pub columns: Vec<Column>,
+    /// URL of the document the data was extracted from
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Page of the document the data was found on
+    #[serde(default)]
+    pub page: Option<u32>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct Column {
     pub name: String,
     pub description: String,
+    /// Cells as text, numbers without separators and symbols and dates in ISO 8601
     pub values: Vec<String>,
+    #[serde(default)]
+    pub kind: ColumnType,
+    /// Values as numbers multiplied by their scale, `None` for empty cells and text
+    #[serde(default)]
+    pub numbers: Vec<Option<f64>>,
+    /// The currency code of a currency column, or `%`
+    #[serde(default)]
+    pub unit: Option<String>,
+    /// What `values` are expressed in, such as `1e6` for a column in millions
+    #[serde(default)]
+    pub scale: Option<f64>,
 }
 
-#[async_trait]
-trait DataExtract {
-    async fn extract(&self, file: &str) -> Result<Data>;
+/// The type inferred from the values of a column.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    Integer,
+    Decimal,
+    Percentage,
+    Currency,
+    Date,
+    #[default]
+    Category,
 }
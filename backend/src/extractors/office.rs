@@ -0,0 +1,173 @@
+use std::{
+    collections::HashMap,
+    io::{Cursor, Read},
+};
+
+use chrono::{DateTime, NaiveDate};
+use quick_xml::{
+    escape::unescape,
+    events::{attributes::Attribute, BytesStart, Event},
+    Reader,
+};
+use zip::{result::ZipError, ZipArchive};
+
+use super::Metadata;
+use crate::prelude::*;
+
+/// Largest part of a package that is read, so a small archive can't inflate into gigabytes.
+const MAX_PART_SIZE: u64 = 64 * 1024 * 1024;
+
+/// An Office Open XML package, the zip archive behind `.docx` and `.pptx` files.
+pub type Package<'a> = ZipArchive<Cursor<&'a [u8]>>;
+
+pub fn package(body: &[u8]) -> Result<Package<'_>> {
+    Ok(ZipArchive::new(Cursor::new(body))?)
+}
+
+/// A part of the package as text, `None` when the package doesn't have it.
+pub fn part(package: &mut Package, name: &str) -> Result<Option<String>> {
+    let file = match package.by_name(name) {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let mut xml = String::new();
+    file.take(MAX_PART_SIZE).read_to_string(&mut xml)?;
+    Ok(Some(xml))
+}
+
+/// The value of an attribute with entities replaced.
+pub fn value(attribute: &Attribute) -> Option<String> {
+    let raw = String::from_utf8_lossy(&attribute.value);
+    unescape(&raw).ok().map(|value| value.into_owned())
+}
+
+/// The value of an attribute, matched by its name without the namespace prefix.
+pub fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attribute| attribute.key.local_name().as_ref() == name)
+        .and_then(|attribute| value(&attribute))
+}
+
+/// Resolves a relationship target against the folder of the part it belongs to.
+fn resolve(part: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut path: Vec<&str> = part.split('/').collect();
+    path.pop();
+    for segment in target.split('/') {
+        match segment {
+            ".." => {
+                path.pop();
+            }
+            "." | "" => {}
+            segment => path.push(segment),
+        }
+    }
+    path.join("/")
+}
+
+/// The parts a part refers to, by relationship id.
+pub fn relationships(package: &mut Package, part_name: &str) -> Result<HashMap<String, String>> {
+    let (folder, file) = part_name.rsplit_once('/').unwrap_or(("", part_name));
+    let rels = match folder {
+        "" => format!("_rels/{}.rels", file),
+        folder => format!("{}/_rels/{}.rels", folder, file),
+    };
+    let Some(xml) = part(package, &rels)? else {
+        return Ok(HashMap::new());
+    };
+    let mut reader = Reader::from_str(&xml);
+    let mut targets = HashMap::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"Relationship" =>
+            {
+                // Links to websites are relationships too
+                if attribute(&element, b"TargetMode").as_deref() == Some("External") {
+                    continue;
+                }
+                if let (Some(id), Some(target)) =
+                    (attribute(&element, b"Id"), attribute(&element, b"Target"))
+                {
+                    targets.insert(id, resolve(part_name, &target));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(targets)
+}
+
+/// Dates in the core properties are W3CDTF, usually with a time of day.
+fn core_date(date: &str) -> Option<NaiveDate> {
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(date) => Some(date.date_naive()),
+        Err(_) => NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok(),
+    }
+}
+
+/// The title, author and creation date in the core properties of the package.
+pub fn core_metadata(package: &mut Package) -> Result<Metadata> {
+    let Some(xml) = part(package, "docProps/core.xml")? else {
+        return Ok(Metadata::default());
+    };
+    let mut reader = Reader::from_str(&xml);
+    let mut metadata = Metadata::default();
+    let mut field = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => field = Some(element.local_name().as_ref().to_vec()),
+            Event::End(_) => field = None,
+            Event::Text(text) => {
+                let text = text.unescape()?.trim().to_string();
+                if text.is_empty() {
+                    continue;
+                }
+                match field.as_deref() {
+                    Some(b"title") => metadata.title = Some(text),
+                    Some(b"creator") => metadata.author = Some(text),
+                    Some(b"created") => metadata.date = core_date(&text),
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(
+            resolve("ppt/presentation.xml", "slides/slide1.xml"),
+            "ppt/slides/slide1.xml"
+        );
+        assert_eq!(
+            resolve("ppt/slides/slide1.xml", "../media/image1.png"),
+            "ppt/media/image1.png"
+        );
+        assert_eq!(
+            resolve("word/document.xml", "/word/styles.xml"),
+            "word/styles.xml"
+        );
+    }
+
+    #[test]
+    fn test_core_date() {
+        let date = NaiveDate::from_ymd_opt(2024, 11, 5);
+        assert_eq!(core_date("2024-11-05T09:30:00Z"), date);
+        assert_eq!(core_date("2024-11-05"), date);
+        assert_eq!(core_date("last week"), None);
+    }
+}
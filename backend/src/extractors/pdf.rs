@@ -1,55 +1,416 @@
-use std::io::{BufWriter, Write};
+use std::collections::{HashMap, HashSet};
 
-use super::{html::HTMLExtractor, Content, ContentExtract, FileType};
+use super::{
+    table::{markdown_table, rows_data},
+    ColumnType, Data, Document as Extracted, Extract, Metadata, RawDocument, TextBlock,
+};
 use crate::prelude::*;
 use async_trait::async_trait;
+use chrono::NaiveDate;
 use log::debug;
-use lopdf::Document;
-use pdf_extract::HTMLOutput;
-// Import Error for `lopdf`
+use lopdf::{Document, Object};
+use once_cell::sync::Lazy;
+use pdf_extract::{MediaBox, OutputDev, OutputError, Transform};
+use regex::Regex;
 use tokio::task;
 
 pub struct PdfExtractor;
 
+/// Share of the page height at the top and bottom where running headers and footers sit.
+const MARGIN: f64 = 0.1;
+
+/// Consecutive lines with several columns it takes to make a table.
+const MIN_TABLE_ROWS: usize = 3;
+
+static PAGE_NUMBER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^(page\s*)?\d+(\s*(of|/)\s*\d+)?$").unwrap());
+
+static BLANK_LINES: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n{3,}").unwrap());
+
+/// A character on the page, positioned from the top left corner.
+struct Glyph {
+    x: f64,
+    y: f64,
+    end: f64,
+    size: f64,
+    text: String,
+}
+
+/// Text on a line separated from the next span by a gap wider than a space.
+struct Span {
+    x: f64,
+    end: f64,
+    text: String,
+}
+
+struct Line {
+    y: f64,
+    size: f64,
+    spans: Vec<Span>,
+}
+
+impl Line {
+    fn text(&self) -> String {
+        self.spans
+            .iter()
+            .map(|span| span.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+struct Page {
+    number: u32,
+    height: f64,
+    glyphs: Vec<Glyph>,
+    lines: Vec<Line>,
+}
+
+/// Collects the characters of every page along with their position and size.
+#[derive(Default)]
+struct Layout {
+    pages: Vec<Page>,
+}
+
+impl OutputDev for Layout {
+    fn begin_page(
+        &mut self,
+        page_num: u32,
+        media_box: &MediaBox,
+        _: Option<(f64, f64, f64, f64)>,
+    ) -> std::result::Result<(), OutputError> {
+        self.pages.push(Page {
+            number: page_num,
+            height: media_box.ury - media_box.lly,
+            glyphs: vec![],
+            lines: vec![],
+        });
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> std::result::Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn output_character(
+        &mut self,
+        trm: &Transform,
+        width: f64,
+        _spacing: f64,
+        font_size: f64,
+        char: &str,
+    ) -> std::result::Result<(), OutputError> {
+        let Some(page) = self.pages.last_mut() else {
+            return Ok(());
+        };
+        if char.trim().is_empty() {
+            return Ok(());
+        }
+        // The text matrix scales the font, take the side of a square of the same area
+        let size = font_size * (trm.m11 * trm.m22 - trm.m12 * trm.m21).abs().sqrt();
+        page.glyphs.push(Glyph {
+            x: trm.m31,
+            y: page.height - trm.m32,
+            end: trm.m31 + width * size,
+            size,
+            text: char.into(),
+        });
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> std::result::Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> std::result::Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> std::result::Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+/// Groups glyphs into lines by their baseline, and lines into spans by the gaps between them.
+fn lines(mut glyphs: Vec<Glyph>) -> Vec<Line> {
+    glyphs.sort_by(|a, b| a.y.total_cmp(&b.y));
+    let mut rows: Vec<Vec<Glyph>> = vec![];
+    for glyph in glyphs {
+        match rows.last_mut() {
+            Some(row) if (glyph.y - row[0].y).abs() <= row[0].size.max(glyph.size) * 0.5 => {
+                row.push(glyph)
+            }
+            _ => rows.push(vec![glyph]),
+        }
+    }
+    rows.into_iter()
+        .map(|mut row| {
+            row.sort_by(|a, b| a.x.total_cmp(&b.x));
+            let size = row.iter().map(|glyph| glyph.size).fold(0.0, f64::max);
+            let y = row[0].y;
+            let mut spans: Vec<Span> = vec![];
+            for glyph in row {
+                match spans.last_mut() {
+                    // Wider than a space is a new column
+                    Some(span) if glyph.x - span.end < size => {
+                        if glyph.x - span.end > size * 0.15 {
+                            span.text.push(' ');
+                        }
+                        span.text.push_str(&glyph.text);
+                        span.end = span.end.max(glyph.end);
+                    }
+                    _ => spans.push(Span {
+                        x: glyph.x,
+                        end: glyph.end,
+                        text: glyph.text,
+                    }),
+                }
+            }
+            Line { y, size, spans }
+        })
+        .collect()
+}
+
+/// Drops page numbers, and headers and footers repeated across pages.
+fn remove_running_lines(pages: &mut [Page]) {
+    let in_margin =
+        |line: &Line, height: f64| line.y < height * MARGIN || line.y > height * (1.0 - MARGIN);
+    // Page numbers change from page to page, so compare the lines without digits
+    let key = |line: &Line| line.text().to_lowercase().replace(char::is_numeric, "#");
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for page in pages.iter() {
+        let keys: HashSet<String> = page
+            .lines
+            .iter()
+            .filter(|line| in_margin(line, page.height))
+            .map(key)
+            .collect();
+        for key in keys {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+    let repeated = (pages.len() / 2).max(2);
+    for page in pages.iter_mut() {
+        let height = page.height;
+        page.lines.retain(|line| {
+            !in_margin(line, height)
+                || !(counts[&key(line)] >= repeated || PAGE_NUMBER.is_match(line.text().trim()))
+        });
+    }
+}
+
+/// The most common font size, weighted by the amount of text set in it.
+fn body_size(pages: &[Page]) -> f64 {
+    let mut sizes: HashMap<i64, usize> = HashMap::new();
+    for line in pages.iter().flat_map(|page| page.lines.iter()) {
+        *sizes.entry((line.size * 2.0).round() as i64).or_default() += line.text().len();
+    }
+    sizes
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(size, _)| size as f64 / 2.0)
+        .unwrap_or(10.0)
+}
+
+/// Splits a run of lines into columns at the gaps no span on any line crosses.
+fn table_rows(lines: &[Line]) -> Vec<Vec<String>> {
+    let mut intervals: Vec<(f64, f64)> = lines
+        .iter()
+        .flat_map(|line| line.spans.iter().map(|span| (span.x, span.end)))
+        .collect();
+    intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut columns: Vec<(f64, f64)> = vec![];
+    for (start, end) in intervals {
+        match columns.last_mut() {
+            Some(column) if start <= column.1 => column.1 = column.1.max(end),
+            _ => columns.push((start, end)),
+        }
+    }
+    lines
+        .iter()
+        .map(|line| {
+            let mut cells = vec![String::new(); columns.len()];
+            for span in &line.spans {
+                let column = columns
+                    .iter()
+                    .position(|(start, end)| span.x >= *start && span.x <= *end)
+                    .unwrap_or(0);
+                if !cells[column].is_empty() {
+                    cells[column].push(' ');
+                }
+                cells[column].push_str(&span.text);
+            }
+            cells
+        })
+        .collect()
+}
+
+/// Turns the lines of every page into Markdown with headings and tables.
+fn structure(pages: Vec<Page>) -> (Vec<TextBlock>, Vec<Data>) {
+    let body = body_size(&pages);
+    let mut texts = vec![];
+    let mut tables = vec![];
+    let mut heading = None;
+    for page in pages {
+        let mut text = String::new();
+        let mut lines = page.lines.as_slice();
+        let mut last_y = None;
+        while let Some(line) = lines.first() {
+            // Runs of lines with several columns are tables, when the columns line up
+            let run = lines.iter().take_while(|line| line.spans.len() > 1).count();
+            if run >= MIN_TABLE_ROWS {
+                let rows = table_rows(&lines[..run]);
+                let title = heading
+                    .clone()
+                    .unwrap_or_else(|| format!("Table on page {}", page.number));
+                // Two column prose lines up as well, only runs with figures are tables
+                let data = rows_data(rows.clone(), title, Some(page.number)).filter(|data| {
+                    data.columns
+                        .iter()
+                        .any(|column| column.kind != ColumnType::Category)
+                });
+                match data {
+                    Some(data) => {
+                        text.push_str(&format!("\n{}\n", markdown_table(&rows)));
+                        tables.push(data);
+                    }
+                    // Prose set in columns is read a column at a time
+                    None => {
+                        let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+                        for column in 0..columns {
+                            text.push('\n');
+                            for cell in rows.iter().filter_map(|row| row.get(column)) {
+                                if !cell.is_empty() {
+                                    text.push_str(cell);
+                                    text.push('\n');
+                                }
+                            }
+                        }
+                    }
+                }
+                last_y = lines[run - 1].y.into();
+                lines = &lines[run..];
+                continue;
+            }
+
+            let content = line.text();
+            if last_y.is_some_and(|y| line.y - y > line.size * 1.8) {
+                text.push('\n');
+            }
+            if line.size >= body * 1.2 && content.len() < 150 {
+                let level = if line.size >= body * 1.6 { "#" } else { "##" };
+                text.push_str(&format!("\n{} {}\n\n", level, content));
+                heading = Some(content);
+            } else {
+                text.push_str(&content);
+                text.push('\n');
+            }
+            last_y = Some(line.y);
+            lines = &lines[1..];
+        }
+        let text = BLANK_LINES.replace_all(text.trim(), "\n\n").to_string();
+        if !text.is_empty() {
+            texts.push(TextBlock {
+                text,
+                page: Some(page.number),
+            });
+        }
+    }
+    (texts, tables)
+}
+
+/// Decodes a PDF text string, which is UTF-16 when it starts with a byte order mark.
+fn text_string(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFE, 0xFF, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => bytes.iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+/// Turns a PDF date such as `D:20240927163005-04'00'` into `2024-09-27`.
+fn pdf_date(date: &str) -> Option<NaiveDate> {
+    let digits = date.strip_prefix("D:").unwrap_or(date);
+    let year = digits.get(0..4)?;
+    let month = digits.get(4..6).unwrap_or("01");
+    let day = digits.get(6..8).unwrap_or("01");
+    let date = format!("{}-{}-{}", year, month, day);
+    NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()
+}
+
+/// The title, author and creation date in the info dictionary of the document.
+fn metadata(doc: &Document) -> Metadata {
+    let Some(info) = doc
+        .trailer
+        .get_deref(b"Info", doc)
+        .and_then(Object::as_dict)
+        .ok()
+    else {
+        return Metadata::default();
+    };
+    let field = |key: &[u8]| {
+        let value = text_string(info.get_deref(key, doc).and_then(Object::as_str).ok()?);
+        Some(value.trim().to_string()).filter(|value| !value.is_empty())
+    };
+    Metadata {
+        title: field(b"Title"),
+        author: field(b"Author"),
+        date: field(b"CreationDate").and_then(|date| pdf_date(&date)),
+    }
+}
+
 #[async_trait]
-impl ContentExtract for PdfExtractor {
-    async fn extract(&self, file: FileType) -> Result<Vec<Content>> {
+impl Extract for PdfExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/pdf"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pdf"]
+    }
+
+    async fn extract(&self, file: &RawDocument) -> Result<Extracted> {
         debug!("Extracting content from PDF...");
-        let FileType::Pdf(buffer) = file else {
-            return Err(FinanalizeError::ParseError(
-                "Invalid input type".to_string(),
-            ));
-        };
+        let buffer = file.body.clone();
         debug!("Valid PDF buffer received: {} bytes", buffer.len());
 
         // Perform PDF extraction in a blocking thread
-        let html_content = task::spawn_blocking(move || {
+        let (metadata, pages, tables) = task::spawn_blocking(move || {
             // Load the PDF document from the buffer using lopdf's `load_from` function
             let doc = Document::load_mem(&buffer)?;
             debug!("PDF document loaded successfully");
-            // Create a buffer to write into
-            let mut buffer = Vec::new();
-
-            // Wrap the buffer with BufWriter
-            {
-                let mut writer = BufWriter::new(&mut buffer);
-                let mut output = HTMLOutput::new(&mut writer);
-                pdf_extract::output_doc(&doc, &mut output)?;
-                writer.flush()?;
+            let metadata = metadata(&doc);
+            let mut layout = Layout::default();
+            pdf_extract::output_doc(&doc, &mut layout)?;
+            for page in layout.pages.iter_mut() {
+                page.lines = lines(std::mem::take(&mut page.glyphs));
             }
-            let output = String::from_utf8(buffer)?;
-            debug!("Text extracted successfully");
-            Ok::<_, FinanalizeError>(output) // Return the HTML-wrapped extracted text
+            remove_running_lines(&mut layout.pages);
+            let (pages, tables) = structure(layout.pages);
+            Ok::<_, FinanalizeError>((metadata, pages, tables))
         })
         .await??;
 
-        let result = HTMLExtractor.extract(FileType::Html(html_content)).await?;
+        if pages.is_empty() {
+            // Scanned documents have no text layer
+            return Err(FinanalizeError::NoTextLayer(file.name.clone()));
+        }
         debug!(
-            "Content extracted successfully from PDF: {} chunks",
-            result.len()
+            "Content extracted successfully from PDF: {} pages, {} tables",
+            pages.len(),
+            tables.len()
         );
-        // Return the extracted HTML content wrapped in Content::Html
-        Ok(result)
+        Ok(Extracted {
+            metadata,
+            blocks: pages,
+            tables,
+            figures: vec![],
+        })
     }
 }
 
@@ -66,10 +427,172 @@ mod tests {
 
         // Extract the content from the PDF
         let result = extractor
-            .extract(FileType::Pdf(sample_pdf_data.to_vec()))
+            .extract(&RawDocument::new(
+                "sample.pdf",
+                None,
+                sample_pdf_data.to_vec(),
+            ))
             .await
             .unwrap();
 
-        assert_eq!(result.len(), 2);
+        let pages = &result.blocks;
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].page, Some(1));
+        assert!(pages[0]
+            .text
+            .starts_with("# Sample PDF\n\n## This is a simple PDF"));
+    }
+
+    #[test]
+    fn test_metadata_strings() {
+        assert_eq!(text_string(b"Form 10-K"), "Form 10-K");
+        assert_eq!(text_string(b"\xFE\xFF\x00A\x00p\x00p\x00l\x00e"), "Apple");
+        assert_eq!(
+            pdf_date("D:20240927163005-04'00'"),
+            NaiveDate::from_ymd_opt(2024, 9, 27)
+        );
+        assert_eq!(pdf_date("D:2024"), NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(pdf_date("yesterday"), None);
+    }
+
+    fn line(y: f64, size: f64, spans: &[(f64, &str)]) -> Line {
+        Line {
+            y,
+            size,
+            spans: spans
+                .iter()
+                .map(|(x, text)| Span {
+                    x: *x,
+                    end: x + text.len() as f64 * size * 0.5,
+                    text: text.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn page(number: u32, lines: Vec<Line>) -> Page {
+        Page {
+            number,
+            height: 800.0,
+            glyphs: vec![],
+            lines,
+        }
+    }
+
+    #[test]
+    fn test_lines() {
+        let glyph = |x: f64, y: f64, text: &str| Glyph {
+            x,
+            y,
+            end: x + 5.0,
+            size: 10.0,
+            text: text.into(),
+        };
+        let lines = lines(vec![
+            glyph(17.0, 100.5, "b"),
+            glyph(10.0, 100.0, "a"),
+            glyph(24.0, 100.0, "c"),
+            glyph(200.0, 100.0, "1"),
+            glyph(10.0, 120.0, "d"),
+        ]);
+        assert_eq!(lines.len(), 2);
+        let texts: Vec<_> = lines[0].spans.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["a b c", "1"]);
+        assert_eq!(lines[1].text(), "d");
+    }
+
+    #[test]
+    fn test_structure() {
+        let mut pages: Vec<Page> = (1..=3)
+            .map(|number| {
+                page(
+                    number,
+                    vec![
+                        line(20.0, 8.0, &[(50.0, "Apple Inc. | 2024 Form 10-K")]),
+                        line(
+                            100.0,
+                            10.0,
+                            &[(50.0, "Apple designs and sells smartphones.")],
+                        ),
+                        line(780.0, 8.0, &[(300.0, &number.to_string())]),
+                    ],
+                )
+            })
+            .collect();
+        pages[1].lines.splice(
+            1..1,
+            vec![
+                line(60.0, 16.0, &[(50.0, "Net sales by category")]),
+                line(
+                    80.0,
+                    10.0,
+                    &[(50.0, "Category"), (200.0, "2024"), (300.0, "2023")],
+                ),
+                line(
+                    92.0,
+                    10.0,
+                    &[(50.0, "iPhone"), (195.0, "$201,183"), (295.0, "$200,583")],
+                ),
+                line(
+                    104.0,
+                    10.0,
+                    &[(50.0, "Services"), (197.0, "96,169"), (297.0, "85,200")],
+                ),
+                line(
+                    116.0,
+                    10.0,
+                    &[(50.0, "Change"), (205.0, "(1)"), (305.0, "3")],
+                ),
+            ],
+        );
+        remove_running_lines(&mut pages);
+        let (texts, tables) = structure(pages);
+
+        assert_eq!(texts.len(), 3);
+        assert_eq!(texts[0].text, "Apple designs and sells smartphones.");
+        assert_eq!(texts[1].page, Some(2));
+        assert!(texts[1]
+            .text
+            .starts_with("# Net sales by category\n\n| Category | 2024 | 2023 |"));
+        assert!(!texts[1].text.contains("10-K"));
+
+        assert_eq!(tables.len(), 1);
+        let data = &tables[0];
+        assert_eq!(data.title, "Net sales by category");
+        assert_eq!(data.page, Some(2));
+        let names: Vec<_> = data.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Category", "2024", "2023"]);
+        assert_eq!(data.columns[1].values, vec!["201183", "96169", "-1"]);
+        assert_eq!(data.columns[1].description, "Values in USD");
+    }
+
+    #[test]
+    fn test_two_column_prose() {
+        let paragraphs = [
+            ("Apple designs and sells", "Services revenue grew"),
+            ("smartphones, computers", "on the back of a larger"),
+            ("and wearables around", "installed base and higher"),
+            ("the world.", "subscription prices."),
+        ];
+        let lines = paragraphs
+            .iter()
+            .enumerate()
+            .map(|(i, (left, right))| {
+                line(
+                    100.0 + i as f64 * 12.0,
+                    10.0,
+                    &[(50.0, left), (320.0, right)],
+                )
+            })
+            .collect();
+        let (texts, tables) = structure(vec![page(1, lines)]);
+        assert!(tables.is_empty());
+        assert!(!texts[0].text.contains('|'));
+        assert_eq!(
+            texts[0].text,
+            "Apple designs and sells\nsmartphones, computers\nand wearables around\nthe world.\n\n\
+             Services revenue grew\non the back of a larger\ninstalled base and higher\n\
+             subscription prices."
+        );
     }
 }
@@ -0,0 +1,262 @@
+use async_trait::async_trait;
+use log::debug;
+use quick_xml::{events::Event, Reader};
+use tokio::task;
+
+use super::{
+    office::{attribute, core_metadata, package, part, relationships, value},
+    table::{markdown_table, rows_data},
+    Document, Extract, RawDocument, TextBlock,
+};
+use crate::prelude::*;
+
+const PRESENTATION: &str = "ppt/presentation.xml";
+
+/// Extracts the titles, text and tables of every slide of a PowerPoint presentation, slides
+/// are kept as pages so they can be cited by number.
+pub struct PptxExtractor;
+
+#[async_trait]
+impl Extract for PptxExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &[
+            "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+            "application/vnd.ms-powerpoint.presentation.macroenabled.12",
+        ]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pptx", "pptm"]
+    }
+
+    async fn extract(&self, file: &RawDocument) -> Result<Document> {
+        debug!("Extracting content from presentation...");
+        let body = file.body.clone();
+        let name = file.name.clone();
+        let document = task::spawn_blocking(move || read(&body, &name)).await??;
+        debug!(
+            "Extracted {} slides and {} tables from {}",
+            document.blocks.len(),
+            document.tables.len(),
+            file.name
+        );
+        if document.blocks.is_empty() {
+            return Err(FinanalizeError::NotFound);
+        }
+        Ok(document)
+    }
+}
+
+/// What is on a slide, in the order of its shapes.
+#[derive(Debug, Default)]
+struct Slide {
+    hidden: bool,
+    title: Option<String>,
+    paragraphs: Vec<String>,
+    tables: Vec<Vec<Vec<String>>>,
+}
+
+fn read(body: &[u8], name: &str) -> Result<Document> {
+    let mut package = package(body)?;
+    let presentation = part(&mut package, PRESENTATION)?
+        .ok_or_else(|| FinanalizeError::UnsupportedDocument(name.to_string()))?;
+    let targets = relationships(&mut package, PRESENTATION)?;
+    let mut metadata = core_metadata(&mut package)?;
+
+    let mut blocks = vec![];
+    let mut tables = vec![];
+    // Slides are numbered as they are shown, hidden ones included
+    for (i, id) in slide_ids(&presentation)?.iter().enumerate() {
+        let number = i as u32 + 1;
+        let Some(xml) = targets.get(id).map(|path| part(&mut package, path)) else {
+            continue;
+        };
+        let Some(slide) = xml?.map(|xml| slide(&xml)).transpose()? else {
+            continue;
+        };
+        if slide.hidden {
+            continue;
+        }
+        if metadata.title.is_none() {
+            metadata.title = slide.title.clone();
+        }
+
+        let mut text = vec![];
+        if let Some(title) = &slide.title {
+            text.push(format!("## {}", title));
+        }
+        text.extend(slide.paragraphs);
+        for (j, rows) in slide.tables.into_iter().enumerate() {
+            text.push(markdown_table(&rows));
+            let title = match &slide.title {
+                Some(title) if j == 0 => title.clone(),
+                Some(title) => format!("{} table {}", title, j + 1),
+                None => format!("Table on slide {}", number),
+            };
+            if let Some(data) = rows_data(rows, title, Some(number)) {
+                tables.push(data);
+            }
+        }
+        if !text.is_empty() {
+            blocks.push(TextBlock {
+                text: text.join("\n"),
+                page: Some(number),
+            });
+        }
+    }
+    Ok(Document {
+        metadata,
+        blocks,
+        tables,
+        ..Default::default()
+    })
+}
+
+/// Relationship ids of the slides, in the order they are shown.
+fn slide_ids(xml: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut ids = vec![];
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"sldId" =>
+            {
+                // The slide has a numeric `id` too, the relationship is the prefixed one
+                let id = element
+                    .attributes()
+                    .flatten()
+                    .find(|a| a.key.prefix().is_some() && a.key.local_name().as_ref() == b"id")
+                    .and_then(|a| value(&a));
+                ids.extend(id);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(ids)
+}
+
+/// Reads the text of the shapes and tables of a slide, the title placeholder is its title.
+fn slide(xml: &str) -> Result<Slide> {
+    let mut reader = Reader::from_str(xml);
+    let mut slide = Slide::default();
+    let mut is_title = false;
+    let mut shape: Vec<String> = vec![];
+    let mut paragraph = String::new();
+    let mut table: Option<Vec<Vec<String>>> = None;
+    let mut in_text = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"sld" => slide.hidden = attribute(&element, b"show").as_deref() == Some("0"),
+                b"sp" => {
+                    is_title = false;
+                    shape.clear();
+                }
+                b"ph" => {
+                    let kind = attribute(&element, b"type");
+                    is_title = matches!(kind.as_deref(), Some("title" | "ctrTitle"));
+                }
+                b"p" => paragraph.clear(),
+                b"t" => in_text = true,
+                b"tbl" => table = Some(vec![]),
+                b"tr" => {
+                    if let Some(rows) = table.as_mut() {
+                        rows.push(vec![]);
+                    }
+                }
+                b"tc" => {
+                    if let Some(row) = table.as_mut().and_then(|rows| rows.last_mut()) {
+                        row.push(String::new());
+                    }
+                }
+                _ => {}
+            },
+            Event::Empty(element) => match element.local_name().as_ref() {
+                b"ph" => {
+                    let kind = attribute(&element, b"type");
+                    is_title = matches!(kind.as_deref(), Some("title" | "ctrTitle"));
+                }
+                b"br" => paragraph.push(' '),
+                _ => {}
+            },
+            Event::Text(text) if in_text => paragraph.push_str(&text.unescape()?),
+            Event::End(element) => match element.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => {
+                    let text = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let cell = table
+                        .as_mut()
+                        .and_then(|rows| rows.last_mut())
+                        .and_then(|row| row.last_mut());
+                    match cell {
+                        Some(cell) if cell.is_empty() => cell.push_str(&text),
+                        Some(cell) => cell.push_str(&format!(" {}", text)),
+                        None => shape.push(text),
+                    }
+                }
+                b"sp" if is_title => {
+                    slide.title = Some(shape.join(" ")).filter(|title| !title.is_empty());
+                }
+                b"sp" => slide.paragraphs.append(&mut shape),
+                b"tbl" => {
+                    let rows: Vec<Vec<String>> = table
+                        .take()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|row| row.iter().any(|cell| !cell.is_empty()))
+                        .collect();
+                    if !rows.is_empty() {
+                        slide.tables.push(rows);
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(slide)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extractors::ColumnType;
+
+    #[tokio::test]
+    async fn test_extract() {
+        let file = RawDocument::new(
+            "https://investors.example.com/q3-presentation.pptx",
+            None,
+            include_bytes!("../../tests/sample.pptx").to_vec(),
+        );
+        let document = PptxExtractor.extract(&file).await.unwrap();
+        // The core properties have no title, so the first slide names the deck
+        assert_eq!(document.metadata.title.as_deref(), Some("Q3 2024 Results"));
+        assert_eq!(
+            document.metadata.date,
+            chrono::NaiveDate::from_ymd_opt(2024, 10, 30)
+        );
+
+        // The hidden second slide is skipped but still counted
+        let pages: Vec<Option<u32>> = document.blocks.iter().map(|b| b.page).collect();
+        assert_eq!(pages, [Some(1), Some(3)]);
+        assert!(document.blocks[0].text.starts_with("## Q3 2024 Results\n"));
+        assert!(document.blocks[0]
+            .text
+            .contains("Revenue up 12% year over year"));
+        assert!(!document.text().contains("Backup"));
+
+        let [table] = document.tables.as_slice() else {
+            panic!("Expected one table, got {}", document.tables.len());
+        };
+        assert_eq!(table.title, "Regional revenue");
+        assert_eq!(table.page, Some(3));
+        assert_eq!(table.columns[0].values, ["Americas", "EMEA"]);
+        assert_eq!(table.columns[1].kind, ColumnType::Currency);
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use ego_tree::NodeId;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{node::Element, ElementRef, Html, Selector};
+
+/// Class and id fragments of blocks that are never part of the article.
+static UNLIKELY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)cookie|consent|gdpr|onetrust|banner|related|recommend|read-?more|more-?stories|share|social|newsletter|subscribe|signup|promo|sponsor|advert|\bad-|-ad\b|\bads?\b|sidebar|comment|breadcrumb|menu|navbar|popup|modal|masthead|footer|header|disclaimer",
+    )
+    .unwrap()
+});
+
+/// Class and id fragments of blocks that likely hold the article.
+static LIKELY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)article|body|content|entry|main|post|story|text").unwrap());
+
+/// Tags whose content is never readable text.
+const REMOVED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "form", "button", "input", "select",
+    "svg", "canvas", "nav", "aside", "header", "footer", "dialog",
+];
+
+/// ARIA roles of landmarks around the article.
+const REMOVED_ROLES: &[&str] = &[
+    "navigation",
+    "banner",
+    "contentinfo",
+    "complementary",
+    "dialog",
+    "alertdialog",
+    "search",
+];
+
+/// Tags whose text scores their ancestors as article candidates.
+const SCORED_TAGS: &str = "p, pre, td, li, blockquote";
+
+/// Shortest paragraph that counts towards a candidate's score.
+const MIN_PARAGRAPH_LEN: usize = 25;
+
+/// Extracts the main content of a page as HTML, without navigation, banners and other
+/// boilerplate, keeping its headings and lists.
+///
+/// Paragraphs score their parent and grandparent by their length and number of commas, the
+/// candidate with the highest score after discounting its links wins, and siblings that score
+/// close to it are kept alongside it. Falls back to the whole cleaned body when no paragraph
+/// is long enough to score.
+pub fn main_content(mut document: Html) -> String {
+    remove_unlikely(&mut document);
+    let scores = score_candidates(&document);
+    let best = scores
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .and_then(|(id, _)| document.tree.get(*id).and_then(ElementRef::wrap));
+    let Some(best) = best else {
+        let body = Selector::parse("body").unwrap();
+        return document.select(&body).next().map(clean).unwrap_or_default();
+    };
+
+    let mut parts = vec![];
+    // The headline is often outside the article body
+    let h1 = Selector::parse("h1").unwrap();
+    if best.select(&h1).next().is_none() {
+        if let Some(headline) = document.select(&h1).next_back() {
+            parts.push(headline.html());
+        }
+    }
+    let threshold = (scores[&best.id()] * 0.2).max(10.0);
+    let siblings = best
+        .parent()
+        .map(|parent| parent.children().filter_map(ElementRef::wrap).collect())
+        .unwrap_or_else(|| vec![best]);
+    for sibling in siblings {
+        let keep = sibling == best
+            || scores
+                .get(&sibling.id())
+                .is_some_and(|score| *score >= threshold)
+            || (sibling.value().name() == "p"
+                && text_len(sibling) > 80
+                && link_density(sibling) < 0.25);
+        if keep {
+            parts.push(clean(sibling));
+        }
+    }
+    parts.join("\n")
+}
+
+fn class_and_id(element: &Element) -> String {
+    format!(
+        "{} {}",
+        element.attr("class").unwrap_or_default(),
+        element.attr("id").unwrap_or_default()
+    )
+}
+
+fn is_unlikely(element: ElementRef) -> bool {
+    let name = element.value().name();
+    if matches!(name, "html" | "body" | "main" | "article") {
+        return false;
+    }
+    // The header of an article holds its headline and byline
+    if name == "header"
+        && element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| ancestor.value().name() == "article")
+    {
+        return false;
+    }
+    let element = element.value();
+    if REMOVED_TAGS.contains(&name)
+        || element
+            .attr("role")
+            .is_some_and(|role| REMOVED_ROLES.contains(&role))
+        || element.attr("aria-modal") == Some("true")
+        || element.attr("hidden").is_some()
+    {
+        return true;
+    }
+    let class_and_id = class_and_id(element);
+    UNLIKELY.is_match(&class_and_id) && !LIKELY.is_match(&class_and_id)
+}
+
+/// Detaches scripts, landmarks and blocks such as cookie banners and related articles.
+fn remove_unlikely(document: &mut Html) {
+    let unlikely: Vec<_> = document
+        .tree
+        .nodes()
+        .filter(|node| ElementRef::wrap(*node).is_some_and(is_unlikely))
+        .map(|node| node.id())
+        .collect();
+    for id in unlikely {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+}
+
+fn text_len(element: ElementRef) -> usize {
+    element.text().map(|text| text.trim().chars().count()).sum()
+}
+
+/// The share of an element's text that is inside links.
+fn link_density(element: ElementRef) -> f64 {
+    let total = text_len(element);
+    if total == 0 {
+        return 0.0;
+    }
+    let links = Selector::parse("a").unwrap();
+    let linked: usize = element.select(&links).map(text_len).sum();
+    linked as f64 / total as f64
+}
+
+fn initial_score(element: &Element) -> f64 {
+    let tag = match element.name() {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "ol" | "ul" | "dl" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let class_and_id = class_and_id(element);
+    let mut weight = 0.0;
+    if LIKELY.is_match(&class_and_id) {
+        weight += 25.0;
+    }
+    if UNLIKELY.is_match(&class_and_id) {
+        weight -= 25.0;
+    }
+    tag + weight
+}
+
+/// Scores the parents and grandparents of paragraphs, scaled down by their link density.
+fn score_candidates(document: &Html) -> HashMap<NodeId, f64> {
+    let paragraphs = Selector::parse(SCORED_TAGS).unwrap();
+    let mut scores: HashMap<_, f64> = HashMap::new();
+    for paragraph in document.select(&paragraphs) {
+        let text: String = paragraph.text().collect();
+        let len = text.trim().chars().count();
+        if len < MIN_PARAGRAPH_LEN {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (len as f64 / 100.0).min(3.0);
+        let ancestors = paragraph.ancestors().filter_map(ElementRef::wrap).take(2);
+        for (level, ancestor) in ancestors.enumerate() {
+            if matches!(ancestor.value().name(), "html" | "body") {
+                break;
+            }
+            let entry = scores
+                .entry(ancestor.id())
+                .or_insert_with(|| initial_score(ancestor.value()));
+            *entry += score / (level + 1) as f64;
+        }
+    }
+    for (id, score) in scores.iter_mut() {
+        if let Some(element) = document.tree.get(*id).and_then(ElementRef::wrap) {
+            *score *= 1.0 - link_density(element);
+        }
+    }
+    scores
+}
+
+/// Drops the link lists, such as related articles, left inside the extracted content.
+fn clean(element: ElementRef) -> String {
+    let mut fragment = Html::parse_fragment(&element.html());
+    let blocks = Selector::parse("div, section, ul, ol, table").unwrap();
+    let junk: Vec<_> = fragment
+        .select(&blocks)
+        .filter(|block| {
+            let len = text_len(*block);
+            let density = link_density(*block);
+            // Lists of links are navigation, short blocks of mostly links are teasers
+            (len == 0 && block.value().name() != "table")
+                || density > 0.5
+                || (density > 0.2 && len < 200 && !matches!(block.value().name(), "ul" | "ol"))
+        })
+        .map(|block| block.id())
+        .collect();
+    for id in junk {
+        if let Some(mut node) = fragment.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+    fragment.root_element().inner_html()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(html: &str) -> String {
+        mdka::from_html(&main_content(Html::parse_document(html)))
+    }
+
+    #[test]
+    fn test_main_content_nbcboston() {
+        let md = extract(include_str!("../../tests/scraped/nbcboston.html"));
+        assert!(md.contains(
+            "# Apple shares rise 3% as boost in services revenue overshadows iPhone miss"
+        ));
+        assert!(md.contains("closely watched iPhone sales declined"));
+        // Cookie banner, navigation and related stories
+        assert!(!md.contains("OneTrust"));
+        assert!(!md.contains("Skip to content"));
+        assert!(!md.contains("ibm-rallies-heads-for-best-day-ever"));
+    }
+
+    #[test]
+    fn test_main_content_tbrc() {
+        let md = extract(include_str!("../../tests/scraped/tbrc.html"));
+        assert!(md.contains("# Apples Market Analysis"));
+        assert!(md.contains("1) By Type: Red Apple, Granny Smith And Golden Apples"));
+        // Related posts, comment form and sidebar
+        assert!(!md.contains("Key Highlights of the Greenhouse, Nursery, And Flowers Market"));
+        assert!(!md.contains("Leave a Reply"));
+        assert!(!md.contains("Recent Posts"));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::prelude::*;
+use log::debug;
+use once_cell::sync::Lazy;
+use reqwest::Url;
+
+use super::{
+    csv::CsvExtractor, docx::DocxExtractor, excel::ExcelExtractor, html::HTMLExtractor,
+    md::MarkdownExtractor, pdf::PdfExtractor, pptx::PptxExtractor, text::TextExtractor, Document,
+    Extract, RawDocument,
+};
+
+/// Every extractor, picked by the MIME type of a document or else its extension.
+pub static EXTRACTORS: Lazy<Registry> = Lazy::new(|| {
+    Registry::new()
+        .register(HTMLExtractor)
+        .register(PdfExtractor)
+        .register(CsvExtractor)
+        .register(ExcelExtractor)
+        .register(DocxExtractor)
+        .register(PptxExtractor)
+        .register(MarkdownExtractor)
+        .register(TextExtractor)
+});
+
+#[derive(Default)]
+pub struct Registry {
+    extractors: Vec<Box<dyn Extract>>,
+}
+
+/// The extension of the last path segment of a URL or file name, lowercase.
+fn extension(name: &str) -> Option<String> {
+    let path = match Url::parse(name) {
+        Ok(url) => url.path().to_string(),
+        Err(_) => name.to_string(),
+    };
+    let file = path.rsplit(['/', '\\']).next()?;
+    let (_, extension) = file.rsplit_once('.')?;
+    Some(extension.to_lowercase())
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an extractor, the first one registered for a type wins.
+    pub fn register(mut self, extractor: impl Extract + 'static) -> Self {
+        self.extractors.push(Box::new(extractor));
+        self
+    }
+
+    pub fn for_mime(&self, mime: &str) -> Option<&dyn Extract> {
+        let mime = mime.split(';').next()?.trim().to_lowercase();
+        self.extractors
+            .iter()
+            .find(|extractor| extractor.mime_types().contains(&mime.as_str()))
+            .map(Box::as_ref)
+    }
+
+    pub fn for_extension(&self, name: &str) -> Option<&dyn Extract> {
+        let extension = extension(name)?;
+        self.extractors
+            .iter()
+            .find(|extractor| extractor.extensions().contains(&extension.as_str()))
+            .map(Box::as_ref)
+    }
+
+    /// The extractor for the MIME type of the document, or else for its extension.
+    pub fn find(&self, file: &RawDocument) -> Option<&dyn Extract> {
+        file.mime
+            .as_deref()
+            .and_then(|mime| self.for_mime(mime))
+            .or_else(|| self.for_extension(&file.name))
+    }
+
+    pub fn supports(&self, file: &RawDocument) -> bool {
+        self.find(file).is_some()
+    }
+
+    pub async fn extract(&self, file: &RawDocument) -> Result<Document> {
+        let Some(extractor) = self.find(file) else {
+            return Err(FinanalizeError::UnsupportedDocument(file.name.clone()));
+        };
+        debug!(
+            "Extracting {} ({})",
+            file.name,
+            file.mime.as_deref().unwrap_or("unknown type")
+        );
+        extractor.extract(file).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let mime_types = |file: &RawDocument| EXTRACTORS.find(file).map(|e| e.mime_types()[0]);
+        // The MIME type beats the extension
+        let file = RawDocument::new("https://a.com/data.csv", Some("application/pdf"), vec![]);
+        assert_eq!(mime_types(&file), Some("application/pdf"));
+        let file = RawDocument::new(
+            "https://a.com/Model.XLSX?download=1",
+            Some("application/octet-stream"),
+            vec![],
+        );
+        assert_eq!(
+            mime_types(&file),
+            Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
+        );
+        let file = RawDocument::new("https://a.com/Q3%20deck.pptx", None, vec![]);
+        assert_eq!(
+            mime_types(&file),
+            Some("application/vnd.openxmlformats-officedocument.presentationml.presentation")
+        );
+        let file = RawDocument::new("notes.md", None, vec![]);
+        assert_eq!(mime_types(&file), Some("text/markdown"));
+        let file = RawDocument::new("https://a.com/", Some("Text/HTML; charset=utf-8"), vec![]);
+        assert_eq!(mime_types(&file), Some("text/html"));
+        assert!(!EXTRACTORS.supports(&RawDocument::new("logo.png", Some("image/png"), vec![])));
+    }
+
+    #[tokio::test]
+    async fn test_extract_unsupported() {
+        let file = RawDocument::new("logo.png", Some("image/png"), vec![]);
+        assert!(matches!(
+            EXTRACTORS.extract(&file).await,
+            Err(FinanalizeError::UnsupportedDocument(name)) if name == "logo.png"
+        ));
+    }
+}
@@ -0,0 +1,766 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+
+use super::{Column, ColumnType, Data};
+
+/// Rows or columns a single cell may span, larger values are treated as typos.
+const MAX_SPAN: usize = 100;
+
+/// Share of a column's non-empty cells that must be numbers or dates for it to be typed.
+const TYPED_SHARE: f64 = 0.6;
+
+/// Date formats tried in order, month first as in US filings.
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%m/%d/%Y",
+    "%d/%m/%Y",
+    "%d.%m.%Y",
+    "%b %d, %Y",
+    "%B %d, %Y",
+    "%d %b %Y",
+    "%d %B %Y",
+];
+
+static CURRENCY_CODE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?:(USD|EUR|GBP|JPY|CHF|CNY|CAD|AUD|INR)\s*)?(.*?)(?:\s*(USD|EUR|GBP|JPY|CHF|CNY|CAD|AUD|INR))?$").unwrap()
+});
+
+static YEAR: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:FY\s?)?(?:19|20)\d{2}$").unwrap());
+
+static DIGITS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d+(?:\.\d+)?$").unwrap());
+
+/// A scale written after a number, as in `1.2bn` or `350 million`.
+static SCALE_SUFFIX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(.*\d)\s*(k|m|mn|mm|bn|b|tn|thousand|million|billion|trillion)$").unwrap()
+});
+
+/// A scale written in a header or title, as in `(in millions)`, `$bn` or `USD m`. Single
+/// letters only count after a currency, as `(b)` is usually a footnote.
+static SCALE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(thousands|millions|billions|trillions)\b|'000|\(000s?\)|(?:[$€£¥]|\b(?:USD|EUR|GBP|JPY|CHF|CNY|CAD|AUD|INR))\s?(k|m|mn|mm|bn|b|tn|t)\b|\((mn|mm|bn|tn)\)").unwrap()
+});
+
+/// A number parsed from a table cell, with the unit and scale written next to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Number {
+    pub value: f64,
+    /// A currency code or `%`
+    pub unit: Option<String>,
+    /// The multiplier of a suffix such as `bn`
+    pub scale: Option<f64>,
+}
+
+/// Strips parentheses or a leading minus, which both mark a negative number.
+fn strip_sign(text: &mut String, negative: &mut bool) {
+    if text.starts_with('(') && text.ends_with(')') {
+        *negative = !*negative;
+        *text = text[1..text.len() - 1].trim().to_string();
+    }
+    if let Some(rest) = text.strip_prefix(['-', '−', '–']) {
+        *negative = !*negative;
+        *text = rest.trim().to_string();
+    }
+}
+
+fn currency_symbol(symbol: char) -> Option<&'static str> {
+    match symbol {
+        '$' => Some("USD"),
+        '€' => Some("EUR"),
+        '£' => Some("GBP"),
+        '¥' => Some("JPY"),
+        '₹' => Some("INR"),
+        _ => None,
+    }
+}
+
+fn scale_value(word: &str) -> Option<f64> {
+    match word.to_lowercase().as_str() {
+        "k" | "thousand" | "thousands" | "'000" | "(000)" | "(000s)" => Some(1e3),
+        "m" | "mn" | "mm" | "million" | "millions" => Some(1e6),
+        "b" | "bn" | "billion" | "billions" => Some(1e9),
+        "t" | "tn" | "trillion" | "trillions" => Some(1e12),
+        _ => None,
+    }
+}
+
+fn scale_name(scale: f64) -> &'static str {
+    match scale {
+        1e3 => "thousands",
+        1e6 => "millions",
+        1e9 => "billions",
+        1e12 => "trillions",
+        _ => "",
+    }
+}
+
+/// The scale a header or title gives its numbers, such as `1e6` for `USD in millions`.
+pub fn scale_of(text: &str) -> Option<f64> {
+    let captures = SCALE.captures(text)?;
+    let word = (1..=3).find_map(|i| captures.get(i)).or(captures.get(0))?;
+    scale_value(word.as_str())
+}
+
+/// Parses numbers the way financial tables write them: `$1,234.5`, `(12.0)` for negatives,
+/// `4.5%`, `1.234,5 €`, `$1.2bn` and trailing footnote markers such as `12*` or `3.1†`.
+pub fn parse_number(cell: &str) -> Option<Number> {
+    let mut text = cell
+        .trim()
+        .trim_end_matches(['*', '†', '‡', '§', '¹', '²', '³'])
+        .trim()
+        .to_string();
+    let mut negative = false;
+    let mut unit = None;
+    if let Some(rest) = text.strip_suffix('%') {
+        unit = Some("%".to_string());
+        text = rest.trim().to_string();
+    }
+    strip_sign(&mut text, &mut negative);
+    if let Some(rest) = text.strip_prefix("US$") {
+        unit = Some("USD".into());
+        text = rest.trim().to_string();
+    }
+    for symbol in [text.chars().next(), text.chars().last()]
+        .into_iter()
+        .flatten()
+    {
+        if let Some(code) = currency_symbol(symbol) {
+            unit = Some(code.into());
+            text = text.trim_matches(symbol).trim().to_string();
+        }
+    }
+    let code = CURRENCY_CODE.captures(&text).and_then(|captures| {
+        let code = captures.get(1).or(captures.get(3))?;
+        Some((code.as_str().to_string(), captures[2].to_string()))
+    });
+    if let Some((code, rest)) = code {
+        unit = Some(code);
+        text = rest;
+    }
+    let mut scale = None;
+    if let Some(captures) = SCALE_SUFFIX.captures(&text) {
+        scale = scale_value(&captures[2]);
+        text = captures[1].trim().to_string();
+    }
+    // The sign can also come after the currency, as in `$(12)` or `$-12`
+    strip_sign(&mut text, &mut negative);
+
+    let text: String = text
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\u{a0}' | '\u{202f}' | '\''))
+        .collect();
+    let normalized = match (text.rfind(','), text.rfind('.')) {
+        // Whichever separator comes last is the decimal one
+        (Some(comma), Some(dot)) if comma > dot => text.replace('.', "").replace(',', "."),
+        (Some(_), Some(_)) => text.replace(',', ""),
+        // Commas alone are thousands separators when every group has three digits
+        (Some(_), None) if text.split(',').skip(1).all(|group| group.len() == 3) => {
+            text.replace(',', "")
+        }
+        (Some(_), None) => text.replace(',', "."),
+        _ => text,
+    };
+    if !DIGITS.is_match(&normalized) {
+        return None;
+    }
+    let value: f64 = normalized.parse().ok()?;
+    Some(Number {
+        value: if negative { -value } else { value },
+        unit,
+        scale,
+    })
+}
+
+/// Parses dates written in ISO 8601, with slashes or dots, or with the month's name.
+pub fn parse_date(cell: &str) -> Option<NaiveDate> {
+    let cell = cell.trim();
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(cell, "%Y-%m-%d %H:%M:%S") {
+        return Some(datetime.date());
+    }
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(cell, format).ok())
+}
+
+/// Whether a cell reads as a label, years included, rather than as a value.
+pub fn is_label(cell: &str) -> bool {
+    YEAR.is_match(cell.trim()) || parse_number(cell).is_none()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The text of a cell, without footnote markers in `<sup>`.
+fn cell_text(cell: ElementRef) -> String {
+    let mut text = String::new();
+    for node in cell.descendants() {
+        if let Node::Text(part) = node.value() {
+            let in_sup = node
+                .ancestors()
+                .take_while(|ancestor| ancestor.id() != cell.id())
+                .filter_map(ElementRef::wrap)
+                .any(|ancestor| ancestor.value().name() == "sup");
+            if !in_sup {
+                text.push_str(part);
+                text.push(' ');
+            }
+        }
+    }
+    collapse_whitespace(&text)
+}
+
+fn span(cell: ElementRef, attribute: &str) -> usize {
+    cell.value()
+        .attr(attribute)
+        .and_then(|span| span.trim().parse().ok())
+        .unwrap_or(1)
+        .clamp(1, MAX_SPAN)
+}
+
+/// A table row laid out on the grid, with spanned cells repeated.
+struct Row {
+    cells: Vec<String>,
+    in_head: bool,
+    in_foot: bool,
+    all_th: bool,
+    /// A single cell spanning the whole row, such as a note or a section label
+    single: bool,
+}
+
+/// The rows of a table in `thead`, `tbody`, `tfoot` order, skipping nested tables.
+fn table_rows(table: ElementRef) -> Vec<(ElementRef, &'static str)> {
+    let mut head = vec![];
+    let mut body = vec![];
+    let mut foot = vec![];
+    for child in table.children().filter_map(ElementRef::wrap) {
+        let (section, rows) = match child.value().name() {
+            "thead" => ("thead", &mut head),
+            "tfoot" => ("tfoot", &mut foot),
+            "tbody" => ("tbody", &mut body),
+            "tr" => {
+                body.push((child, "tbody"));
+                continue;
+            }
+            _ => continue,
+        };
+        for row in child.children().filter_map(ElementRef::wrap) {
+            if row.value().name() == "tr" {
+                rows.push((row, section));
+            }
+        }
+    }
+    head.into_iter().chain(body).chain(foot).collect()
+}
+
+/// Lays the rows out on a grid, so that colspan and rowspan cells fill every slot they cover.
+fn grid(table: ElementRef) -> Vec<Row> {
+    // The text of a rowspan cell and the number of rows below it still covers
+    let mut pending: Vec<Option<(String, usize)>> = vec![];
+    let mut rows = vec![];
+    for (row, section) in table_rows(table) {
+        let mut cells = vec![];
+        let mut all_th = true;
+        let mut own_cells = 0;
+        let mut widest = 0;
+        let mut elements = row
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|cell| matches!(cell.value().name(), "td" | "th"));
+        loop {
+            let column = cells.len();
+            if let Some(Some((text, left))) = pending.get_mut(column) {
+                cells.push(text.clone());
+                *left -= 1;
+                if *left == 0 {
+                    pending[column] = None;
+                }
+                continue;
+            }
+            let Some(cell) = elements.next() else {
+                break;
+            };
+            all_th &= cell.value().name() == "th";
+            own_cells += 1;
+            let text = cell_text(cell);
+            let (colspan, rowspan) = (span(cell, "colspan"), span(cell, "rowspan"));
+            widest = widest.max(colspan);
+            for _ in 0..colspan {
+                let column = cells.len();
+                if pending.len() <= column {
+                    pending.resize(column + 1, None);
+                }
+                pending[column] = (rowspan > 1).then(|| (text.clone(), rowspan - 1));
+                cells.push(text.clone());
+            }
+        }
+        // Rowspans from above can continue past the last cell of this row
+        while let Some(Some((text, left))) = pending.get_mut(cells.len()) {
+            cells.push(text.clone());
+            *left -= 1;
+            if *left == 0 {
+                pending[cells.len() - 1] = None;
+            }
+        }
+        if cells.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+        rows.push(Row {
+            single: own_cells == 1 && widest > 1,
+            cells,
+            in_head: section == "thead",
+            in_foot: section == "tfoot",
+            all_th,
+        });
+    }
+    rows
+}
+
+/// Builds the header of every column from the header rows, joining stacked labels.
+pub fn header_names(header: &[Vec<String>], width: usize) -> Vec<String> {
+    (0..width)
+        .map(|column| {
+            let mut parts: Vec<&str> = vec![];
+            for row in header {
+                let part = row.get(column).map(|s| s.as_str()).unwrap_or("");
+                if !part.is_empty() && !parts.contains(&part) {
+                    parts.push(part);
+                }
+            }
+            if parts.is_empty() {
+                format!("Column {}", column + 1)
+            } else {
+                parts.join(" ")
+            }
+        })
+        .collect()
+}
+
+/// Drops the noise floating point arithmetic leaves in scaled values.
+fn tidy(value: f64) -> f64 {
+    (value * 1e9).round() / 1e9
+}
+
+fn most_common<T: PartialEq + Clone>(items: impl Iterator<Item = T>) -> Option<T> {
+    let mut counts: Vec<(T, usize)> = vec![];
+    for item in items {
+        match counts.iter_mut().find(|(seen, _)| *seen == item) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((item, 1)),
+        }
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(item, _)| item)
+}
+
+/// Infers the type of a column from its cells and parses them, so numbers can be used
+/// without reading them again.
+///
+/// The scale comes from the header, then from suffixes in the cells and finally from the
+/// `context`, usually the title and notes of the table. Percentages and years aren't scaled.
+/// Dashes and n/a in a typed column become empty values rather than text.
+pub fn typed_column(name: String, values: Vec<String>, context: &str) -> Column {
+    let filled = values.iter().filter(|value| !value.is_empty()).count();
+    let typed = |count: usize| count > 0 && count as f64 >= filled as f64 * TYPED_SHARE;
+
+    let dates: Vec<Option<NaiveDate>> = values.iter().map(|value| parse_date(value)).collect();
+    if typed(dates.iter().flatten().count()) {
+        return Column {
+            name,
+            values: dates
+                .iter()
+                .map(|date| date.map(|d| d.to_string()).unwrap_or_default())
+                .collect(),
+            kind: ColumnType::Date,
+            ..Default::default()
+        };
+    }
+
+    let numbers: Vec<Option<Number>> = values.iter().map(|value| parse_number(value)).collect();
+    if !typed(numbers.iter().flatten().count()) {
+        return Column {
+            name,
+            values,
+            ..Default::default()
+        };
+    }
+    let unit = most_common(numbers.iter().flatten().filter_map(|n| n.unit.clone()));
+    let years = values
+        .iter()
+        .filter(|value| !value.is_empty())
+        .all(|value| YEAR.is_match(value.trim()));
+    let scale = if years || unit.as_deref() == Some("%") {
+        None
+    } else {
+        scale_of(&name)
+            .or_else(|| most_common(numbers.iter().flatten().filter_map(|n| n.scale)))
+            .or_else(|| scale_of(context))
+    };
+
+    // Values stay in the column's scale, numbers are absolute
+    let scaled: Vec<Option<(f64, f64)>> = numbers
+        .iter()
+        .map(|number| {
+            let number = number.as_ref()?;
+            let column_scale = scale.unwrap_or(1.0);
+            let cell_scale = number.scale.unwrap_or(column_scale);
+            Some((
+                tidy(number.value * cell_scale / column_scale),
+                tidy(number.value * cell_scale),
+            ))
+        })
+        .collect();
+    let kind = match unit.as_deref() {
+        Some("%") => ColumnType::Percentage,
+        Some(_) => ColumnType::Currency,
+        None if scaled
+            .iter()
+            .flatten()
+            .all(|(value, _)| value.fract() == 0.0) =>
+        {
+            ColumnType::Integer
+        }
+        None => ColumnType::Decimal,
+    };
+    let description = [
+        unit.clone().unwrap_or_default(),
+        scale.map(scale_name).unwrap_or_default().to_string(),
+    ]
+    .into_iter()
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(" ");
+    Column {
+        name,
+        description: if description.is_empty() {
+            description
+        } else {
+            format!("Values in {}", description)
+        },
+        values: scaled
+            .iter()
+            .map(|cell| cell.map(|(value, _)| value.to_string()).unwrap_or_default())
+            .collect(),
+        kind,
+        numbers: scaled
+            .iter()
+            .map(|cell| cell.map(|(_, number)| number))
+            .collect(),
+        unit,
+        scale,
+    }
+}
+
+/// Turns a single `<table>` into `Data`, or `None` for layout tables and tables without data.
+fn table_data(table: ElementRef, title: String, url: &str) -> Option<Data> {
+    let mut rows = grid(table);
+    let mut footnotes = vec![];
+    // Notes and sources sit below the data, in the tfoot or spanning the whole row
+    while let Some(row) = rows.last() {
+        if !(row.in_foot || row.single) {
+            break;
+        }
+        let row = rows.pop().unwrap();
+        let mut cells = row.cells;
+        cells.dedup();
+        footnotes.insert(0, cells.join(" "));
+    }
+    // Section labels in between the data are not data
+    rows.retain(|row| !row.single || row.in_head);
+
+    let mut header_len = rows
+        .iter()
+        .take_while(|row| row.in_head || row.all_th)
+        .count();
+    if header_len == 0
+        && rows.len() > 2
+        && rows[0].cells.iter().all(|cell| is_label(cell))
+        && rows[1]
+            .cells
+            .iter()
+            .any(|cell| parse_number(cell).is_some())
+    {
+        header_len = 1;
+    }
+    let (header, body) = rows.split_at(header_len);
+    let width = body.iter().map(|row| row.cells.len()).max()?;
+    if body.len() < 2 || width < 2 {
+        return None;
+    }
+
+    let header: Vec<Vec<String>> = header.iter().map(|row| row.cells.clone()).collect();
+    let names = header_names(&header, width);
+    let context = format!("{} {}", title, footnotes.join(" "));
+    let columns = names
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, name)| {
+            let values: Vec<String> = body
+                .iter()
+                .map(|row| row.cells.get(i).cloned().unwrap_or_default())
+                .collect();
+            if values.iter().all(|value| value.is_empty()) {
+                return None;
+            }
+            Some(typed_column(name, values, &context))
+        })
+        .collect::<Vec<_>>();
+    if columns.len() < 2 {
+        return None;
+    }
+    Some(Data {
+        title,
+        description: footnotes.join("\n"),
+        columns,
+        source: Some(url.into()),
+        page: None,
+    })
+}
+
+/// Builds `Data` from table rows, using the first row as header when it holds no numbers.
+/// Short rows are padded to the widest one.
+pub fn rows_data(rows: Vec<Vec<String>>, title: String, page: Option<u32>) -> Option<Data> {
+    let width = rows.iter().map(|row| row.len()).max()?;
+    if width < 2 {
+        return None;
+    }
+    let has_header = rows[0].iter().all(|cell| is_label(cell));
+    let (header, body) = rows.split_at(has_header as usize);
+    let columns: Vec<Column> = (0..width)
+        .filter_map(|i| {
+            let values: Vec<String> = body
+                .iter()
+                .map(|row| row.get(i).cloned().unwrap_or_default())
+                .collect();
+            if values.iter().all(|value| value.is_empty()) {
+                return None;
+            }
+            let name = header
+                .first()
+                .and_then(|row| row.get(i).cloned())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| format!("Column {}", i + 1));
+            Some(typed_column(name, values, &title))
+        })
+        .collect();
+    (columns.len() >= 2).then(|| Data {
+        title,
+        description: String::new(),
+        columns,
+        source: None,
+        page,
+    })
+}
+
+/// Renders rows as a Markdown table with the first row as header.
+pub fn markdown_table(rows: &[Vec<String>]) -> String {
+    let mut table = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        table.push_str(&format!("| {} |\n", row.join(" | ")));
+        if i == 0 {
+            table.push_str(&format!("|{}\n", " --- |".repeat(row.len())));
+        }
+    }
+    table
+}
+
+/// Extracts every data table of an HTML page, titled by its caption or the heading above it.
+pub fn html_tables(html: &str, url: &str) -> Vec<Data> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("h1, h2, h3, h4, h5, h6, table").unwrap();
+    let caption = Selector::parse("caption").unwrap();
+    let nested = Selector::parse("table").unwrap();
+    let page_title = Selector::parse("title")
+        .ok()
+        .and_then(|title| document.select(&title).next())
+        .map(|title| collapse_whitespace(&title.text().collect::<String>()))
+        .unwrap_or_default();
+
+    let mut heading = None;
+    let mut tables = vec![];
+    for element in document.select(&selector) {
+        if element.value().name() != "table" {
+            heading = Some(collapse_whitespace(&element.text().collect::<String>()));
+            continue;
+        }
+        // Tables inside tables, or containing them, lay out the page instead of holding data
+        let in_table = element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| ancestor.value().name() == "table");
+        if in_table || element.select(&nested).next().is_some() {
+            continue;
+        }
+        let title = element
+            .select(&caption)
+            .next()
+            .map(cell_text)
+            .filter(|caption| !caption.is_empty())
+            .or_else(|| heading.clone())
+            .unwrap_or_else(|| format!("{} table {}", page_title, tables.len() + 1));
+        if let Some(data) = table_data(element, title, url) {
+            tables.push(data);
+        }
+    }
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_number() {
+        let number = |value: f64, unit: Option<&str>| {
+            Some(Number {
+                value,
+                unit: unit.map(Into::into),
+                scale: None,
+            })
+        };
+        assert_eq!(parse_number("1,234.5"), number(1234.5, None));
+        assert_eq!(parse_number("(12.0)"), number(-12.0, None));
+        assert_eq!(parse_number("$(1,200)"), number(-1200.0, Some("USD")));
+        assert_eq!(parse_number("($1,200)"), number(-1200.0, Some("USD")));
+        assert_eq!(parse_number("−3.5%"), number(-3.5, Some("%")));
+        assert_eq!(parse_number("1.234,5 €"), number(1234.5, Some("EUR")));
+        assert_eq!(parse_number("EUR 12"), number(12.0, Some("EUR")));
+        assert_eq!(parse_number("US$ 7"), number(7.0, Some("USD")));
+        assert_eq!(parse_number("12*"), number(12.0, None));
+        assert_eq!(parse_number("0,5"), number(0.5, None));
+        assert_eq!(parse_number("1 000 000"), number(1000000.0, None));
+        assert_eq!(
+            parse_number("$1.2bn"),
+            Some(Number {
+                value: 1.2,
+                unit: Some("USD".into()),
+                scale: Some(1e9),
+            })
+        );
+        assert_eq!(parse_number("—"), None);
+        assert_eq!(parse_number("n/a"), None);
+        assert_eq!(parse_number("Q1 2024"), None);
+        assert_eq!(parse_number(""), None);
+    }
+
+    #[test]
+    fn test_typed_column() {
+        let column = |name: &str, values: &[&str], context: &str| {
+            let values = values.iter().map(|value| value.to_string()).collect();
+            typed_column(name.into(), values, context)
+        };
+        let revenue = column("Revenue ($m)", &["1,200", "$1.5bn", "—"], "");
+        assert_eq!(revenue.kind, ColumnType::Currency);
+        assert_eq!(revenue.unit.as_deref(), Some("USD"));
+        assert_eq!(revenue.scale, Some(1e6));
+        assert_eq!(revenue.values, vec!["1200", "1500", ""]);
+        assert_eq!(revenue.numbers, vec![Some(1.2e9), Some(1.5e9), None]);
+        assert_eq!(revenue.description, "Values in USD millions");
+
+        let margin = column("Margin", &["45.9%", "46.2%"], "In millions of dollars");
+        assert_eq!(margin.kind, ColumnType::Percentage);
+        assert_eq!(margin.scale, None);
+        assert_eq!(margin.numbers, vec![Some(45.9), Some(46.2)]);
+
+        let units = column("Units", &["12.5", "3"], "Shipments in thousands");
+        assert_eq!(units.kind, ColumnType::Decimal);
+        assert_eq!(units.numbers, vec![Some(12500.0), Some(3000.0)]);
+
+        let years = column("Year", &["2023", "2024"], "Revenue in millions");
+        assert_eq!(years.kind, ColumnType::Integer);
+        assert_eq!(years.numbers, vec![Some(2023.0), Some(2024.0)]);
+
+        let dates = column("Date", &["Jan 31, 2024", "2024-02-29", "n/a"], "");
+        assert_eq!(dates.kind, ColumnType::Date);
+        assert_eq!(dates.values, vec!["2024-01-31", "2024-02-29", ""]);
+
+        let segments = column("Segment", &["iPhone", "Services"], "");
+        assert_eq!(segments.kind, ColumnType::Category);
+        assert_eq!(segments.values, vec!["iPhone", "Services"]);
+    }
+
+    #[test]
+    fn test_scale_of() {
+        assert_eq!(scale_of("Net sales (in millions)"), Some(1e6));
+        assert_eq!(scale_of("Revenue, $bn"), Some(1e9));
+        assert_eq!(scale_of("Assets (USD m)"), Some(1e6));
+        assert_eq!(scale_of("Employees ('000)"), Some(1e3));
+        assert_eq!(scale_of("Services (b)"), None);
+        assert_eq!(scale_of("Revenue"), None);
+    }
+
+    #[test]
+    fn test_html_tables() {
+        let html = r#"
+        <html><head><title>Apple results</title></head><body>
+            <h2>Net sales by category</h2>
+            <table>
+                <thead>
+                    <tr><th rowspan="2">Category</th><th colspan="2">Three months ended</th></tr>
+                    <tr><th>Dec 28, 2024</th><th>Dec 30, 2023</th></tr>
+                </thead>
+                <tbody>
+                    <tr><td>iPhone</td><td>$69,138</td><td>$69,702</td></tr>
+                    <tr><td colspan="3">Services and other</td></tr>
+                    <tr><td>Services<sup>1</sup></td><td>26,340</td><td>23,117</td></tr>
+                    <tr><td>Change</td><td>(564)</td><td>—</td></tr>
+                </tbody>
+                <tfoot><tr><td colspan="3">(1) Includes advertising and cloud services.</td></tr></tfoot>
+            </table>
+            <table><tr><td>Layout</td><td><table><tr><td>a</td></tr></table></td></tr></table>
+        </body></html>
+        "#;
+        let tables = html_tables(html, "https://www.apple.com/newsroom");
+        assert_eq!(tables.len(), 1);
+        let data = &tables[0];
+        assert_eq!(data.title, "Net sales by category");
+        assert_eq!(
+            data.description,
+            "(1) Includes advertising and cloud services."
+        );
+        assert_eq!(
+            data.source.as_deref(),
+            Some("https://www.apple.com/newsroom")
+        );
+        let names: Vec<_> = data.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Category",
+                "Three months ended Dec 28, 2024",
+                "Three months ended Dec 30, 2023"
+            ]
+        );
+        assert_eq!(data.columns[0].values, vec!["iPhone", "Services", "Change"]);
+        assert_eq!(data.columns[1].values, vec!["69138", "26340", "-564"]);
+        assert_eq!(data.columns[1].description, "Values in USD");
+        assert_eq!(data.columns[2].values, vec!["69702", "23117", ""]);
+    }
+
+    #[test]
+    fn test_html_tables_rowspan() {
+        let html = r#"
+        <table>
+            <caption>Segments</caption>
+            <tr><td>Region</td><td>Quarter</td><td>Revenue</td></tr>
+            <tr><td rowspan="2">Americas</td><td>Q1</td><td>52.6</td></tr>
+            <tr><td>Q2</td><td>40.3</td></tr>
+            <tr><td>Europe</td><td>Q1</td><td>33.9</td></tr>
+        </table>
+        "#;
+        let tables = html_tables(html, "https://example.com");
+        assert_eq!(tables.len(), 1);
+        let data = &tables[0];
+        assert_eq!(data.title, "Segments");
+        assert_eq!(data.columns[0].name, "Region");
+        assert_eq!(
+            data.columns[0].values,
+            vec!["Americas", "Americas", "Europe"]
+        );
+        assert_eq!(data.columns[2].values, vec!["52.6", "40.3", "33.9"]);
+    }
+}
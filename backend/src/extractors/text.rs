@@ -1,5 +1,4 @@
-use super::FileType;
-use super::{Content, ContentExtract};
+use super::{Document, Extract, RawDocument, TextBlock};
 use crate::prelude::*;
 use async_trait::async_trait;
 use log::debug;
@@ -7,22 +6,25 @@ use log::debug;
 pub struct TextExtractor;
 
 #[async_trait]
-impl ContentExtract for TextExtractor {
-    async fn extract(&self, file: FileType) -> Result<Vec<Content>> {
+impl Extract for TextExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["text/plain"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt"]
+    }
+
+    async fn extract(&self, file: &RawDocument) -> Result<Document> {
         debug!("Extracting text content...");
-        let FileType::Text(input) = file else {
-            return Err(FinanalizeError::ParseError(
-                "Invalid input type".to_string(),
-            ));
-        };
+        let input = file.text();
         // debug!("Received valid input: {}", input);
         let mut chunks = Vec::new();
         let mut current_chunk = String::new();
 
         for word in input.split_whitespace() {
             if current_chunk.len() + word.len() >= 512 {
-                // Wrap the chunk as `Content::Text` and push it to the vector
-                chunks.push(Content::Text(current_chunk.clone()));
+                chunks.push(TextBlock::new(current_chunk.clone()));
                 debug!("Pushed chunk with length: {}", current_chunk.len());
                 current_chunk.clear();
             }
@@ -37,13 +39,16 @@ impl ContentExtract for TextExtractor {
                 "Pushing the last chunk with length: {}",
                 current_chunk.len()
             );
-            chunks.push(Content::Text(current_chunk));
+            chunks.push(TextBlock::new(current_chunk));
         }
         debug!(
             "Finished extracting text content. Returning {} chunks",
             chunks.len()
         );
-        Ok(chunks)
+        Ok(Document {
+            blocks: chunks,
+            ..Default::default()
+        })
     }
 }
 
@@ -62,18 +67,14 @@ mod tests {
         where dreams shaped reality and hope never faded. Eldrin smiled, knowing that every story leads to another.";
 
         let extractor = TextExtractor;
-        let chunks = extractor
-            .extract(FileType::Text(text.into()))
+        let document = extractor
+            .extract(&RawDocument::new("story.txt", None, text.into()))
             .await
             .unwrap();
 
-        // Check that each chunk is wrapped as `Content::Text`
-        for chunk in &chunks {
-            if let Content::Text(text_chunk) = chunk {
-                assert!(text_chunk.len() <= 512, "Chunk exceeds 512 characters!");
-            } else {
-                panic!("Chunk is not wrapped as Content::Text");
-            }
+        assert!(document.blocks.len() > 1);
+        for chunk in &document.blocks {
+            assert!(chunk.text.len() <= 512, "Chunk exceeds 512 characters!");
         }
     }
 }
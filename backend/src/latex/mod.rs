@@ -32,6 +32,10 @@ pub struct Paragraph {
 pub struct Figure {
     pub caption: String,
     pub path: String,
+    /// Who published the figure, such as the site it was taken from
+    pub attribution: Option<String>,
+    /// Key of the figure's source in the bibliography
+    pub citation: Option<String>,
 }
 
 pub struct Table {
@@ -70,7 +74,8 @@ pub struct Source {
     pub citation_key: String,
     pub author: String,
     pub title: String,
-    pub year: i32,
+    /// Left out of the bibliography when the source has no date
+    pub year: Option<i32>,
     pub journal: String,
     pub url: String,
 }
@@ -81,7 +86,7 @@ impl Source {
         citation_key: String,
         author: String,
         title: String,
-        year: i32,
+        year: Option<i32>,
         journal: String,
         url: String,
     ) -> Self {
@@ -208,8 +213,17 @@ pub fn get_commands(components: Vec<LatexComponent>) -> Result<Vec<LatexCommand>
                     command: r"\includegraphics[width=\linewidth]".to_string(),
                     args: format!("{{{}}}", figure.path.clone()),
                 });
+                // Captions and attributions come from scraped pages, so nothing in them may be
+                // read as a command
+                let mut caption = escape_untrusted_text(&figure.caption);
+                if let Some(attribution) = &figure.attribution {
+                    caption.push_str(&format!(" Source: {}", escape_untrusted_text(attribution)));
+                }
+                if let Some(citation) = &figure.citation {
+                    caption.push_str(&format!(r"~\cite{{{}}}", citation));
+                }
                 commands.push(LatexCommand {
-                    command: format!(r"\caption{{{}}}", figure.caption.clone()),
+                    command: format!(r"\caption{{{}}}", caption),
                     args: "".to_string(),
                 });
                 commands.push(LatexCommand {
@@ -315,3 +329,46 @@ fn escape_special_chars(input: String) -> String {
     }
     escaped
 }
+
+/// Escapes text from untrusted sources, such as scraped pages, so that it is always typeset
+/// literally. Unlike `escape_special_chars`, this also neutralises backslashes and braces.
+fn escape_untrusted_text(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for ch in input.chars() {
+        match ch {
+            '\\' => escaped.push_str(r"\textbackslash{}"),
+            '{' | '}' | '&' | '%' | '$' | '#' | '_' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '~' => escaped.push_str(r"\textasciitilde{}"),
+            '^' => escaped.push_str(r"\textasciicircum{}"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_figure_caption_cannot_inject_commands() {
+        let components = vec![LatexComponent::Figure(Figure {
+            caption: r"Revenue \input{/etc/passwd} \immediate\write18{rm -rf ~}".to_string(),
+            path: "figure".to_string(),
+            attribution: Some(r"evil.example}\def\x{".to_string()),
+            citation: Some("source1".to_string()),
+        })];
+        let commands = get_commands(components).unwrap();
+        let caption = commands
+            .iter()
+            .find(|command| command.command.starts_with(r"\caption"))
+            .unwrap();
+        assert_eq!(
+            caption.command,
+            r"\caption{Revenue \textbackslash{}input\{/etc/passwd\} \textbackslash{}immediate\textbackslash{}write18\{rm -rf \textasciitilde{}\} Source: evil.example\}\textbackslash{}def\textbackslash{}x\{~\cite{source1}}"
+        );
+    }
+}
@@ -12,6 +12,7 @@ use uuid::Uuid;
 struct TemplateData {
     report_title: String,
     report_subtitle: String,
+    babel_language: String,
     components: Vec<LatexCommand>,
 }
 
@@ -32,11 +33,13 @@ pub fn construct_report(
     commands: Vec<LatexCommand>,
     report_title: String,
     report_subtitle: String,
+    babel_language: String,
 ) -> Result<PdfReport> {
     debug!("Constructing report: {}", report_title);
     let data = TemplateData {
         report_title: report_title.clone(),
         report_subtitle: report_subtitle.clone(),
+        babel_language,
         components: commands,
     };
     let backend_dir = env::current_dir()?;
@@ -172,7 +175,7 @@ mod tests {
                 citation_key: "smith2024".to_string(),
                 author: "John Smith".to_string(),
                 title: "The Future of AI".to_string(),
-                year: 2024,
+                year: Some(2024),
                 journal: "Tech Insights".to_string(),
                 url: "https://example.com/future-ai".to_string(),
             },
@@ -181,7 +184,7 @@ mod tests {
                 citation_key: "doe2023".to_string(),
                 author: "Jane Doe".to_string(),
                 title: "Blockchain and Data Security".to_string(),
-                year: 2023,
+                year: Some(2023),
                 journal: "Cyber Journal".to_string(),
                 url: "https://example.com/blockchain-security".to_string(),
             },
@@ -584,6 +587,8 @@ Q4 2024 was a strong quarter for the company, with steady revenue growth, contro
             LatexComponent::Figure(Figure {
                 caption: "Test Caption".to_string(),
                 path: "image".to_string(),
+                attribution: Some("example.com".to_string()),
+                citation: None,
             }),
             LatexComponent::Table(Table {
                 caption: "Table caption".to_string(),
@@ -612,6 +617,7 @@ Q4 2024 was a strong quarter for the company, with steady revenue growth, contro
             commands,
             "Test Report".to_string(),
             "This is a test report".to_string(),
+            "english".to_string(),
         )
         .unwrap();
     }
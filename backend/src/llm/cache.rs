@@ -0,0 +1,253 @@
+use std::{env, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{db::DB, prelude::*};
+
+use super::{Api, GenerationCaching, GenerationParams, GenerationResult, LLMApi};
+
+const CACHE_TABLE: &str = "llm_cache";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResponse {
+    backend: Api,
+    model: String,
+    generated: String,
+    prompt_token_count: usize,
+    generated_token_count: usize,
+    total_duration_us: i64,
+    created_at: DateTime<Utc>,
+}
+
+/// Caches generations in SurrealDB, keyed by everything that influences the output.
+///
+/// Enabled by setting `LLM_CACHE_TTL_SECS`, hits are returned with `cache_hit` set.
+pub struct ResponseCache {
+    backend: Api,
+    inner: Arc<dyn LLMApi>,
+    ttl: Option<Duration>,
+}
+
+impl ResponseCache {
+    pub fn new(backend: Api, inner: Arc<dyn LLMApi>) -> Self {
+        let ttl = env::var("LLM_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::seconds);
+        Self {
+            backend,
+            inner,
+            ttl,
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    async fn get(&self, key: &str, ttl: Duration) -> Result<Option<GenerationResult>> {
+        let Some(db) = DB.get() else {
+            return Ok(None);
+        };
+        let cached: Option<CachedResponse> = db.select((CACHE_TABLE, key)).await?;
+        let Some(cached) = cached else {
+            return Ok(None);
+        };
+        if cached.created_at + ttl < Utc::now() {
+            debug!("Cached response {} expired", key);
+            let _: Option<CachedResponse> = db.delete((CACHE_TABLE, key)).await?;
+            return Ok(None);
+        }
+        Ok(Some(GenerationResult {
+            generated: cached.generated,
+            api: cached.backend,
+            prompt_token_count: cached.prompt_token_count,
+            generated_token_count: cached.generated_token_count,
+            caching: GenerationCaching::None,
+            total_duration_us: cached.total_duration_us,
+            cache_hit: true,
+        }))
+    }
+
+    async fn put(
+        &self,
+        key: &str,
+        params: &GenerationParams,
+        res: &GenerationResult,
+    ) -> Result<()> {
+        let Some(db) = DB.get() else {
+            return Ok(());
+        };
+        let _: Option<CachedResponse> = db
+            .upsert((CACHE_TABLE, key))
+            .content(CachedResponse {
+                backend: self.backend.clone(),
+                model: params.model.clone(),
+                generated: res.generated.clone(),
+                prompt_token_count: res.prompt_token_count,
+                generated_token_count: res.generated_token_count,
+                total_duration_us: res.total_duration_us,
+                created_at: Utc::now(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn cached<F, Fut>(
+        &self,
+        params: &GenerationParams,
+        prompt: &str,
+        schema: Option<&str>,
+        generate: F,
+    ) -> Result<GenerationResult>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<GenerationResult>>,
+    {
+        let Some(ttl) = self.ttl else {
+            return generate().await;
+        };
+        let key = cache_key(&self.backend, params, prompt, schema);
+        // The cache is an optimization, a broken cache should never fail the generation
+        match self.get(&key, ttl).await {
+            Ok(Some(hit)) => {
+                debug!("Cache hit for model {}", params.model);
+                return Ok(hit);
+            }
+            Ok(None) => {}
+            Err(err) => warn!("Failed to read cached response: {}", err),
+        }
+        let res = generate().await?;
+        if let Err(err) = self.put(&key, params, &res).await {
+            warn!("Failed to cache response: {}", err);
+        }
+        Ok(res)
+    }
+}
+
+/// Hash of (backend, model, rendered prompt, schema, temperature)
+pub fn cache_key(
+    backend: &Api,
+    params: &GenerationParams,
+    prompt: &str,
+    schema: Option<&str>,
+) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        format!("{:?}", backend).as_str(),
+        params.model.as_str(),
+        prompt,
+        if schema.is_some() { "json" } else { "raw" },
+        schema.unwrap_or(""),
+        params.temperature.to_string().as_str(),
+    ] {
+        // Length-prefix every part so shifting text between parts changes the key
+        hasher.update(part.len().to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[async_trait]
+impl LLMApi for ResponseCache {
+    async fn generate(
+        &self,
+        params: &GenerationParams,
+        prompt: String,
+    ) -> Result<GenerationResult> {
+        let cache_prompt = prompt.clone();
+        self.cached(params, &cache_prompt, None, || {
+            self.inner.generate(params, prompt)
+        })
+        .await
+    }
+
+    async fn generate_json(
+        &self,
+        params: &GenerationParams,
+        prompt: String,
+        json_schema: String,
+    ) -> Result<GenerationResult> {
+        let (cache_prompt, cache_schema) = (prompt.clone(), json_schema.clone());
+        self.cached(params, &cache_prompt, Some(&cache_schema), || {
+            self.inner.generate_json(params, prompt, json_schema)
+        })
+        .await
+    }
+
+    async fn embed(&self, text: String) -> Result<Vec<f32>> {
+        self.inner.embed(text).await
+    }
+
+    async fn forget(&self, params: &GenerationParams, prompt: &str, json_schema: Option<&str>) {
+        let (Some(_), Some(db)) = (self.ttl, DB.get()) else {
+            return;
+        };
+        let key = cache_key(&self.backend, params, prompt, json_schema);
+        debug!("Forgetting cached response {}", key);
+        let res: surrealdb::Result<Option<CachedResponse>> = db.delete((CACHE_TABLE, key)).await;
+        if let Err(err) = res {
+            warn!("Failed to forget cached response: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key_depends_on_every_part() {
+        let params = GenerationParams::default();
+        let key = cache_key(&Api::Ollama, &params, "prompt", Some("{}"));
+        assert_eq!(key, cache_key(&Api::Ollama, &params, "prompt", Some("{}")));
+        assert_eq!(key.len(), 64);
+
+        let hotter = GenerationParams {
+            temperature: 0.9,
+            ..Default::default()
+        };
+        let other_model = GenerationParams {
+            model: "qwen2.5:latest".into(),
+            ..Default::default()
+        };
+        for other in [
+            cache_key(&Api::OpenAI, &params, "prompt", Some("{}")),
+            cache_key(&Api::Ollama, &other_model, "prompt", Some("{}")),
+            cache_key(&Api::Ollama, &params, "prompt!", Some("{}")),
+            cache_key(&Api::Ollama, &params, "prompt", None),
+            cache_key(&Api::Ollama, &hotter, "prompt", Some("{}")),
+            cache_key(&Api::Ollama, &params, "prompt{", Some("}")),
+        ] {
+            assert_ne!(key, other);
+        }
+    }
+
+    #[test]
+    fn test_cache_hits_are_free() {
+        let hit = GenerationResult {
+            generated: "cached".into(),
+            api: Api::Ollama,
+            prompt_token_count: 1000,
+            generated_token_count: 1000,
+            caching: GenerationCaching::None,
+            total_duration_us: 0,
+            cache_hit: true,
+        };
+        assert_eq!(Api::Ollama.cost(hit.clone()), rust_decimal::Decimal::ZERO);
+        let miss = GenerationResult {
+            cache_hit: false,
+            ..hit
+        };
+        assert!(Api::Ollama.cost(miss) > rust_decimal::Decimal::ZERO);
+    }
+}
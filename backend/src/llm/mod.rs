@@ -3,20 +3,31 @@ use std::{str::FromStr, sync::Arc};
 use crate::prelude::*;
 
 use async_trait::async_trait;
+use cache::ResponseCache;
 use ollama::Ollama;
 use once_cell::sync::Lazy;
 use rust_decimal::Decimal;
+use scheduler::{Priority, Scheduler};
 use serde::{Deserialize, Serialize};
 
+pub mod cache;
 pub mod ollama;
+pub mod scheduler;
 // pub mod ullm;
 
-pub static API: Lazy<Arc<dyn LLMApi>> = Lazy::new(|| Arc::new(Ollama::default()));
+pub static SCHEDULER: Lazy<Arc<Scheduler>> =
+    Lazy::new(|| Arc::new(Scheduler::new(Api::Ollama, Arc::new(Ollama::default()))));
+
+// Cache hits skip the scheduler queue entirely
+pub static API: Lazy<Arc<dyn LLMApi>> =
+    Lazy::new(|| Arc::new(ResponseCache::new(Api::Ollama, SCHEDULER.clone())));
 
 #[derive(Debug, Clone)]
 pub struct GenerationParams {
     pub model: String,
     pub ctx: u128,
+    pub temperature: f64,
+    pub priority: Priority,
 }
 
 impl Default for GenerationParams {
@@ -24,6 +35,8 @@ impl Default for GenerationParams {
         Self {
             model: "llama3.1:latest".to_string(),
             ctx: 12228,
+            temperature: 0.5,
+            priority: Priority::Batch,
         }
     }
 }
@@ -47,6 +60,7 @@ pub enum CostType {
     CachedInput,
     CacheRead,
     CacheWrite,
+    CachedResponse,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,7 +78,7 @@ impl Api {
                 CostType::Input => Decimal::from_str("0.0005").unwrap(),
                 CostType::Output => Decimal::from_str("0.0015").unwrap(),
                 _ => Decimal::from(0),
-            }
+            },
             // Credits are 1000 per $1
             //
             // Input: $0.50 per 1M tokens, so in credits it's 500 credits per 1M tokens, or 0.0005
@@ -81,6 +95,14 @@ impl Api {
     }
 
     pub fn cost(self, gr: GenerationResult) -> Decimal {
+        if gr.cache_hit {
+            // Served from our own response cache, no tokens were generated
+            return Decimal::from_str(
+                &(gr.prompt_token_count + gr.generated_token_count).to_string(),
+            )
+            .unwrap()
+            .saturating_mul(self.exchange_rate(CostType::CachedResponse));
+        }
         let mut total = Decimal::ZERO;
         let input = Decimal::from_str(&gr.prompt_token_count.to_string())
             .unwrap()
@@ -122,6 +144,8 @@ pub struct GenerationResult {
     pub generated_token_count: usize,
     pub caching: GenerationCaching,
     pub total_duration_us: i64,
+    #[serde(default)]
+    pub cache_hit: bool,
 }
 
 impl From<GenerationResult> for String {
@@ -143,4 +167,7 @@ pub trait LLMApi: Send + Sync + 'static {
     ) -> Result<GenerationResult>;
 
     async fn embed(&self, text: String) -> Result<Vec<f32>>;
+
+    /// Drops a cached generation that turned out to be unusable, so a retry generates anew
+    async fn forget(&self, _params: &GenerationParams, _prompt: &str, _json_schema: Option<&str>) {}
 }
@@ -76,7 +76,6 @@ fn default_options() -> HashMap<&'static str, Value> {
     options.insert("stop", Value::Array(vec!["```".into(), "</Output>".into()]));
     options.insert("num_ctx", Value::Number(Number::from_u128(12228).unwrap()));
     options.insert("keep_alive", Value::String("1m".into()));
-    options.insert("temperature", Value::Number(Number::from_f64(0.5).unwrap()));
     options
 }
 
@@ -92,6 +91,10 @@ impl LLMApi for Ollama {
             "num_ctx",
             Value::Number(Number::from_u128(params.ctx).unwrap()),
         );
+        options.insert(
+            "temperature",
+            Value::Number(Number::from_f64(params.temperature).unwrap()),
+        );
         let request = OllamaCompletionRequest {
             model: params.model.clone(),
             prompt,
@@ -118,6 +121,7 @@ impl LLMApi for Ollama {
             generated_token_count: result.eval_count,
             caching: GenerationCaching::None,
             total_duration_us: result.total_duration.num_microseconds().unwrap_or(0),
+            cache_hit: false,
         })
     }
 
@@ -132,6 +136,10 @@ impl LLMApi for Ollama {
             "num_ctx",
             Value::Number(Number::from_u128(params.ctx).unwrap()),
         );
+        options.insert(
+            "temperature",
+            Value::Number(Number::from_f64(params.temperature).unwrap()),
+        );
         let request = OllamaCompletionRequest {
             model: params.model.clone(),
             prompt,
@@ -161,6 +169,7 @@ impl LLMApi for Ollama {
             generated_token_count: result.eval_count,
             caching: GenerationCaching::None,
             total_duration_us: result.total_duration.num_microseconds().unwrap_or(0),
+            cache_hit: false,
         })
     }
 
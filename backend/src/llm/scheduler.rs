@@ -0,0 +1,489 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::Notify,
+    time::{timeout, Instant},
+};
+
+use crate::prelude::*;
+
+use super::{Api, GenerationParams, GenerationResult, LLMApi};
+
+const TOKEN_WINDOW: Duration = Duration::from_secs(60);
+const EMBED_LANE: &str = "embeddings";
+
+/// Who is waiting on the generation, interactive work is always admitted before batch work.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Priority {
+    Interactive,
+    #[default]
+    Batch,
+}
+
+#[derive(Debug, Clone)]
+pub struct LaneLimits {
+    pub max_concurrency: usize,
+    pub tokens_per_minute: Option<usize>,
+    pub request_timeout: Duration,
+}
+
+impl Default for LaneLimits {
+    fn default() -> Self {
+        let max_concurrency = env::var("LLM_MAX_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1);
+        let tokens_per_minute = env::var("LLM_TOKENS_PER_MINUTE")
+            .ok()
+            .and_then(|v| v.parse().ok());
+        let request_timeout = env::var("LLM_REQUEST_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(60));
+        Self {
+            max_concurrency,
+            tokens_per_minute,
+            request_timeout,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaneMetrics {
+    pub backend: Api,
+    pub model: String,
+    pub active: usize,
+    pub queued_interactive: usize,
+    pub queued_batch: usize,
+    pub tokens_last_minute: usize,
+    pub max_concurrency: usize,
+    pub tokens_per_minute: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct LaneState {
+    active: usize,
+    queued_interactive: usize,
+    queued_batch: usize,
+    usage: VecDeque<(Instant, usize)>,
+}
+
+impl LaneState {
+    fn tokens_last_minute(&mut self, now: Instant) -> usize {
+        while let Some((at, _)) = self.usage.front() {
+            if now.duration_since(*at) < TOKEN_WINDOW {
+                break;
+            }
+            self.usage.pop_front();
+        }
+        self.usage.iter().map(|(_, tokens)| tokens).sum()
+    }
+
+    fn queued_mut(&mut self, priority: Priority) -> &mut usize {
+        match priority {
+            Priority::Interactive => &mut self.queued_interactive,
+            Priority::Batch => &mut self.queued_batch,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Lane {
+    limits: LaneLimits,
+    state: Mutex<LaneState>,
+    notify: Notify,
+}
+
+impl Lane {
+    fn new(limits: LaneLimits) -> Self {
+        Self {
+            limits,
+            state: Mutex::new(LaneState::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Waits until the lane has a free slot and token budget, interactive work goes first.
+    async fn acquire(self: &Arc<Self>, priority: Priority) -> Permit {
+        *self.state.lock().unwrap().queued_mut(priority) += 1;
+        // Keeps the queue depth right when the caller gives up while waiting
+        let mut ticket = Ticket {
+            lane: self,
+            priority,
+            admitted: false,
+        };
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            let retry_at = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                let used = state.tokens_last_minute(now);
+                let has_slot = state.active < self.limits.max_concurrency;
+                let has_budget = self.limits.tokens_per_minute.is_none_or(|tpm| used < tpm);
+                let is_turn = priority == Priority::Interactive || state.queued_interactive == 0;
+                if has_slot && has_budget && is_turn {
+                    *state.queued_mut(priority) -= 1;
+                    state.active += 1;
+                    ticket.admitted = true;
+                    // Batch work waiting on its turn can take the slots that are left
+                    if priority == Priority::Interactive && state.queued_interactive == 0 {
+                        self.notify.notify_waiters();
+                    }
+                    return Permit { lane: self.clone() };
+                }
+                // The budget frees up once the oldest usage leaves the window
+                state.usage.front().map(|(at, _)| *at + TOKEN_WINDOW)
+            };
+            match retry_at {
+                Some(at) => {
+                    tokio::select! {
+                        _ = &mut notified => {}
+                        _ = tokio::time::sleep_until(at) => {}
+                    }
+                }
+                None => notified.await,
+            }
+        }
+    }
+
+    fn record(&self, tokens: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .usage
+            .push_back((Instant::now(), tokens));
+    }
+}
+
+struct Ticket<'a> {
+    lane: &'a Lane,
+    priority: Priority,
+    admitted: bool,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        if self.admitted {
+            return;
+        }
+        *self.lane.state.lock().unwrap().queued_mut(self.priority) -= 1;
+        self.lane.notify.notify_waiters();
+    }
+}
+
+/// Holds a concurrency slot in a lane until dropped.
+struct Permit {
+    lane: Arc<Lane>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.lane.state.lock().unwrap().active -= 1;
+        self.lane.notify.notify_waiters();
+    }
+}
+
+/// Shared scheduler in front of an [`LLMApi`], enforces the limits per backend/model.
+pub struct Scheduler {
+    backend: Api,
+    inner: Arc<dyn LLMApi>,
+    default_limits: LaneLimits,
+    overrides: HashMap<String, LaneLimits>,
+    lanes: Mutex<HashMap<String, Arc<Lane>>>,
+}
+
+impl Scheduler {
+    pub fn new(backend: Api, inner: Arc<dyn LLMApi>) -> Self {
+        Self {
+            backend,
+            inner,
+            default_limits: LaneLimits::default(),
+            overrides: HashMap::new(),
+            lanes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_default_limits(mut self, limits: LaneLimits) -> Self {
+        self.default_limits = limits;
+        self
+    }
+
+    pub fn with_model_limits(mut self, model: &str, limits: LaneLimits) -> Self {
+        self.overrides.insert(model.into(), limits);
+        self
+    }
+
+    fn lane(&self, model: &str) -> Arc<Lane> {
+        self.lanes
+            .lock()
+            .unwrap()
+            .entry(model.into())
+            .or_insert_with(|| {
+                let limits = self
+                    .overrides
+                    .get(model)
+                    .cloned()
+                    .unwrap_or_else(|| self.default_limits.clone());
+                Arc::new(Lane::new(limits))
+            })
+            .clone()
+    }
+
+    /// Queue depth and usage of every lane that has seen traffic.
+    pub fn metrics(&self) -> Vec<LaneMetrics> {
+        let now = Instant::now();
+        let mut metrics = self
+            .lanes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(model, lane)| {
+                let mut state = lane.state.lock().unwrap();
+                LaneMetrics {
+                    backend: self.backend.clone(),
+                    model: model.clone(),
+                    active: state.active,
+                    queued_interactive: state.queued_interactive,
+                    queued_batch: state.queued_batch,
+                    tokens_last_minute: state.tokens_last_minute(now),
+                    max_concurrency: lane.limits.max_concurrency,
+                    tokens_per_minute: lane.limits.tokens_per_minute,
+                }
+            })
+            .collect::<Vec<_>>();
+        metrics.sort_by(|a, b| a.model.cmp(&b.model));
+        metrics
+    }
+
+    async fn scheduled<F, Fut>(
+        &self,
+        params: &GenerationParams,
+        generate: F,
+    ) -> Result<GenerationResult>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<GenerationResult>>,
+    {
+        let lane = self.lane(&params.model);
+        let _permit = lane.acquire(params.priority).await;
+        debug!(
+            "Acquired {:?} slot for model {}",
+            params.priority, params.model
+        );
+        let Ok(res) = timeout(lane.limits.request_timeout, generate()).await else {
+            warn!("Generation for model {} timed out", params.model);
+            return Err(FinanalizeError::LLMTimeout(params.model.clone()));
+        };
+        let res = res?;
+        lane.record(res.prompt_token_count + res.generated_token_count);
+        Ok(res)
+    }
+}
+
+#[async_trait]
+impl LLMApi for Scheduler {
+    async fn generate(
+        &self,
+        params: &GenerationParams,
+        prompt: String,
+    ) -> Result<GenerationResult> {
+        self.scheduled(params, || self.inner.generate(params, prompt))
+            .await
+    }
+
+    async fn generate_json(
+        &self,
+        params: &GenerationParams,
+        prompt: String,
+        json_schema: String,
+    ) -> Result<GenerationResult> {
+        self.scheduled(params, || {
+            self.inner.generate_json(params, prompt, json_schema)
+        })
+        .await
+    }
+
+    async fn embed(&self, text: String) -> Result<Vec<f32>> {
+        let lane = self.lane(EMBED_LANE);
+        let _permit = lane.acquire(Priority::Batch).await;
+        let embedding = self.inner.embed(text).await;
+        // Embeddings are tiny, count them as a single token
+        lane.record(1);
+        embedding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::llm::GenerationCaching;
+    use tokio::time::sleep;
+
+    #[derive(Default)]
+    struct MockApi {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+        order: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl LLMApi for MockApi {
+        async fn generate(
+            &self,
+            _params: &GenerationParams,
+            prompt: String,
+        ) -> Result<GenerationResult> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            self.order.lock().unwrap().push(prompt.clone());
+            sleep(Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(GenerationResult {
+                generated: prompt,
+                api: Api::Ollama,
+                prompt_token_count: 10,
+                generated_token_count: 10,
+                caching: GenerationCaching::None,
+                total_duration_us: 0,
+                cache_hit: false,
+            })
+        }
+
+        async fn generate_json(
+            &self,
+            params: &GenerationParams,
+            prompt: String,
+            _json_schema: String,
+        ) -> Result<GenerationResult> {
+            self.generate(params, prompt).await
+        }
+
+        async fn embed(&self, _text: String) -> Result<Vec<f32>> {
+            Ok(vec![])
+        }
+    }
+
+    fn limits(max_concurrency: usize) -> LaneLimits {
+        LaneLimits {
+            max_concurrency,
+            tokens_per_minute: None,
+            request_timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrency() {
+        let mock = Arc::new(MockApi::default());
+        let scheduler =
+            Arc::new(Scheduler::new(Api::Ollama, mock.clone()).with_default_limits(limits(2)));
+        let mut handles = vec![];
+        for i in 0..6 {
+            let scheduler = scheduler.clone();
+            handles.push(tokio::spawn(async move {
+                scheduler
+                    .generate(&GenerationParams::default(), i.to_string())
+                    .await
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+        assert_eq!(mock.peak.load(Ordering::SeqCst), 2);
+        let metrics = scheduler.metrics();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].active, 0);
+        assert_eq!(metrics[0].tokens_last_minute, 6 * 20);
+    }
+
+    #[tokio::test]
+    async fn test_interactive_before_batch() {
+        let mock = Arc::new(MockApi::default());
+        let scheduler =
+            Arc::new(Scheduler::new(Api::Ollama, mock.clone()).with_default_limits(limits(1)));
+        let mut handles = vec![];
+        for (name, priority) in [
+            ("first", Priority::Batch),
+            ("batch", Priority::Batch),
+            ("interactive", Priority::Interactive),
+        ] {
+            let scheduler = scheduler.clone();
+            let params = GenerationParams {
+                priority,
+                ..Default::default()
+            };
+            handles.push(tokio::spawn(async move {
+                scheduler.generate(&params, name.to_string()).await
+            }));
+            sleep(Duration::from_millis(5)).await;
+        }
+        let metrics = scheduler.metrics();
+        assert_eq!(metrics[0].queued_batch, 1);
+        assert_eq!(metrics[0].queued_interactive, 1);
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+        assert_eq!(
+            *mock.order.lock().unwrap(),
+            vec!["first", "interactive", "batch"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_after_interactive() {
+        let lane = Arc::new(Lane::new(limits(2)));
+        let first = lane.acquire(Priority::Batch).await;
+        let second = lane.acquire(Priority::Batch).await;
+        let batch = tokio::spawn({
+            let lane = lane.clone();
+            async move { lane.acquire(Priority::Batch).await }
+        });
+        sleep(Duration::from_millis(5)).await;
+        let interactive = tokio::spawn({
+            let lane = lane.clone();
+            async move { lane.acquire(Priority::Interactive).await }
+        });
+        sleep(Duration::from_millis(5)).await;
+        // Both slots free up at once, the batch request wakes first but it isn't its turn
+        drop(first);
+        drop(second);
+        let _interactive = interactive.await.unwrap();
+        let batch = timeout(Duration::from_millis(100), batch).await;
+        assert!(batch.is_ok());
+        assert_eq!(lane.state.lock().unwrap().active, 2);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_per_minute() {
+        let mock = Arc::new(MockApi::default());
+        let scheduler = Scheduler::new(Api::Ollama, mock.clone()).with_default_limits(LaneLimits {
+            tokens_per_minute: Some(20),
+            ..limits(4)
+        });
+        scheduler
+            .generate(&GenerationParams::default(), "spent".into())
+            .await
+            .unwrap();
+        let blocked = timeout(
+            Duration::from_millis(100),
+            scheduler.generate(&GenerationParams::default(), "blocked".into()),
+        )
+        .await;
+        assert!(blocked.is_err());
+        let metrics = scheduler.metrics();
+        assert_eq!(metrics[0].queued_batch, 0);
+        assert_eq!(metrics[0].tokens_last_minute, 20);
+    }
+}
@@ -9,6 +9,8 @@ use actix_web::{
 use api::{
     v1::{
         auth::{login, logout, me, refresh, register},
+        documents::upload_documents,
+        metrics::get_llm_metrics,
         report::{create_report, get_live_report, get_preview, get_report, get_reports, retry},
     },
     ApiResponse,
@@ -44,11 +46,14 @@ mod rabbitmq;
 #[allow(dead_code)]
 mod rag;
 #[allow(dead_code)]
+mod scraping;
+#[allow(dead_code)]
 mod search;
 #[allow(dead_code)]
 mod sec;
 #[allow(dead_code)]
 mod tasks;
+mod uploads;
 #[allow(dead_code)]
 mod workflow;
 #[allow(dead_code)]
@@ -58,6 +63,7 @@ async fn main() -> Result<()> {
     env_logger::init();
 
     db::init().await?;
+    search::local::LocalIndex::init().await?;
 
     let token_factory: TokenFactory = "secret".into();
 
@@ -102,13 +108,15 @@ async fn main() -> Result<()> {
                     .service(logout)
                     .service(me)
                     .service(create_report)
+                    .service(upload_documents)
                     .service(retry)
                     .service(get_report)
                     .service(get_reports)
                     .service(get_wallet_balance)
                     .service(get_wallet_transactions)
                     .service(add_credits)
-                    .service(buy_report),
+                    .service(buy_report)
+                    .service(get_llm_metrics),
             )
             .service(
                 web::scope("/api/v1/unprotected")
@@ -1,22 +1,51 @@
-use crate::api::v1::report::{ReportModel, ReportSize};
-use crate::extractors::Data;
+use std::collections::HashMap;
+
+use crate::api::v1::report::{ReportLanguage, ReportModel, ReportSize};
+use crate::extractors::{Data, Figure};
 use crate::llm::GenerationResult;
+use crate::search::{options::SearchFilters, SearchResult};
+use crate::uploads::Upload;
 use crate::workflow::job::answer_questions::models::QuestionAnswer;
 use crate::workflow::job::classify_sources::models::ClassifiedSource;
 // use crate::workflow::job::generate_graphs::models::{GraphFileOutput, TableOutput};
 // use crate::workflow::job::generate_visualizations::models::Visualization;
 // use crate::workflow::job::graph_identifier::models::GraphIdentifierOutput;
+use crate::prelude::StdResult;
 use crate::workflow::{
     job::{
-        chunk_content::models::Chunk, index_chunks::models::EmbeddedChunk,
-        validation::models::ValidationOutput,
+        chunk_content::models::Chunk, include_figures::models::ReportFigure,
+        index_chunks::models::EmbeddedChunk, validation::models::ValidationOutput,
     },
     JobType,
 };
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
 use surrealdb::sql::Thing;
 
+/// A search result as stored, older reports only have its URL.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredSearchResult {
+    Url(String),
+    Result(SearchResult),
+}
+
+fn search_results<'de, D>(deserializer: D) -> StdResult<Option<Vec<SearchResult>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let stored: Option<Vec<StoredSearchResult>> = Option::deserialize(deserializer)?;
+    Ok(stored.map(|results| {
+        results
+            .into_iter()
+            .map(|result| match result {
+                StoredSearchResult::Url(url) => SearchResult::from(url.as_str()),
+                StoredSearchResult::Result(result) => result,
+            })
+            .collect()
+    }))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: String,
@@ -63,6 +92,8 @@ pub struct SurrealDBReport {
     pub status: JobType,
     pub size: ReportSize,
     pub model: ReportModel,
+    #[serde(default)]
+    pub language: ReportLanguage,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub generation_results: Vec<GenerationResult>,
@@ -76,6 +107,7 @@ impl From<SurrealDBReport> for Report {
             status: report.status,
             size: report.size,
             model: report.model,
+            language: report.language,
             created_at: report.created_at.to_utc(),
             updated_at: report.updated_at.to_utc(),
             generation_results: report.generation_results,
@@ -105,6 +137,8 @@ pub struct Report {
     pub status: JobType,
     pub size: ReportSize,
     pub model: ReportModel,
+    #[serde(default)]
+    pub language: ReportLanguage,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub generation_results: Vec<GenerationResult>,
@@ -116,22 +150,39 @@ pub struct ReportCreation {
     pub status: JobType,
     pub size: ReportSize,
     pub model: ReportModel,
+    #[serde(default)]
+    pub language: ReportLanguage,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub generation_results: Vec<GenerationResult>,
+    pub documents: Option<Vec<Upload>>,
+    pub max_source_age: Option<u32>,
+    pub search_filters: Option<SearchFilters>,
 }
 
 impl ReportCreation {
-    pub fn new(user_input: String, size: ReportSize, model: ReportModel) -> Self {
+    pub fn new(
+        user_input: String,
+        size: ReportSize,
+        model: ReportModel,
+        language: ReportLanguage,
+        documents: Vec<Upload>,
+        max_source_age: Option<u32>,
+        search_filters: Option<SearchFilters>,
+    ) -> Self {
         let now = Utc::now();
         ReportCreation {
             user_input,
             status: JobType::Pending,
             size,
             model,
+            language,
             created_at: now,
             updated_at: now,
             generation_results: Vec::new(),
+            documents: Some(documents),
+            max_source_age,
+            search_filters,
         }
     }
 }
@@ -143,6 +194,8 @@ pub struct FullSDBReport {
     pub status: JobType,
     pub size: ReportSize,
     pub model: ReportModel,
+    #[serde(default)]
+    pub language: ReportLanguage,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub generation_results: Vec<GenerationResult>,
@@ -153,17 +206,46 @@ pub struct FullSDBReport {
     pub sub_sections: Option<Vec<Vec<String>>>,
     pub sub_section_questions: Option<Vec<Vec<Vec<String>>>>,
     pub search_queries: Option<Vec<String>>,
-    pub search_urls: Option<Vec<String>>,
+    /// Reports stored before results had metadata only kept the URLs
+    #[serde(default, deserialize_with = "search_results")]
+    pub search_urls: Option<Vec<SearchResult>>,
+    /// The questions each search query was generated for
+    pub query_questions: Option<HashMap<String, Vec<String>>>,
+    /// The questions each search result was found for, keyed by normalized URL
+    pub url_questions: Option<HashMap<String, Vec<String>>>,
+    /// Documents the user uploaded to be used as sources
+    #[serde(default)]
+    pub documents: Option<Vec<Upload>>,
+    /// Sources published more than this many days before the report are left out
+    #[serde(default)]
+    pub max_source_age: Option<u32>,
+    /// Search depth and filters chosen for the report
+    #[serde(default)]
+    pub search_filters: Option<SearchFilters>,
+    #[serde(default)]
+    pub scrape_outcomes: Option<Vec<ScrapeOutcome>>,
     pub html_sources: Option<Vec<PreClassificationSource>>,
+    /// Publication dates found in the sources themselves, keyed by source URL
+    #[serde(default)]
+    pub source_dates: Option<HashMap<String, NaiveDate>>,
+    /// Sources dropped as near-duplicates, by URL of the duplicate to the URL of the source kept
+    #[serde(default)]
+    pub duplicate_sources: Option<HashMap<String, String>>,
     pub raw_sources: Option<Vec<PreClassificationSource>>,
     pub csv_sources: Option<Vec<String>>,
     pub data_sources: Option<Vec<Data>>,
+    /// Figures found in the main content of the scraped pages
+    #[serde(default)]
+    pub figures: Option<Vec<Figure>>,
     pub formatted_sources: Option<Vec<PreClassificationSource>>,
     pub sources: Option<Vec<ClassifiedSource>>,
     pub chunks: Option<Vec<Chunk>>,
     pub chunk_embeddings: Option<Vec<EmbeddedChunk>>,
     pub question_answer_pairs: Option<Vec<Vec<Vec<QuestionAnswer>>>>,
     pub sub_section_contents: Option<Vec<Vec<String>>>,
+    /// Downloaded figures and the subsections they go in
+    #[serde(default)]
+    pub report_figures: Option<Vec<ReportFigure>>,
     pub report: Option<String>,
     pub preview: Option<String>,
     // pub visuals: Option<Vec<Visualization>>,
@@ -181,6 +263,7 @@ impl From<FullSDBReport> for FullReport {
             status: report.status,
             size: report.size,
             model: report.model,
+            language: report.language,
             created_at: report.created_at.to_utc(),
             updated_at: report.updated_at.to_utc(),
             generation_results: report.generation_results,
@@ -192,16 +275,26 @@ impl From<FullSDBReport> for FullReport {
             sub_section_questions: report.sub_section_questions,
             search_queries: report.search_queries,
             search_urls: report.search_urls,
+            query_questions: report.query_questions,
+            url_questions: report.url_questions,
+            documents: report.documents,
+            max_source_age: report.max_source_age,
+            search_filters: report.search_filters,
+            scrape_outcomes: report.scrape_outcomes,
             html_sources: report.html_sources,
+            source_dates: report.source_dates,
+            duplicate_sources: report.duplicate_sources,
             md_sources: report.raw_sources,
             csv_sources: report.csv_sources,
             data_sources: report.data_sources,
+            figures: report.figures,
             formatted_sources: report.formatted_sources,
             sources: report.sources,
             chunks: report.chunks,
             chunk_embeddings: report.chunk_embeddings,
             question_answer_pairs: report.question_answer_pairs,
             sub_section_contents: report.sub_section_contents,
+            report_figures: report.report_figures,
             report: report.report,
             preview: report.preview,
             // visuals: report.visuals,
@@ -219,6 +312,41 @@ pub struct PreClassificationSource {
     pub content: String,
 }
 
+/// What happened to a URL found by search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScrapeStatus {
+    Success,
+    Timeout,
+    HttpError(u16),
+    /// Refused by bot protection
+    Blocked,
+    DisallowedByRobots,
+    Untrusted,
+    Unsupported,
+    ExtractionFailed,
+    BrowserFailed,
+    /// Published before the freshness window of the report
+    Stale,
+    /// A near-duplicate of a more authoritative source
+    Duplicate,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScrapeOutcome {
+    pub url: String,
+    pub status: ScrapeStatus,
+}
+
+impl ScrapeOutcome {
+    pub fn new(url: impl Into<String>, status: ScrapeStatus) -> Self {
+        ScrapeOutcome {
+            url: url.into(),
+            status,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FullReport {
     pub id: String,
@@ -226,6 +354,8 @@ pub struct FullReport {
     pub status: JobType,
     pub size: ReportSize,
     pub model: ReportModel,
+    #[serde(default)]
+    pub language: ReportLanguage,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub generation_results: Vec<GenerationResult>,
@@ -236,17 +366,47 @@ pub struct FullReport {
     pub sub_sections: Option<Vec<Vec<String>>>,
     pub sub_section_questions: Option<Vec<Vec<Vec<String>>>>,
     pub search_queries: Option<Vec<String>>,
-    pub search_urls: Option<Vec<String>>,
+    /// Reports stored before results had metadata only kept the URLs
+    #[serde(default, deserialize_with = "search_results")]
+    pub search_urls: Option<Vec<SearchResult>>,
+    /// The questions each search query was generated for
+    pub query_questions: Option<HashMap<String, Vec<String>>>,
+    /// The questions each search result was found for, keyed by normalized URL
+    pub url_questions: Option<HashMap<String, Vec<String>>>,
+    /// Documents the user uploaded to be used as sources
+    #[serde(default)]
+    pub documents: Option<Vec<Upload>>,
+    /// Sources published more than this many days before the report are left out
+    #[serde(default)]
+    pub max_source_age: Option<u32>,
+    /// Search depth and filters chosen for the report
+    #[serde(default)]
+    pub search_filters: Option<SearchFilters>,
+    /// What happened to every URL that was considered as a source
+    #[serde(default)]
+    pub scrape_outcomes: Option<Vec<ScrapeOutcome>>,
     pub html_sources: Option<Vec<PreClassificationSource>>,
+    /// Publication dates found in the sources themselves, keyed by source URL
+    #[serde(default)]
+    pub source_dates: Option<HashMap<String, NaiveDate>>,
+    /// Sources dropped as near-duplicates, by URL of the duplicate to the URL of the source kept
+    #[serde(default)]
+    pub duplicate_sources: Option<HashMap<String, String>>,
     pub md_sources: Option<Vec<PreClassificationSource>>,
     pub csv_sources: Option<Vec<String>>,
     pub data_sources: Option<Vec<Data>>,
+    /// Figures found in the main content of the scraped pages
+    #[serde(default)]
+    pub figures: Option<Vec<Figure>>,
     pub formatted_sources: Option<Vec<PreClassificationSource>>,
     pub sources: Option<Vec<ClassifiedSource>>,
     pub chunks: Option<Vec<Chunk>>,
     pub chunk_embeddings: Option<Vec<EmbeddedChunk>>,
     pub question_answer_pairs: Option<Vec<Vec<Vec<QuestionAnswer>>>>,
     pub sub_section_contents: Option<Vec<Vec<String>>>,
+    /// Downloaded figures and the subsections they go in
+    #[serde(default)]
+    pub report_figures: Option<Vec<ReportFigure>>,
     pub report: Option<String>,
     pub preview: Option<String>,
     // pub visuals: Option<Vec<Visualization>>,
@@ -262,6 +422,7 @@ pub struct FrontendReport {
     pub status: JobType,
     pub size: ReportSize,
     pub model: ReportModel,
+    pub language: ReportLanguage,
     pub error: Option<String>,
     pub valid: Option<bool>,
     pub title: Option<String>,
@@ -269,8 +430,8 @@ pub struct FrontendReport {
 
 #[cfg(test)]
 mod tests {
-    use super::{FullReport, PreClassificationSource};
-    use crate::api::v1::report::{ReportModel, ReportSize};
+    use super::{FullReport, PreClassificationSource, SearchResult};
+    use crate::api::v1::report::{ReportLanguage, ReportModel, ReportSize};
     use crate::workflow::job::classify_sources::models::ClassifiedSource;
     // use crate::workflow::job::generate_graphs::models::{GraphFileOutput, TableOutput};
     use crate::workflow::{
@@ -287,6 +448,7 @@ mod tests {
                 user_input,
                 size: ReportSize::Small,
                 model: ReportModel::Llama,
+                language: ReportLanguage::English,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 generation_results: vec![],
@@ -299,11 +461,20 @@ mod tests {
                 sub_section_questions: None,
                 search_queries: None,
                 search_urls: None,
+                query_questions: None,
+                url_questions: None,
+                documents: None,
+                max_source_age: None,
+                search_filters: None,
+                scrape_outcomes: None,
 
                 html_sources: None,
+                source_dates: None,
+                duplicate_sources: None,
                 md_sources: None,
                 csv_sources: None,
                 data_sources: None,
+                figures: None,
                 formatted_sources: None,
                 sources: None,
                 chunks: None,
@@ -311,6 +482,7 @@ mod tests {
 
                 question_answer_pairs: None,
                 sub_section_contents: None,
+                report_figures: None,
 
                 report: None,
                 preview: None,
@@ -355,7 +527,7 @@ mod tests {
             self
         }
 
-        pub fn with_search_results(mut self, search_results: Vec<String>) -> Self {
+        pub fn with_search_results(mut self, search_results: Vec<SearchResult>) -> Self {
             self.search_urls = Some(search_results);
             self
         }
@@ -398,4 +570,26 @@ mod tests {
         //     self
         // }
     }
+
+    #[test]
+    fn test_search_results_as_urls() {
+        #[derive(serde::Deserialize)]
+        struct Stored {
+            #[serde(default, deserialize_with = "super::search_results")]
+            search_urls: Option<Vec<SearchResult>>,
+        }
+        let stored: Stored = serde_json::from_str(
+            r#"{"search_urls": [
+                "https://a.com/1",
+                {"url": "https://b.com/2", "title": "B", "snippet": "", "engine": "brave",
+                 "published_date": null, "score": 0.5}
+            ]}"#,
+        )
+        .unwrap();
+        let results = stored.search_urls.unwrap();
+        assert_eq!(results[0], SearchResult::from("https://a.com/1"));
+        assert_eq!(results[1].title, "B");
+        let missing: Stored = serde_json::from_str("{}").unwrap();
+        assert_eq!(missing.search_urls, None);
+    }
 }
@@ -25,6 +25,16 @@ pub enum FinanalizeError {
 
     #[error("Scraper timed out on page: {0}")]
     ScraperTimemout(String),
+    #[error("Only {0} sources could be scraped")]
+    NotEnoughSources(usize),
+    #[error("Response too large: {0}")]
+    ResponseTooLarge(String),
+    #[error("Unsupported document: {0}")]
+    UnsupportedDocument(String),
+    #[error("PDF has no text layer: {0}")]
+    NoTextLayer(String),
+    #[error("LLM generation timed out for model: {0}")]
+    LLMTimeout(String),
 
     #[error("Some retry logic generated the following errors: {0:#?}")]
     MultipleErrors(Vec<FinanalizeError>),
@@ -64,6 +74,12 @@ pub enum FinanalizeError {
     Polars(#[from] polars::prelude::PolarsError),
     #[error("Xlsl error: {0}")]
     Excel(#[from] calamine::XlsxError),
+    #[error("Spreadsheet error: {0}")]
+    Spreadsheet(#[from] calamine::Error),
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("XML error: {0}")]
+    Xml(#[from] quick_xml::Error),
     #[error("Lopdf error: {0}")]
     LopdfError(#[from] lopdf::Error),
     #[error("Deadpool error: {0}")]
@@ -10,12 +10,26 @@ pub struct DistancedChunk {
     pub source_id: String,
     pub chunk: String,
     pub distance: f64,
+    /// Trust score of the source, filled in by the caller
+    #[serde(default)]
+    pub trust: f64,
 }
 
 static VECTOR_SEARCH_QUERY: &str = r#"
 SELECT report_id, source_id, chunk, vector::similarity::cosine(embeddings, $embedding) AS distance FROM embedded_chunk WHERE report_id = $report_id LIMIT 20;
 "#;
 
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
 pub async fn vector_search(report: Thing, query: String) -> Result<Vec<DistancedChunk>> {
     debug!("Searching for '{:#?}'", &query);
     let search_embed = API.clone().embed(query).await?;
@@ -37,6 +51,13 @@ mod tests {
     use super::*;
     use surrealdb::sql::Thing;
 
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+
     #[tokio::test]
     #[ignore = "Depends on external service"]
     async fn test_vector_search() {
@@ -0,0 +1,199 @@
+use std::{env, time::Duration};
+
+use deadpool::unmanaged::{Object, Pool};
+use fantoccini::{Client, ClientBuilder};
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use tokio::time::{sleep, timeout, Instant};
+
+use crate::prelude::*;
+
+use super::politeness::{POLITENESS, USER_AGENT};
+
+const DEFAULT_ADDRESSES: &str =
+    "http://localhost:4444,http://localhost:4445,http://localhost:4446,http://localhost:4447";
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Parses a comma-separated list of WebDriver URLs, ignoring empty entries.
+pub fn parse_addresses(addresses: &str) -> Vec<String> {
+    addresses
+        .split(',')
+        .map(|address| address.trim().trim_end_matches('/'))
+        .filter(|address| !address.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn env_secs(key: &str, default: u64) -> Duration {
+    Duration::from_secs(
+        env::var(key)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default),
+    )
+}
+
+/// One WebDriver endpoint and the session currently open on it, if any.
+pub struct Session {
+    address: String,
+    client: Option<Client>,
+    pages: usize,
+}
+
+impl Session {
+    fn new(address: String) -> Self {
+        Session {
+            address,
+            client: None,
+            pages: 0,
+        }
+    }
+
+    /// Returns a live client, replacing a session that stopped responding.
+    async fn client(&mut self) -> Result<Client> {
+        if let Some(client) = &self.client {
+            match timeout(HEALTH_CHECK_TIMEOUT, client.current_url()).await {
+                Ok(Ok(_)) => return Ok(client.clone()),
+                _ => {
+                    warn!(
+                        "Browser session on {} is unhealthy, recycling",
+                        self.address
+                    );
+                    self.close().await;
+                }
+            }
+        }
+        debug!("Opening browser session on {}", self.address);
+        let client = ClientBuilder::native()
+            .capabilities(
+                json!({
+                    "moz:firefoxOptions": {
+                        "args": ["--headless"],
+                        "prefs": {
+                            "general.useragent.override": USER_AGENT.as_str()
+                        }
+                    }
+                })
+                .as_object()
+                .unwrap()
+                .clone(),
+            )
+            .connect(&self.address)
+            .await?;
+        self.client = Some(client.clone());
+        Ok(client)
+    }
+
+    async fn close(&mut self) {
+        self.pages = 0;
+        if let Some(client) = self.client.take() {
+            if let Err(e) = client.close().await {
+                debug!("Failed to close browser session on {}: {}", self.address, e);
+            }
+        }
+    }
+}
+
+/// Long-lived browser sessions shared by every job, one per configured WebDriver.
+///
+/// Sessions are opened on first use and kept between reports. A session is replaced when it
+/// fails a health check, errors or times out on a page, or has loaded `recycle_after` pages.
+pub struct BrowserPool {
+    sessions: Pool<Session>,
+    page_timeout: Duration,
+    ready_timeout: Duration,
+    recycle_after: usize,
+}
+
+impl BrowserPool {
+    pub fn new(addresses: Vec<String>) -> Self {
+        BrowserPool {
+            sessions: Pool::from(addresses.into_iter().map(Session::new).collect::<Vec<_>>()),
+            page_timeout: env_secs("BROWSER_PAGE_TIMEOUT_SECS", 10),
+            ready_timeout: env_secs("BROWSER_READY_TIMEOUT_SECS", 5),
+            recycle_after: env::var("BROWSER_RECYCLE_AFTER")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(50),
+        }
+    }
+
+    /// Loads a page in the first free browser and returns its rendered HTML.
+    pub async fn scrape(&self, url: &str) -> Result<String> {
+        if self.sessions.status().max_size == 0 {
+            warn!("No WebDriver configured, cannot scrape {}", url);
+            return Err(FinanalizeError::InternalServerError);
+        }
+        let mut session = self.sessions.get().await?;
+        // Only waits for the host once a browser is free, so queued pages don't hold up plain
+        // fetches to the same host
+        let slot = POLITENESS.slot(url).await;
+        let result = self.load(&mut session, url).await;
+        drop(slot);
+        if result.is_err() || session.pages >= self.recycle_after {
+            // A timed out page can keep the browser busy, so start over with a fresh session
+            session.close().await;
+        }
+        result
+    }
+
+    async fn load(&self, session: &mut Object<Session>, url: &str) -> Result<String> {
+        let client = session.client().await?;
+        session.pages += 1;
+        match timeout(self.page_timeout, client.goto(url)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => return Err(FinanalizeError::ScraperTimemout(url.into())),
+        }
+        self.wait_until_ready(&client, url).await;
+        Ok(client.source().await?)
+    }
+
+    /// Waits for `document.readyState` to be complete, giving up after `ready_timeout`.
+    async fn wait_until_ready(&self, client: &Client, url: &str) {
+        let deadline = Instant::now() + self.ready_timeout;
+        while Instant::now() < deadline {
+            match client.execute("return document.readyState", vec![]).await {
+                Ok(Value::String(state)) if state == "complete" => return,
+                Ok(_) => sleep(READY_POLL_INTERVAL).await,
+                Err(_) => return,
+            }
+        }
+        debug!("Page not ready after {:?}: {}", self.ready_timeout, url);
+    }
+}
+
+pub static BROWSERS: Lazy<BrowserPool> = Lazy::new(|| {
+    let addresses =
+        parse_addresses(&env::var("WEBDRIVER_URLS").unwrap_or(DEFAULT_ADDRESSES.into()));
+    debug!("Browser pool: {:?}", addresses);
+    BrowserPool::new(addresses)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addresses() {
+        assert_eq!(
+            parse_addresses(" http://geckodriver:4444/, ,http://10.0.0.2:4444"),
+            vec!["http://geckodriver:4444", "http://10.0.0.2:4444"]
+        );
+        assert_eq!(parse_addresses(DEFAULT_ADDRESSES).len(), 4);
+        assert!(parse_addresses("").is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "Uses WebDriver (External Service)"]
+    async fn test_browser_pool_scrape() {
+        let pool = BrowserPool::new(parse_addresses(DEFAULT_ADDRESSES));
+        let html = pool.scrape("https://example.com").await.unwrap();
+        assert!(html.contains("Example Domain"));
+        // The session is reused for the next page
+        let html = pool.scrape("https://example.org").await.unwrap();
+        assert!(html.contains("Example Domain"));
+    }
+}
@@ -0,0 +1,232 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use log::warn;
+use reqwest::header::{HeaderMap, CACHE_CONTROL, CONTENT_TYPE, ETAG, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+
+use crate::{db::DB, prelude::*, search::normalize_url};
+
+const CACHE_TABLE: &str = "page_cache";
+/// Larger downloads are fetched again rather than stored in the database
+pub const MAX_CACHED_BYTES: usize = 20 * 1024 * 1024;
+/// Longer `max-age`s are cut down to this, servers rarely mean them
+const MAX_FRESHNESS: TimeDelta = TimeDelta::days(365);
+
+/// A downloaded page or document, shared between reports.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedPage {
+    /// After redirects
+    pub url: String,
+    pub content_type: Option<String>,
+    #[serde_as(as = "Base64")]
+    pub body: Vec<u8>,
+    pub fetched_at: DateTime<Utc>,
+    /// After this the page is revalidated before it is used again
+    pub expires_at: DateTime<Utc>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl CachedPage {
+    /// Builds a cache entry from a response, `None` when the server forbids storing it.
+    pub fn from_response(
+        url: String,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+        max_age: TimeDelta,
+    ) -> Option<Self> {
+        if body.len() > MAX_CACHED_BYTES {
+            return None;
+        }
+        let fetched_at = Utc::now();
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        Some(CachedPage {
+            url,
+            content_type: header(CONTENT_TYPE),
+            body,
+            fetched_at,
+            expires_at: expires_at(fetched_at, freshness(headers, max_age)?),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        })
+    }
+
+    /// Whether the page can be used without asking the server.
+    pub fn is_fresh(&self) -> bool {
+        Utc::now() < self.expires_at
+    }
+
+    /// Marks the page as confirmed unchanged by a `304 Not Modified`.
+    pub fn revalidated(mut self, headers: &HeaderMap, max_age: TimeDelta) -> Self {
+        self.fetched_at = Utc::now();
+        self.expires_at = expires_at(
+            self.fetched_at,
+            freshness(headers, max_age).unwrap_or_default(),
+        );
+        if let Some(etag) = headers.get(ETAG).and_then(|v| v.to_str().ok()) {
+            self.etag = Some(etag.to_string());
+        }
+        self
+    }
+}
+
+/// When a page fetched at `fetched_at` goes stale, right away when that can't be represented.
+fn expires_at(fetched_at: DateTime<Utc>, freshness: TimeDelta) -> DateTime<Utc> {
+    fetched_at
+        .checked_add_signed(freshness)
+        .unwrap_or(fetched_at)
+}
+
+/// How long a response stays fresh: `max-age` when the server sends one, otherwise `max_age`.
+///
+/// `no-store` returns `None`, `no-cache` makes it stale right away so it is always revalidated.
+/// A `max-age` is capped at a year, one that can't be read makes the response stale.
+pub fn freshness(headers: &HeaderMap, max_age: TimeDelta) -> Option<TimeDelta> {
+    let Some(cache_control) = headers.get(CACHE_CONTROL).and_then(|v| v.to_str().ok()) else {
+        return Some(max_age);
+    };
+    let mut freshness = max_age;
+    for directive in cache_control.split(',').map(|d| d.trim().to_lowercase()) {
+        if directive == "no-store" {
+            return None;
+        }
+        if directive == "no-cache" {
+            return Some(TimeDelta::zero());
+        }
+        if let Some(seconds) = directive.strip_prefix("max-age=") {
+            freshness = seconds
+                .parse()
+                .ok()
+                .and_then(TimeDelta::try_seconds)
+                .map_or(TimeDelta::zero(), |seconds| {
+                    seconds.clamp(TimeDelta::zero(), MAX_FRESHNESS)
+                });
+        }
+    }
+    Some(freshness)
+}
+
+/// Looks up a page by normalized URL. Without a database nothing is cached.
+pub async fn load(url: &str) -> Option<CachedPage> {
+    let db = DB.get()?;
+    match db.select((CACHE_TABLE, normalize_url(url))).await {
+        Ok(page) => page,
+        Err(e) => {
+            warn!("Failed to read page cache for {}: {}", url, e);
+            None
+        }
+    }
+}
+
+/// Stores a page under the normalized URL it was requested with.
+pub async fn store(url: &str, page: CachedPage) -> Result<()> {
+    let Some(db) = DB.get() else {
+        return Ok(());
+    };
+    let _: Option<CachedPage> = db
+        .upsert((CACHE_TABLE, normalize_url(url)))
+        .content(page)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(cache_control: Option<&'static str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        headers.insert(ETAG, HeaderValue::from_static("\"v1\""));
+        if let Some(cache_control) = cache_control {
+            headers.insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_freshness() {
+        let day = TimeDelta::days(1);
+        assert_eq!(freshness(&headers(None), day), Some(day));
+        assert_eq!(
+            freshness(&headers(Some("public, max-age=600")), day),
+            Some(TimeDelta::minutes(10))
+        );
+        assert_eq!(
+            freshness(&headers(Some("no-cache")), day),
+            Some(TimeDelta::zero())
+        );
+        assert_eq!(freshness(&headers(Some("private, no-store")), day), None);
+    }
+
+    #[test]
+    fn test_freshness_huge_max_age() {
+        let day = TimeDelta::days(1);
+        assert_eq!(
+            freshness(&headers(Some("max-age=10000000000000")), day),
+            Some(MAX_FRESHNESS)
+        );
+        assert_eq!(
+            freshness(&headers(Some("max-age=9223372036854775807")), day),
+            Some(TimeDelta::zero())
+        );
+        assert_eq!(
+            freshness(&headers(Some("max-age=99999999999999999999")), day),
+            Some(TimeDelta::zero())
+        );
+        let page = CachedPage::from_response(
+            "https://cnbc.com/apple".into(),
+            &headers(Some("max-age=10000000000000")),
+            b"<html></html>".to_vec(),
+            day,
+        )
+        .unwrap();
+        assert!(page.is_fresh());
+        assert_eq!(expires_at(page.fetched_at, TimeDelta::MAX), page.fetched_at);
+    }
+
+    #[test]
+    fn test_cached_page_from_response() {
+        let page = CachedPage::from_response(
+            "https://cnbc.com/apple".into(),
+            &headers(None),
+            b"<html></html>".to_vec(),
+            TimeDelta::hours(1),
+        )
+        .unwrap();
+        assert!(page.is_fresh());
+        assert_eq!(page.content_type.as_deref(), Some("text/html"));
+        assert_eq!(page.etag.as_deref(), Some("\"v1\""));
+
+        let stale = page.revalidated(&headers(Some("no-cache")), TimeDelta::hours(1));
+        assert!(!stale.is_fresh());
+        assert!(CachedPage::from_response(
+            "https://cnbc.com/apple".into(),
+            &headers(Some("no-store")),
+            vec![],
+            TimeDelta::hours(1),
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_cached_page_serialization() {
+        let page = CachedPage::from_response(
+            "https://a.com/report.pdf".into(),
+            &HeaderMap::new(),
+            b"%PDF-\x00\xff".to_vec(),
+            TimeDelta::hours(1),
+        )
+        .unwrap();
+        let json = serde_json::to_value(&page).unwrap();
+        assert_eq!(json["body"], "JVBERi0A/w==");
+        assert_eq!(serde_json::from_value::<CachedPage>(json).unwrap(), page);
+    }
+}
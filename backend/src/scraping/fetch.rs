@@ -0,0 +1,599 @@
+use std::{env, time::Duration};
+
+use chrono::TimeDelta;
+use log::{debug, warn};
+use reqwest::{
+    header::{CONTENT_TYPE, IF_MODIFIED_SINCE, IF_NONE_MATCH},
+    Client, StatusCode, Url,
+};
+use scraper::{Html, Selector};
+
+use crate::prelude::*;
+
+use super::{
+    cache::{self, CachedPage},
+    politeness::{Politeness, POLITENESS, USER_AGENT},
+};
+
+const ODS_MIME: &[u8] = b"application/vnd.oasis.opendocument.spreadsheet";
+
+/// The name of the workbook stream in an .xls file, in UTF-16
+const WORKBOOK_STREAM: &[u8] = b"W\0o\0r\0k\0b\0o\0o\0k\0";
+
+/// What a fetched document is, which decides the extractor it goes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Html,
+    Pdf,
+    Csv,
+    Spreadsheet,
+    /// Word documents
+    WordProcessing,
+    Presentation,
+    Text,
+    Other,
+}
+
+impl DocumentKind {
+    /// The MIME type extractors are registered for
+    pub fn mime(&self) -> Option<&'static str> {
+        match self {
+            DocumentKind::Html => Some("text/html"),
+            DocumentKind::Pdf => Some("application/pdf"),
+            DocumentKind::Csv => Some("text/csv"),
+            // Workbooks are read whatever their format
+            DocumentKind::Spreadsheet => {
+                Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
+            }
+            DocumentKind::WordProcessing => {
+                Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
+            }
+            DocumentKind::Presentation => {
+                Some("application/vnd.openxmlformats-officedocument.presentationml.presentation")
+            }
+            DocumentKind::Text => Some("text/plain"),
+            DocumentKind::Other => None,
+        }
+    }
+
+    fn from_mime(mime: &str) -> Option<Self> {
+        match mime {
+            "text/html" | "application/xhtml+xml" => Some(DocumentKind::Html),
+            "application/pdf" => Some(DocumentKind::Pdf),
+            "text/csv" | "application/csv" => Some(DocumentKind::Csv),
+            "text/markdown" => Some(DocumentKind::Text),
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            | "application/vnd.ms-excel"
+            | "application/vnd.oasis.opendocument.spreadsheet" => Some(DocumentKind::Spreadsheet),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+                Some(DocumentKind::WordProcessing)
+            }
+            "application/vnd.openxmlformats-officedocument.presentationml.presentation" => {
+                Some(DocumentKind::Presentation)
+            }
+            _ => None,
+        }
+    }
+
+    fn from_extension(url: &str) -> Option<Self> {
+        let path = Url::parse(url).ok()?.path().to_lowercase();
+        let (_, extension) = path.rsplit_once('.')?;
+        match extension {
+            "html" | "htm" => Some(DocumentKind::Html),
+            "pdf" => Some(DocumentKind::Pdf),
+            "csv" => Some(DocumentKind::Csv),
+            "xlsx" | "xlsm" | "xls" | "ods" => Some(DocumentKind::Spreadsheet),
+            "docx" | "docm" => Some(DocumentKind::WordProcessing),
+            "pptx" | "pptm" => Some(DocumentKind::Presentation),
+            "txt" | "md" => Some(DocumentKind::Text),
+            _ => None,
+        }
+    }
+
+    /// Binary formats have a signature, which beats a mislabeled header
+    fn from_magic(body: &[u8]) -> Option<Self> {
+        if body.starts_with(b"%PDF-") {
+            return Some(DocumentKind::Pdf);
+        }
+        // Office files are zip archives, Word documents keep their parts under `word/`,
+        // presentations under `ppt/` and spreadsheets under `xl/`. OpenDocument names its
+        // type in the first entry
+        let zip = body.starts_with(b"PK\x03\x04");
+        if zip && body.windows(5).any(|w| w == b"word/") {
+            return Some(DocumentKind::WordProcessing);
+        }
+        if zip && body.windows(4).any(|w| w == b"ppt/") {
+            return Some(DocumentKind::Presentation);
+        }
+        if zip
+            && (body.windows(3).any(|w| w == b"xl/")
+                || body
+                    .windows(ODS_MIME.len())
+                    .take(100)
+                    .any(|w| w == ODS_MIME))
+        {
+            return Some(DocumentKind::Spreadsheet);
+        }
+        // Legacy .xls files are compound documents with a `Workbook` stream
+        if body.starts_with(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1")
+            && body
+                .windows(WORKBOOK_STREAM.len())
+                .any(|w| w == WORKBOOK_STREAM)
+        {
+            return Some(DocumentKind::Spreadsheet);
+        }
+        None
+    }
+
+    fn from_text(body: &[u8]) -> Self {
+        let Ok(text) = std::str::from_utf8(body) else {
+            return DocumentKind::Other;
+        };
+        let start: String = text.trim_start().chars().take(15).collect();
+        let start = start.to_lowercase();
+        if start.starts_with("<!doctype html") || start.starts_with("<html") {
+            DocumentKind::Html
+        } else {
+            DocumentKind::Text
+        }
+    }
+}
+
+/// Decides the kind from magic bytes, then the `Content-Type` header, then the URL's extension
+/// and finally by looking at the content.
+///
+/// Servers often send downloads as `application/octet-stream` or `text/plain`, and sometimes
+/// serve PDFs as `text/html`.
+pub fn sniff(content_type: Option<&str>, url: &str, body: &[u8]) -> DocumentKind {
+    let mime = content_type
+        .and_then(|c| c.split(';').next())
+        .map(|c| c.trim().to_lowercase());
+    DocumentKind::from_magic(body)
+        .or_else(|| mime.as_deref().and_then(DocumentKind::from_mime))
+        .or_else(|| DocumentKind::from_extension(url))
+        .unwrap_or_else(|| DocumentKind::from_text(body))
+}
+
+/// Links in a page to documents worth ingesting, such as annual reports and data files.
+pub fn document_links(html: &str, base_url: &str) -> Vec<String> {
+    let Ok(base) = Url::parse(base_url) else {
+        return vec![];
+    };
+    let Ok(selector) = Selector::parse("a[href]") else {
+        return vec![];
+    };
+    let mut links = vec![];
+    for link in Html::parse_document(html).select(&selector) {
+        let Some(url) = link
+            .value()
+            .attr("href")
+            .and_then(|href| base.join(href.trim()).ok())
+        else {
+            continue;
+        };
+        let url = url.to_string();
+        let is_document = matches!(
+            DocumentKind::from_extension(&url),
+            Some(
+                DocumentKind::Pdf
+                    | DocumentKind::Csv
+                    | DocumentKind::Spreadsheet
+                    | DocumentKind::WordProcessing
+                    | DocumentKind::Presentation
+            )
+        );
+        if is_document && url.starts_with("http") && !links.contains(&url) {
+            links.push(url);
+        }
+    }
+    links
+}
+
+/// Phrases of pages that only render with JavaScript, or bot challenges that need a real browser
+const BROWSER_MARKERS: &[&str] = &[
+    "enable javascript",
+    "javascript is disabled",
+    "javascript is required",
+    "checking your browser",
+    "just a moment...",
+];
+
+/// Pages with less visible text than this are probably rendered client side
+const MIN_STATIC_TEXT: usize = 500;
+
+/// Whether a page fetched without a browser is too empty to be the real content.
+pub fn needs_browser(html: &str) -> bool {
+    let lower = html.to_lowercase();
+    if BROWSER_MARKERS.iter().any(|marker| lower.contains(marker)) {
+        return true;
+    }
+    let document = Html::parse_document(html);
+    let Ok(body) = Selector::parse("body") else {
+        return false;
+    };
+    let text_length: usize = document
+        .select(&body)
+        .flat_map(|body| body.descendants())
+        .filter_map(|node| {
+            let text = node.value().as_text()?;
+            let parent = node.parent()?.value().as_element()?.name();
+            (!matches!(parent, "script" | "style" | "noscript" | "template"))
+                .then(|| text.trim().len())
+        })
+        .sum();
+    text_length < MIN_STATIC_TEXT
+}
+
+#[derive(Debug, Clone)]
+pub struct Fetched {
+    /// After redirects
+    pub url: String,
+    pub kind: DocumentKind,
+    pub body: Vec<u8>,
+}
+
+impl From<CachedPage> for Fetched {
+    fn from(page: CachedPage) -> Self {
+        let kind = sniff(page.content_type.as_deref(), &page.url, &page.body);
+        Fetched {
+            url: page.url,
+            kind,
+            body: page.body,
+        }
+    }
+}
+
+/// Plain HTTP client, used before falling back to a headless browser.
+pub struct Fetcher {
+    client: Client,
+    /// How long a cached page is used without revalidating, unless the server says otherwise
+    max_age: TimeDelta,
+    /// Larger responses are abandoned instead of read into memory
+    max_bytes: usize,
+    /// Rate limits the requests to every host, none are limited without it
+    politeness: Option<&'static Politeness>,
+}
+
+impl Fetcher {
+    /// Configured with `FETCH_TIMEOUT_SECS` (10 by default), `PAGE_CACHE_MAX_AGE_SECS`
+    /// (a day by default) and `FETCH_MAX_BYTES` (50MB by default)
+    pub fn new() -> Self {
+        let timeout = env::var("FETCH_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10);
+        let max_age = env::var("PAGE_CACHE_MAX_AGE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(24 * 60 * 60);
+        let max_bytes = env::var("FETCH_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(50 * 1024 * 1024);
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .redirect(reqwest::redirect::Policy::limited(5))
+            .user_agent(USER_AGENT.as_str())
+            .build()
+            .expect("Failed to build HTTP client");
+        Fetcher {
+            client,
+            max_age: TimeDelta::seconds(max_age),
+            max_bytes,
+            politeness: Some(&POLITENESS),
+        }
+    }
+
+    pub fn with_politeness(mut self, politeness: Option<&'static Politeness>) -> Self {
+        self.politeness = politeness;
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Fetches a document through the shared page cache.
+    pub async fn fetch(&self, url: &str) -> Result<Fetched> {
+        let cached = cache::load(url).await;
+        let (fetched, update) = self.fetch_with_cache(url, cached).await?;
+        if let Some(page) = update {
+            if let Err(e) = cache::store(url, page).await {
+                warn!("Failed to cache {}: {}", url, e);
+            }
+        }
+        Ok(fetched)
+    }
+
+    /// Uses a fresh cached page as is and revalidates a stale one with a conditional GET.
+    ///
+    /// Returns the document and, when it was downloaded or revalidated, the new cache entry.
+    pub async fn fetch_with_cache(
+        &self,
+        url: &str,
+        cached: Option<CachedPage>,
+    ) -> Result<(Fetched, Option<CachedPage>)> {
+        if let Some(page) = cached.as_ref().filter(|page| page.is_fresh()) {
+            debug!("Page cache hit: {}", url);
+            return Ok((page.clone().into(), None));
+        }
+        let mut request = self.client.get(url);
+        if let Some(page) = &cached {
+            if let Some(etag) = &page.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &page.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        // Only requests that go over the network wait for the host, and the slot is given up
+        // before the caller extracts the document
+        let _slot = match self.politeness {
+            Some(politeness) => politeness.slot(url).await,
+            None => None,
+        };
+        let response = request.send().await?;
+        if let (StatusCode::NOT_MODIFIED, Some(page)) = (response.status(), cached) {
+            debug!("Page not modified: {}", url);
+            let page = page.revalidated(response.headers(), self.max_age);
+            return Ok((page.clone().into(), Some(page)));
+        }
+        let response = response.error_for_status()?;
+        let url = response.url().to_string();
+        let headers = response.headers().clone();
+        let body = self.read_body(&url, response).await?;
+        let page = CachedPage::from_response(url.clone(), &headers, body.clone(), self.max_age);
+        let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok());
+        let kind = sniff(content_type, &url, &body);
+        debug!("Fetched {} as {:?} ({:?})", url, kind, content_type);
+        Ok((Fetched { url, kind, body }, page))
+    }
+
+    /// Reads the body in chunks, giving up as soon as it is larger than `max_bytes`, so a huge
+    /// download never has to fit in memory.
+    async fn read_body(&self, url: &str, mut response: reqwest::Response) -> Result<Vec<u8>> {
+        let too_large = || FinanalizeError::ResponseTooLarge(url.to_string());
+        if response
+            .content_length()
+            .is_some_and(|length| length > self.max_bytes as u64)
+        {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > self.max_bytes {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::stub;
+
+    #[test]
+    fn test_sniff_headers_and_extensions() {
+        let url = "https://investor.apple.com/sec-filings/10-K.pdf";
+        assert_eq!(
+            sniff(Some("text/html; charset=utf-8"), "https://cnbc.com/", b""),
+            DocumentKind::Html
+        );
+        assert_eq!(sniff(Some("application/pdf"), url, b""), DocumentKind::Pdf);
+        assert_eq!(
+            sniff(Some("application/octet-stream"), url, b""),
+            DocumentKind::Pdf
+        );
+        assert_eq!(
+            sniff(
+                Some("text/plain"),
+                "https://a.com/data.CSV?x=1",
+                b"a,b\n1,2"
+            ),
+            DocumentKind::Csv
+        );
+        assert_eq!(
+            sniff(
+                Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+                "https://a.com/download",
+                b""
+            ),
+            DocumentKind::Spreadsheet
+        );
+    }
+
+    #[test]
+    fn test_sniff_content() {
+        let download = "https://a.com/download";
+        // A PDF mislabeled as a web page
+        assert_eq!(
+            sniff(Some("text/html"), download, b"%PDF-1.7\n..."),
+            DocumentKind::Pdf
+        );
+        assert_eq!(
+            sniff(
+                None,
+                download,
+                b"PK\x03\x04....[Content_Types].xml....xl/workbook.xml"
+            ),
+            DocumentKind::Spreadsheet
+        );
+        assert_eq!(
+            sniff(
+                None,
+                download,
+                b"PK\x03\x04....mimetypeapplication/vnd.oasis.opendocument.spreadsheetPK"
+            ),
+            DocumentKind::Spreadsheet
+        );
+        assert_eq!(
+            sniff(
+                Some("application/octet-stream"),
+                download,
+                b"PK\x03\x04....[Content_Types].xml....word/document.xml"
+            ),
+            DocumentKind::WordProcessing
+        );
+        assert_eq!(
+            sniff(
+                None,
+                "https://a.com/deck.docx",
+                b"PK\x03\x04....[Content_Types].xml....ppt/presentation.xml"
+            ),
+            DocumentKind::Presentation
+        );
+        assert_eq!(
+            sniff(
+                Some("application/octet-stream"),
+                download,
+                b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1....R\0o\0o\0t\0....W\0o\0r\0k\0b\0o\0o\0k\0"
+            ),
+            DocumentKind::Spreadsheet
+        );
+        assert_eq!(
+            sniff(None, download, b"  <!DOCTYPE html><html></html>"),
+            DocumentKind::Html
+        );
+        assert_eq!(
+            sniff(Some("text/plain"), download, b"Annual report 2024"),
+            DocumentKind::Text
+        );
+        assert_eq!(
+            sniff(
+                Some("image/png"),
+                "https://a.com/logo.png",
+                b"\x89PNG\r\n\x1a\n\xff"
+            ),
+            DocumentKind::Other
+        );
+    }
+
+    #[test]
+    fn test_document_links() {
+        let html = r#"<html><body>
+            <a href="/files/annual-report-2024.pdf">Annual report</a>
+            <a href="https://cdn.apple.com/data/revenue.xlsx">Revenue</a>
+            <a href="/files/q4-earnings-presentation.pptx">Slides</a>
+            <a href="/files/annual-report-2024.pdf">Annual report (again)</a>
+            <a href="/investors">Investors</a>
+            <a href="mailto:ir@apple.com">Mail</a>
+        </body></html>"#;
+        assert_eq!(
+            document_links(html, "https://investor.apple.com/home"),
+            vec![
+                "https://investor.apple.com/files/annual-report-2024.pdf",
+                "https://cdn.apple.com/data/revenue.xlsx",
+                "https://investor.apple.com/files/q4-earnings-presentation.pptx"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_needs_browser() {
+        let article = format!(
+            "<html><body><script>var x = 1;</script><article>{}</article></body></html>",
+            "Apple reported record services revenue. ".repeat(20)
+        );
+        assert!(!needs_browser(&article));
+        let app = format!(
+            r#"<html><body><div id="root"></div><script>{}</script></body></html>"#,
+            "render();".repeat(200)
+        );
+        assert!(needs_browser(&app));
+        let challenge = format!(
+            "<html><head><title>Just a moment...</title></head><body>{}</body></html>",
+            "Checking your browser before accessing the site. ".repeat(20)
+        );
+        assert!(needs_browser(&challenge));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stub() {
+        let (address, _) = stub::serve("application/pdf", b"%PDF-1.7").await;
+        let fetched = Fetcher::new()
+            .fetch(&format!("{}/report", address))
+            .await
+            .unwrap();
+        assert_eq!(fetched.kind, DocumentKind::Pdf);
+        assert_eq!(fetched.body, b"%PDF-1.7");
+        assert_eq!(fetched.url, format!("{}/report", address));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_too_large() {
+        let (address, _) = stub::serve("application/pdf", vec![b'x'; 2048]).await;
+        let url = format!("{}/report", address);
+        let fetcher = Fetcher::new().with_politeness(None);
+        let res = fetcher.with_max_bytes(1024).fetch(&url).await;
+        assert!(matches!(res, Err(FinanalizeError::ResponseTooLarge(_))));
+        let fetched = Fetcher::new()
+            .with_politeness(None)
+            .with_max_bytes(2048)
+            .fetch(&url)
+            .await
+            .unwrap();
+        assert_eq!(fetched.body.len(), 2048);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_with_cache() {
+        let (address, requests) =
+            stub::serve_with_etag("text/csv", "a,b\n1,2", Some("\"v1\"")).await;
+        let url = format!("{}/data", address);
+        let fetcher = Fetcher::new().with_politeness(None);
+
+        let (fetched, page) = fetcher.fetch_with_cache(&url, None).await.unwrap();
+        assert_eq!(fetched.kind, DocumentKind::Csv);
+        let page = page.unwrap();
+        assert_eq!(page.etag.as_deref(), Some("\"v1\""));
+
+        // A fresh page is not requested again
+        let (fetched, update) = fetcher
+            .fetch_with_cache(&url, Some(page.clone()))
+            .await
+            .unwrap();
+        assert_eq!(fetched.body, b"a,b\n1,2");
+        assert!(update.is_none());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // A stale page is revalidated and the cached body reused
+        let stale = CachedPage {
+            expires_at: page.fetched_at,
+            ..page
+        };
+        let (fetched, update) = fetcher.fetch_with_cache(&url, Some(stale)).await.unwrap();
+        assert_eq!(fetched.body, b"a,b\n1,2");
+        assert_eq!(fetched.kind, DocumentKind::Csv);
+        assert!(update.unwrap().is_fresh());
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].to_lowercase().contains("if-none-match: \"v1\""));
+    }
+
+    #[tokio::test]
+    async fn test_fresh_cache_hit_skips_politeness() {
+        let (address, requests) = stub::serve("text/csv", "a,b\n1,2").await;
+        let url = format!("{}/data", address);
+        let politeness = Box::leak(Box::new(Politeness::new(1, Duration::from_secs(2))));
+        let fetcher = Fetcher::new().with_politeness(Some(politeness));
+
+        let (_, page) = fetcher.fetch_with_cache(&url, None).await.unwrap();
+        let page = page.unwrap();
+        // robots.txt and the page itself
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        let start = std::time::Instant::now();
+        for _ in 0..2 {
+            fetcher
+                .fetch_with_cache(&url, Some(page.clone()))
+                .await
+                .unwrap();
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod browser;
+pub mod cache;
+pub mod fetch;
+pub mod politeness;
@@ -0,0 +1,345 @@
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
+
+use log::debug;
+use once_cell::sync::Lazy;
+use reqwest::{Client, Url};
+use tokio::{
+    sync::{Mutex, OnceCell, OwnedSemaphorePermit, Semaphore},
+    time::{sleep_until, Instant},
+};
+
+const ROBOTS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sent with every request, configurable with `SCRAPER_USER_AGENT`.
+pub static USER_AGENT: Lazy<String> = Lazy::new(|| {
+    env::var("SCRAPER_USER_AGENT")
+        .unwrap_or("FinAnalizeBot/1.0 (nguijoel.bryana@student.ehb.be)".into())
+});
+
+/// The rules of a robots.txt that apply to one user agent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Robots {
+    /// `(allow, pattern)` pairs
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Parses the group of the most specific matching user agent, falling back to `*`.
+    pub fn parse(content: &str, user_agent: &str) -> Self {
+        // Only the product token counts, "FinAnalizeBot/1.0 (...)" is matched as "finanalizebot"
+        let product_token = |agent: &str| {
+            agent
+                .split(['/', ' '])
+                .next()
+                .unwrap_or_default()
+                .to_lowercase()
+        };
+        let token = product_token(user_agent);
+        let mut specific: Option<Robots> = None;
+        let mut wildcard: Option<Robots> = None;
+        let mut agents: Vec<String> = vec![];
+        let mut group = Robots::default();
+        let mut in_rules = false;
+
+        let mut finish = |agents: &[String], group: Robots| {
+            if !token.is_empty() && agents.contains(&token) {
+                specific.get_or_insert_with(Robots::default).merge(group);
+            } else if agents.iter().any(|agent| agent == "*") {
+                wildcard.get_or_insert_with(Robots::default).merge(group);
+            }
+        };
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    // Consecutive user-agent lines share the rules that follow them
+                    if in_rules {
+                        finish(&agents, std::mem::take(&mut group));
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    // A group for an empty agent is aimed at nobody
+                    let agent = product_token(value);
+                    if !agent.is_empty() {
+                        agents.push(agent);
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty disallow allows everything
+                    if !value.is_empty() {
+                        group
+                            .rules
+                            .push((key.trim().eq_ignore_ascii_case("allow"), value.into()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    group.crawl_delay = value.parse::<f64>().ok().map(Duration::from_secs_f64);
+                }
+                _ => {}
+            }
+        }
+        finish(&agents, group);
+        specific.or(wildcard).unwrap_or_default()
+    }
+
+    fn merge(&mut self, other: Robots) {
+        self.rules.extend(other.rules);
+        self.crawl_delay = self.crawl_delay.or(other.crawl_delay);
+    }
+
+    /// The longest matching rule wins, an allow wins a tie.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| matches_pattern(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .map(|(allow, _)| *allow)
+            .unwrap_or(true)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// Matches a robots.txt path pattern, supporting `*` wildcards and a `$` end anchor.
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// Per-origin state: how many requests may run at once and when the next one may start.
+struct Host {
+    permits: Arc<Semaphore>,
+    next_request: Mutex<Instant>,
+}
+
+/// Keeps scraping polite: robots.txt is respected and every host is rate limited.
+///
+/// Configured with `DOMAIN_CONCURRENCY` (2 by default) and `DOMAIN_DELAY_MS` (1000 by default).
+/// A longer `Crawl-delay` in robots.txt takes precedence over the delay.
+pub struct Politeness {
+    client: Client,
+    concurrency: usize,
+    delay: Duration,
+    robots: Mutex<HashMap<String, Arc<OnceCell<Robots>>>>,
+    hosts: Mutex<HashMap<String, Arc<Host>>>,
+}
+
+impl Politeness {
+    pub fn new(concurrency: usize, delay: Duration) -> Self {
+        let client = Client::builder()
+            .timeout(ROBOTS_TIMEOUT)
+            .user_agent(USER_AGENT.as_str())
+            .build()
+            .expect("Failed to build HTTP client");
+        Politeness {
+            client,
+            concurrency: concurrency.max(1),
+            delay,
+            robots: Mutex::new(HashMap::new()),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fetches robots.txt once per origin, a missing or unreachable one allows everything.
+    async fn robots(&self, url: &Url) -> Robots {
+        let origin = url.origin().ascii_serialization();
+        let cell = self
+            .robots
+            .lock()
+            .await
+            .entry(origin.clone())
+            .or_default()
+            .clone();
+        cell.get_or_init(|| async {
+            let response = self
+                .client
+                .get(format!("{}/robots.txt", origin))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match response {
+                Ok(response) => {
+                    Robots::parse(&response.text().await.unwrap_or_default(), &USER_AGENT)
+                }
+                Err(e) => {
+                    debug!("No robots.txt for {}: {}", origin, e);
+                    Robots::default()
+                }
+            }
+        })
+        .await
+        .clone()
+    }
+
+    /// Whether robots.txt allows us to request this URL.
+    pub async fn allowed(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path = format!("{}?{}", path, query);
+        }
+        self.robots(&url).await.allows(&path)
+    }
+
+    /// Waits for a free slot on the URL's origin. The slot is released when the permit is dropped.
+    pub async fn slot(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        let url = Url::parse(url).ok()?;
+        let origin = url.origin().ascii_serialization();
+        let delay = self
+            .robots(&url)
+            .await
+            .crawl_delay()
+            .map_or(self.delay, |crawl_delay| crawl_delay.max(self.delay));
+        let state = self
+            .hosts
+            .lock()
+            .await
+            .entry(origin)
+            .or_insert_with(|| {
+                Arc::new(Host {
+                    permits: Arc::new(Semaphore::new(self.concurrency)),
+                    next_request: Mutex::new(Instant::now()),
+                })
+            })
+            .clone();
+        let permit = state.permits.clone().acquire_owned().await.ok()?;
+        let start = {
+            let mut next_request = state.next_request.lock().await;
+            let start = (*next_request).max(Instant::now());
+            *next_request = start + delay;
+            start
+        };
+        sleep_until(start).await;
+        Some(permit)
+    }
+}
+
+pub static POLITENESS: Lazy<Politeness> = Lazy::new(|| {
+    let concurrency = env::var("DOMAIN_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2);
+    let delay = env::var("DOMAIN_DELAY_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1000);
+    Politeness::new(concurrency, Duration::from_millis(delay))
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::stub;
+
+    const ROBOTS: &str = "
+        # Comments are ignored
+        User-agent: *
+        Disallow: /private/
+        Allow: /private/annual-report.pdf
+        Disallow: /*.json$
+        Crawl-delay: 2
+
+        User-agent: OtherBot
+        User-agent: FinAnalizeBot
+        Disallow: /search
+        Crawl-delay: 0.5
+    ";
+
+    #[test]
+    fn test_robots_wildcard_group() {
+        let robots = Robots::parse(ROBOTS, "SomeBot/2.0");
+        assert!(robots.allows("/"));
+        assert!(!robots.allows("/private/notes.html"));
+        assert!(robots.allows("/private/annual-report.pdf"));
+        assert!(!robots.allows("/data/prices.json"));
+        assert!(robots.allows("/data/prices.json?format=csv"));
+        assert!(robots.allows("/search?q=apple"));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_robots_specific_group() {
+        let robots = Robots::parse(ROBOTS, "FinAnalizeBot/1.0 (contact@example.com)");
+        assert!(robots.allows("/private/notes.html"));
+        assert!(!robots.allows("/search?q=apple"));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_robots_other_agents() {
+        let robots = "
+            User-agent: *
+            Disallow: /private/
+
+            User-agent: bot
+            User-agent: a
+            User-agent:
+            User-agent: FinAnalizeBotX
+            Disallow: /
+        ";
+        let robots = Robots::parse(robots, "FinAnalizeBot/1.0");
+        assert!(robots.allows("/"));
+        assert!(!robots.allows("/private/notes.html"));
+
+        let versioned = "User-agent: FinAnalizeBot/1.0\nDisallow: /\n\nUser-agent: *\nDisallow:";
+        assert!(!Robots::parse(versioned, "FinAnalizeBot").allows("/"));
+    }
+
+    #[test]
+    fn test_robots_empty() {
+        let robots = Robots::parse("User-agent: *\nDisallow:", "FinAnalizeBot");
+        assert!(robots.allows("/anything"));
+        assert_eq!(Robots::parse("", "FinAnalizeBot"), Robots::default());
+    }
+
+    #[tokio::test]
+    async fn test_politeness_stub() {
+        let (address, requests) =
+            stub::serve("text/plain", "User-agent: *\nDisallow: /private").await;
+        let politeness = Politeness::new(1, Duration::from_millis(100));
+        assert!(politeness.allowed(&format!("{}/public", address)).await);
+        assert!(
+            !politeness
+                .allowed(&format!("{}/private/page", address))
+                .await
+        );
+        // robots.txt is cached per origin
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert!(requests.lock().unwrap()[0].starts_with("GET /robots.txt "));
+
+        let start = Instant::now();
+        drop(politeness.slot(&format!("{}/a", address)).await);
+        drop(politeness.slot(&format!("{}/b", address)).await);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::prelude::*;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::{extend_unique, SafeSearch, SearchEngine, SearchOptions, SearchResult, TimeRange};
+
+pub const DEFAULT_ADDRESS: &str = "https://api.search.brave.com/res/v1";
+/// The API refuses larger pages and offsets
+const MAX_COUNT: usize = 20;
+const MAX_OFFSET: usize = 9;
+
+/// Client for the Brave web search API, authenticated with a subscription token.
+pub struct Brave {
+    base_url: String,
+    api_key: String,
+    client: Client,
+}
+
+impl Brave {
+    pub fn new(base_url: &str, api_key: &str) -> Self {
+        Brave {
+            base_url: base_url.to_string(),
+            api_key: api_key.to_string(),
+            client: Client::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveResponse {
+    web: Option<BraveWeb>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveWeb {
+    results: Vec<BraveItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveItem {
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    page_age: Option<String>,
+}
+
+#[async_trait]
+impl SearchEngine for Brave {
+    async fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>> {
+        let count = options.count.clamp(1, MAX_COUNT);
+        let mut params = vec![
+            ("q", options.query(query)),
+            ("count", count.to_string()),
+            (
+                "safesearch",
+                match options.safe_search {
+                    SafeSearch::Off => "off",
+                    SafeSearch::Moderate => "moderate",
+                    SafeSearch::Strict => "strict",
+                }
+                .into(),
+            ),
+        ];
+        if let Some(time_range) = options.time_range {
+            let freshness = match time_range {
+                TimeRange::Day => "pd",
+                TimeRange::Week => "pw",
+                TimeRange::Month => "pm",
+                TimeRange::Year => "py",
+            };
+            params.push(("freshness", freshness.into()));
+        }
+        if let Some(language) = &options.language {
+            params.push(("search_lang", language.clone()));
+        }
+
+        let mut results = Vec::new();
+        for offset in 0..options.pages.clamp(1, MAX_OFFSET + 1) {
+            let response: BraveResponse = self
+                .client
+                .get(format!("{}/web/search", self.base_url))
+                .header("Accept", "application/json")
+                .header("X-Subscription-Token", &self.api_key)
+                .query(&params)
+                .query(&[("offset", offset)])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let items = response.web.map(|web| web.results).unwrap_or_default();
+            if items.is_empty() {
+                break;
+            }
+            extend_unique(
+                &mut results,
+                items
+                    .into_iter()
+                    .map(|item| SearchResult {
+                        url: item.url,
+                        title: item.title,
+                        snippet: item.description,
+                        engine: "brave".into(),
+                        published_date: item.page_age,
+                        score: None,
+                    })
+                    .filter(|r| options.allows(&r.url)),
+            );
+            if results.len() >= options.count {
+                break;
+            }
+        }
+        results.truncate(options.count);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::stub;
+
+    #[tokio::test]
+    async fn test_brave_stub() {
+        let (address, requests) = stub::serve_json(
+            r#"{"web": {"results": [
+                {"url": "https://www.cnbc.com/apple", "title": "Apple", "description": "Q1", "page_age": "2025-01-30T21:00:00"},
+                {"url": "https://www.sec.gov/aapl", "title": "10-K", "description": "..."}
+            ]}}"#,
+        )
+        .await;
+        let results = Brave::new(&address, "secret")
+            .search("apple earnings 2025", &SearchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            results.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            vec!["https://www.cnbc.com/apple", "https://www.sec.gov/aapl"]
+        );
+        assert_eq!(results[0].title, "Apple");
+        assert_eq!(results[0].snippet, "Q1");
+        assert_eq!(results[0].engine, "brave");
+        assert_eq!(
+            results[0].published_date.as_deref(),
+            Some("2025-01-30T21:00:00")
+        );
+        let request = requests.lock().unwrap()[0].to_lowercase();
+        assert!(request.starts_with(
+            "get /web/search?q=apple+earnings+2025&count=3&safesearch=moderate&offset=0 "
+        ));
+        assert!(request.contains("x-subscription-token: secret"));
+    }
+
+    #[tokio::test]
+    async fn test_brave_stub_without_web_results() {
+        let (address, _) = stub::serve_json(r#"{"type": "search"}"#).await;
+        let results = Brave::new(&address, "secret")
+            .search("apple", &SearchOptions::default())
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_brave_stub_options() {
+        let (address, requests) = stub::serve_json(
+            r#"{"web": {"results": [
+                {"url": "https://www.cnbc.com/apple"},
+                {"url": "https://www.reddit.com/r/apple"}
+            ]}}"#,
+        )
+        .await;
+        let options = SearchOptions {
+            count: 40,
+            pages: 1,
+            time_range: Some(TimeRange::Week),
+            language: Some("fr".into()),
+            safe_search: SafeSearch::Strict,
+            exclude_sites: vec!["reddit.com".into()],
+            ..Default::default()
+        };
+        let results = Brave::new(&address, "secret")
+            .search("apple", &options)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].url, "https://www.cnbc.com/apple");
+        assert!(requests.lock().unwrap()[0].starts_with(
+            "GET /web/search?q=apple+-site%3Areddit.com&count=20&safesearch=strict\
+            &freshness=pw&search_lang=fr&offset=0 "
+        ));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::prelude::*;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::{extend_unique, SafeSearch, SearchEngine, SearchOptions, SearchResult, TimeRange};
+
+pub const DEFAULT_ADDRESS: &str = "https://www.googleapis.com/customsearch/v1";
+/// The API returns at most 10 results per request
+const MAX_COUNT: usize = 10;
+
+/// Client for the Google Custom Search JSON API, or anything that speaks the same protocol.
+pub struct GoogleCse {
+    base_url: String,
+    api_key: String,
+    cx: String,
+    client: Client,
+}
+
+impl GoogleCse {
+    pub fn new(base_url: &str, api_key: &str, cx: &str) -> Self {
+        GoogleCse {
+            base_url: base_url.to_string(),
+            api_key: api_key.to_string(),
+            cx: cx.to_string(),
+            client: Client::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleCseResponse {
+    // Missing entirely when there are no results
+    #[serde(default)]
+    items: Vec<GoogleCseItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleCseItem {
+    link: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    snippet: String,
+}
+
+#[async_trait]
+impl SearchEngine for GoogleCse {
+    async fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>> {
+        let count = options.count.clamp(1, MAX_COUNT);
+        let mut params = vec![
+            ("key", self.api_key.clone()),
+            ("cx", self.cx.clone()),
+            ("q", options.query(query)),
+            ("num", count.to_string()),
+            (
+                "safe",
+                match options.safe_search {
+                    SafeSearch::Off => "off",
+                    SafeSearch::Moderate | SafeSearch::Strict => "active",
+                }
+                .into(),
+            ),
+        ];
+        if let Some(time_range) = options.time_range {
+            let date_restrict = match time_range {
+                TimeRange::Day => "d1",
+                TimeRange::Week => "w1",
+                TimeRange::Month => "m1",
+                TimeRange::Year => "y1",
+            };
+            params.push(("dateRestrict", date_restrict.into()));
+        }
+        if let Some(language) = &options.language {
+            params.push(("lr", format!("lang_{}", language)));
+        }
+
+        let mut results = Vec::new();
+        for page in 0..options.pages.max(1) {
+            let response: GoogleCseResponse = self
+                .client
+                .get(&self.base_url)
+                .query(&params)
+                .query(&[("start", 1 + page * count)])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            if response.items.is_empty() {
+                break;
+            }
+            extend_unique(
+                &mut results,
+                response
+                    .items
+                    .into_iter()
+                    .map(|item| SearchResult {
+                        url: item.link,
+                        title: item.title,
+                        snippet: item.snippet,
+                        engine: "google".into(),
+                        published_date: None,
+                        score: None,
+                    })
+                    .filter(|r| options.allows(&r.url)),
+            );
+            if results.len() >= options.count {
+                break;
+            }
+        }
+        results.truncate(options.count);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::stub;
+
+    #[tokio::test]
+    async fn test_google_cse_stub() {
+        let (address, requests) = stub::serve_json(
+            r#"{"kind": "customsearch#search", "items": [
+                {"link": "https://investor.apple.com/", "title": "Apple IR", "snippet": "..."},
+                {"link": "https://www.reuters.com/apple", "title": "Reuters", "snippet": "..."}
+            ]}"#,
+        )
+        .await;
+        let results = GoogleCse::new(&address, "key", "engine")
+            .search("apple", &SearchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            results.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            vec![
+                "https://investor.apple.com/",
+                "https://www.reuters.com/apple"
+            ]
+        );
+        assert_eq!(results[0].title, "Apple IR");
+        assert_eq!(results[0].engine, "google");
+        assert!(requests.lock().unwrap()[0]
+            .starts_with("GET /?key=key&cx=engine&q=apple&num=3&safe=active&start=1 "));
+    }
+
+    #[tokio::test]
+    async fn test_google_cse_stub_without_items() {
+        let (address, _) = stub::serve_json(r#"{"kind": "customsearch#search"}"#).await;
+        let results = GoogleCse::new(&address, "key", "engine")
+            .search("apple", &SearchOptions::default())
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_google_cse_stub_options() {
+        let (address, requests) = stub::serve_json(
+            r#"{"items": [
+                {"link": "https://investor.apple.com/"},
+                {"link": "https://www.reuters.com/apple"}
+            ]}"#,
+        )
+        .await;
+        let options = SearchOptions {
+            count: 2,
+            pages: 3,
+            time_range: Some(TimeRange::Year),
+            language: Some("en".into()),
+            safe_search: SafeSearch::Off,
+            include_sites: vec!["reuters.com".into()],
+            ..Default::default()
+        };
+        let results = GoogleCse::new(&address, "key", "engine")
+            .search("apple", &options)
+            .await
+            .unwrap();
+        // Every page repeats the one result that passes the site filter
+        assert_eq!(
+            results.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            vec!["https://www.reuters.com/apple"]
+        );
+        // The count is never reached, so every page is requested
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].starts_with(
+            "GET /?key=key&cx=engine&q=apple+site%3Areuters.com&num=2&safe=off\
+            &dateRestrict=y1&lr=lang_en&start=1 "
+        ));
+        assert!(requests[1].contains("&start=3 "));
+        assert!(requests[2].contains("&start=5 "));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{db::DB, prelude::*};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{normalize_url, SearchEngine, SearchOptions, SearchResult};
+
+const DOCUMENT_TABLE: &str = "scraped_document";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapedDocument {
+    pub url: String,
+    pub content: String,
+}
+
+/// Searches the markdown of pages scraped for earlier reports.
+pub struct LocalIndex;
+
+/// Full-text index over the scraped pages, stemmed English with accents folded.
+const SCHEMA: &str = r#"
+DEFINE ANALYZER IF NOT EXISTS scraped_text TOKENIZERS blank, class, punct
+    FILTERS lowercase, ascii, snowball(english);
+DEFINE INDEX IF NOT EXISTS scraped_content ON scraped_document
+    FIELDS content SEARCH ANALYZER scraped_text BM25;
+"#;
+
+impl LocalIndex {
+    /// Defines the full-text index searches go through, existing pages are indexed too.
+    pub async fn init() -> Result<()> {
+        let Some(db) = DB.get() else {
+            return Ok(());
+        };
+        db.query(SCHEMA).await?.check()?;
+        Ok(())
+    }
+
+    /// Stores a scraped page, replacing an earlier scrape of the same URL.
+    pub async fn index(url: &str, content: &str) -> Result<()> {
+        let Some(db) = DB.get() else {
+            return Ok(());
+        };
+        let _: Option<ScrapedDocument> = db
+            .upsert((DOCUMENT_TABLE, normalize_url(url)))
+            .content(ScrapedDocument {
+                url: url.to_string(),
+                content: content.to_string(),
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+/// Ranks pages by BM25 over the full-text index, so only matching pages leave the database.
+const SEARCH_QUERY: &str = r#"
+SELECT url, content, search::score(1) AS score FROM scraped_document
+WHERE content @1@ $query ORDER BY score DESC LIMIT $limit;
+"#;
+
+/// Site filters are applied after the query, so fetch some spare results.
+const OVERFETCH: usize = 3;
+
+#[derive(Debug, Clone, Deserialize)]
+struct ScoredDocument {
+    url: String,
+    content: String,
+    score: f64,
+}
+
+impl From<ScoredDocument> for SearchResult {
+    fn from(doc: ScoredDocument) -> Self {
+        SearchResult {
+            url: doc.url,
+            title: doc.content.lines().next().unwrap_or_default().to_string(),
+            snippet: doc.content.chars().take(200).collect(),
+            engine: "local".into(),
+            published_date: None,
+            score: Some(doc.score),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchEngine for LocalIndex {
+    async fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>> {
+        let Some(db) = DB.get() else {
+            return Ok(vec![]);
+        };
+        if query.trim().is_empty() {
+            return Ok(vec![]);
+        }
+        let documents: Vec<ScoredDocument> = db
+            .query(SEARCH_QUERY)
+            .bind(("query", query.to_string()))
+            .bind(("limit", options.count * OVERFETCH))
+            .await?
+            .take(0)?;
+        Ok(documents
+            .into_iter()
+            .map(SearchResult::from)
+            .filter(|r| options.allows(&r.url))
+            .take(options.count)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result() {
+        let result = SearchResult::from(ScoredDocument {
+            url: "https://b.com".into(),
+            content: "Apple revenue: Apple grew its revenue in 2024.\nServices led.".into(),
+            score: 2.5,
+        });
+        assert_eq!(
+            result.title,
+            "Apple revenue: Apple grew its revenue in 2024."
+        );
+        assert_eq!(result.score, Some(2.5));
+        assert_eq!(result.engine, "local");
+    }
+}
@@ -0,0 +1,137 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::prelude::*;
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use log::warn;
+
+use super::{normalize_url, SearchEngine, SearchOptions, SearchResult};
+
+/// Queries several providers concurrently and merges their results.
+///
+/// Results are interleaved so every provider's best hit comes first, duplicates are dropped by
+/// normalized URL. Fails only when every provider fails.
+pub struct MetaSearch {
+    engines: Vec<Arc<dyn SearchEngine>>,
+}
+
+impl MetaSearch {
+    pub fn new(engines: Vec<Arc<dyn SearchEngine>>) -> Self {
+        MetaSearch { engines }
+    }
+}
+
+pub fn merge(results: Vec<Vec<SearchResult>>) -> Vec<SearchResult> {
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+    let longest = results.iter().map(Vec::len).max().unwrap_or(0);
+    for rank in 0..longest {
+        for result in results.iter().filter_map(|r| r.get(rank)) {
+            if seen.insert(normalize_url(&result.url)) {
+                merged.push(result.clone());
+            }
+        }
+    }
+    merged
+}
+
+#[async_trait]
+impl SearchEngine for MetaSearch {
+    async fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>> {
+        let responses = join_all(self.engines.iter().map(|e| e.search(query, options))).await;
+        let mut results = Vec::new();
+        let mut last_error = None;
+        for response in responses {
+            match response {
+                Ok(hits) => results.push(hits),
+                Err(err) => {
+                    warn!("Search provider failed for '{}': {}", query, err);
+                    last_error = Some(err);
+                }
+            }
+        }
+        match (results.is_empty(), last_error) {
+            (true, Some(err)) => Err(err),
+            _ => Ok(merge(results).into_iter().take(options.count).collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{brave::Brave, stub, SearxNG};
+
+    #[tokio::test]
+    async fn test_meta_search_count() {
+        let (searxng, _) = stub::serve_json(
+            r#"{"results": [{"url": "https://a.com"}, {"url": "https://b.com"}]}"#,
+        )
+        .await;
+        let meta = MetaSearch::new(vec![
+            Arc::new(SearxNG::new(&searxng)),
+            Arc::new(SearxNG::new(&searxng)),
+        ]);
+        let options = SearchOptions {
+            count: 1,
+            ..Default::default()
+        };
+        assert_eq!(meta.search("apple", &options).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_merge() {
+        let merged = merge(vec![
+            vec!["https://a.com/1".into(), "https://www.b.com/2/".into()],
+            vec![
+                "https://b.com/2".into(),
+                "https://c.com/3".into(),
+                "https://a.com/1#top".into(),
+            ],
+        ]);
+        assert_eq!(
+            merged.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            vec!["https://a.com/1", "https://b.com/2", "https://c.com/3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_meta_search_stub() {
+        let (searxng, _) = stub::serve_json(
+            r#"{"results": [{"url": "https://www.reuters.com/apple"}, {"url": "https://a.com"}]}"#,
+        )
+        .await;
+        let (brave, _) = stub::serve_json(
+            r#"{"web": {"results": [{"url": "https://reuters.com/apple/"}, {"url": "https://b.com"}]}}"#,
+        )
+        .await;
+        let meta = MetaSearch::new(vec![
+            Arc::new(SearxNG::new(&searxng)),
+            Arc::new(Brave::new(&brave, "key")),
+            // Unreachable providers are skipped
+            Arc::new(SearxNG::new("http://127.0.0.1:1")),
+        ]);
+        let results = meta
+            .search("apple", &SearchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            results.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            vec![
+                "https://www.reuters.com/apple",
+                "https://a.com",
+                "https://b.com"
+            ]
+        );
+        assert_eq!(results[2].engine, "brave");
+    }
+
+    #[tokio::test]
+    async fn test_meta_search_all_failing() {
+        let meta = MetaSearch::new(vec![Arc::new(SearxNG::new("http://127.0.0.1:1"))]);
+        assert!(meta
+            .search("apple", &SearchOptions::default())
+            .await
+            .is_err());
+    }
+}
@@ -1,62 +1,254 @@
-use std::{env, sync::Arc};
+use std::{collections::HashSet, env, sync::Arc};
 
 use crate::prelude::*;
 use async_trait::async_trait;
+use brave::Brave;
+use google::GoogleCse;
+use local::LocalIndex;
+use log::{debug, warn};
+use meta::MetaSearch;
 use once_cell::sync::Lazy;
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+pub mod brave;
+pub mod google;
+pub mod local;
+pub mod meta;
+pub mod options;
+pub mod reputation;
+pub mod searxng;
+
+pub use options::{SafeSearch, SearchOptions, TimeRange};
+pub use searxng::SearxNG;
+
+/// A single hit, with whatever metadata the provider returned alongside the URL.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub url: String,
+    pub title: String,
+    pub snippet: String,
+    /// The provider, or for SearxNG the upstream engine, that found the result
+    pub engine: String,
+    /// As reported by the provider, formats differ between providers
+    pub published_date: Option<String>,
+    /// Provider relevance score, only comparable between results of the same provider
+    pub score: Option<f64>,
+}
+
+impl From<&str> for SearchResult {
+    fn from(url: &str) -> Self {
+        SearchResult {
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+}
 
 #[async_trait]
 pub trait SearchEngine: Send + Sync + 'static {
-    async fn search(&self, query: &str) -> Result<Vec<String>>;
+    async fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>>;
 }
 
+/// The providers are picked with `SEARCH_PROVIDERS`, a comma separated list of `searxng`,
+/// `brave`, `google` and `local`. More than one provider turns on meta-search.
 pub static SEARCH: Lazy<Arc<dyn SearchEngine>> = Lazy::new(|| {
-    let mut default = "http://localhost:8081".into();
-    if let Ok(address) = env::var("SEARXNG_ADDRESS") {
-        default = address;
-    };
-    Arc::new(SearxNG::new(&default))
+    let providers = env::var("SEARCH_PROVIDERS").unwrap_or("searxng".into());
+    let mut engines: Vec<Arc<dyn SearchEngine>> = Vec::new();
+    for provider in providers.split(',').map(str::trim) {
+        match make_engine(provider) {
+            Some(engine) => engines.push(engine),
+            None => warn!(
+                "Skipping unknown or unconfigured search provider: {}",
+                provider
+            ),
+        }
+    }
+    debug!("Using {} search provider(s): {}", engines.len(), providers);
+    match engines.len() {
+        0 => Arc::new(SearxNG::new(&searxng_address())),
+        1 => engines.remove(0),
+        _ => Arc::new(MetaSearch::new(engines)),
+    }
 });
 
-#[derive(Default)]
-pub struct SearxNG {
-    base_url: String,
-    client: Client,
+fn searxng_address() -> String {
+    env::var("SEARXNG_ADDRESS").unwrap_or("http://localhost:8081".into())
 }
 
-impl SearxNG {
-    pub fn new(base_url: &str) -> Self {
-        SearxNG {
-            base_url: base_url.to_string(),
-            client: Client::new(),
+fn make_engine(provider: &str) -> Option<Arc<dyn SearchEngine>> {
+    match provider {
+        "searxng" => Some(Arc::new(SearxNG::new(&searxng_address()))),
+        "brave" => {
+            let key = env::var("BRAVE_API_KEY").ok()?;
+            let base_url = env::var("BRAVE_ADDRESS").unwrap_or(brave::DEFAULT_ADDRESS.into());
+            Some(Arc::new(Brave::new(&base_url, &key)))
         }
+        "google" => {
+            let key = env::var("GOOGLE_CSE_KEY").ok()?;
+            let cx = env::var("GOOGLE_CSE_CX").ok()?;
+            let base_url = env::var("GOOGLE_CSE_ADDRESS").unwrap_or(google::DEFAULT_ADDRESS.into());
+            Some(Arc::new(GoogleCse::new(&base_url, &key, &cx)))
+        }
+        "local" => Some(Arc::new(LocalIndex)),
+        _ => None,
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct SearxNGResult {
-    results: Vec<SearxNGItem>,
+/// Query parameters that only track where a visitor came from
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_ga",
+    "_gl",
+    "_hsenc",
+    "_hsmi",
+    "ref",
+    "ref_src",
+    "cmpid",
+    "ocid",
+    "guccounter",
+];
+
+fn is_tracking_param(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
 }
 
-#[derive(Debug, Deserialize)]
-struct SearxNGItem {
-    url: String,
+/// Removes tracking parameters and the fragment, keeping the URL otherwise as found.
+pub fn clean_url(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url.trim()) else {
+        return url.trim().to_string();
+    };
+    parsed.set_fragment(None);
+    let kept: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        parsed.set_query(None);
+    } else if parsed.query_pairs().count() != kept.len() {
+        parsed.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    parsed.to_string()
 }
 
-#[async_trait]
-impl SearchEngine for SearxNG {
-    async fn search(&self, query: &str) -> Result<Vec<String>> {
-        let mut urls = Vec::new();
-        let url = format!(
-            "{}/search?q={}&format=json&pageno={}",
-            self.base_url, query, 1
-        );
+/// Normalizes a URL so the same page found through different providers compares equal.
+///
+/// Drops tracking parameters, `www.`, the fragment, empty queries and trailing slashes.
+/// Parsing already lowercases the host and removes default ports.
+pub fn normalize_url(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(&clean_url(url)) else {
+        return url.trim().to_string();
+    };
+    if let Some(host) = parsed.host_str() {
+        let host = host.to_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+        let _ = parsed.set_host(Some(&host));
+    }
+    if parsed.query() == Some("") {
+        parsed.set_query(None);
+    }
+    let normalized = parsed.to_string();
+    match normalized.strip_suffix('/') {
+        Some(stripped) if parsed.query().is_none() => stripped.to_string(),
+        _ => normalized,
+    }
+}
 
-        let response = self.client.get(&url).send().await?;
-        let results: SearxNGResult = response.json().await?;
-        urls.extend(results.results.into_iter().map(|r| r.url));
-        Ok(urls.into_iter().take(3).collect())
+/// Adds the results of another page, leaving out the ones earlier pages already returned.
+pub fn extend_unique(
+    results: &mut Vec<SearchResult>,
+    page: impl IntoIterator<Item = SearchResult>,
+) {
+    let mut seen: HashSet<String> = results.iter().map(|r| normalize_url(&r.url)).collect();
+    results.extend(
+        page.into_iter()
+            .filter(|r| seen.insert(normalize_url(&r.url))),
+    );
+}
+
+#[cfg(test)]
+pub(crate) mod stub {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Serves `body` as JSON to every request on a random local port.
+    ///
+    /// Returns the base URL and the raw requests (request line and headers) received.
+    pub async fn serve_json(body: &'static str) -> (String, Arc<Mutex<Vec<String>>>) {
+        serve("application/json", body.as_bytes()).await
+    }
+
+    /// Serves `body` with the given content type to every request on a random local port.
+    pub async fn serve(
+        content_type: &'static str,
+        body: impl Into<Vec<u8>>,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        serve_with_etag(content_type, body, None).await
+    }
+
+    /// Like [`serve`], but sends an `ETag` and answers `304 Not Modified` to requests that
+    /// send it back in `If-None-Match`.
+    pub async fn serve_with_etag(
+        content_type: &'static str,
+        body: impl Into<Vec<u8>>,
+        etag: Option<&'static str>,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let body = body.into();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = vec![0; 8192];
+                let mut read = 0;
+                while !String::from_utf8_lossy(&buffer[..read]).contains("\r\n\r\n") {
+                    match stream.read(&mut buffer[read..]).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => read += n,
+                    }
+                }
+                let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+                received.lock().unwrap().push(request.clone());
+                let etag_header = etag
+                    .map(|etag| format!("ETag: {}\r\n", etag))
+                    .unwrap_or_default();
+                if etag.is_some_and(|etag| {
+                    request
+                        .to_lowercase()
+                        .contains(&format!("if-none-match: {}", etag))
+                }) {
+                    let header = format!(
+                        "HTTP/1.1 304 Not Modified\r\n{}Connection: close\r\n\r\n",
+                        etag_header
+                    );
+                    let _ = stream.write_all(header.as_bytes()).await;
+                    continue;
+                }
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+                    content_type,
+                    body.len(),
+                    etag_header
+                );
+                let _ = stream.write_all(header.as_bytes()).await;
+                let _ = stream.write_all(&body).await;
+            }
+        });
+        (address, requests)
     }
 }
 
@@ -67,7 +259,52 @@ mod tests {
     #[tokio::test]
     #[ignore = "Depends on external service"]
     async fn test_searxng() {
-        let results = SEARCH.search("rust").await.unwrap();
+        let results = SEARCH
+            .search("rust", &SearchOptions::default())
+            .await
+            .unwrap();
         assert!(!results.is_empty());
     }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            normalize_url("https://WWW.Nasdaq.com/articles/apple/#comments"),
+            "https://nasdaq.com/articles/apple"
+        );
+        assert_eq!(
+            normalize_url("https://nasdaq.com:443/articles/apple?"),
+            "https://nasdaq.com/articles/apple"
+        );
+        assert_eq!(
+            normalize_url("https://nasdaq.com/?q=apple"),
+            "https://nasdaq.com/?q=apple"
+        );
+        assert_eq!(normalize_url("not a url"), "not a url");
+        assert_eq!(
+            normalize_url("https://www.cnbc.com/apple/?utm_source=x&utm_medium=y#top"),
+            "https://cnbc.com/apple"
+        );
+    }
+
+    #[test]
+    fn test_clean_url() {
+        assert_eq!(
+            clean_url("https://www.cnbc.com/apple?utm_source=twitter&id=3&fbclid=abc#top"),
+            "https://www.cnbc.com/apple?id=3"
+        );
+        assert_eq!(
+            clean_url("https://www.nbcboston.com/news/apple/?os=android&ref=app"),
+            "https://www.nbcboston.com/news/apple/?os=android"
+        );
+        assert_eq!(
+            clean_url("https://www.cnbc.com/apple?gclid=1"),
+            "https://www.cnbc.com/apple"
+        );
+        // Untouched queries keep their original encoding
+        assert_eq!(
+            clean_url("https://www.cnbc.com/search?q=apple%20stock"),
+            "https://www.cnbc.com/search?q=apple%20stock"
+        );
+    }
 }
@@ -0,0 +1,273 @@
+use std::env;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::models::FullReport;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeRange {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeRange {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "day" => Some(TimeRange::Day),
+            "week" => Some(TimeRange::Week),
+            "month" => Some(TimeRange::Month),
+            "year" => Some(TimeRange::Year),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SafeSearch {
+    Off,
+    #[default]
+    Moderate,
+    Strict,
+}
+
+impl SafeSearch {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "off" => Some(SafeSearch::Off),
+            "moderate" => Some(SafeSearch::Moderate),
+            "strict" => Some(SafeSearch::Strict),
+            _ => None,
+        }
+    }
+}
+
+/// What to ask a provider for, providers ignore what they do not support.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    /// Results kept per query
+    pub count: usize,
+    /// Result pages fetched at most to reach `count`
+    pub pages: usize,
+    pub time_range: Option<TimeRange>,
+    /// ISO 639-1 code, e.g. `en`
+    pub language: Option<String>,
+    pub categories: Vec<String>,
+    pub safe_search: SafeSearch,
+    /// Only keep results from these domains (and their subdomains), when not empty
+    pub include_sites: Vec<String>,
+    pub exclude_sites: Vec<String>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            count: 3,
+            pages: 1,
+            time_range: None,
+            language: None,
+            categories: vec![],
+            safe_search: SafeSearch::default(),
+            include_sites: vec![],
+            exclude_sites: vec![],
+        }
+    }
+}
+
+/// Search settings chosen for a report, what is left out comes from the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchFilters {
+    pub count: Option<usize>,
+    pub pages: Option<usize>,
+    pub time_range: Option<TimeRange>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    pub safe_search: Option<SafeSearch>,
+    #[serde(default)]
+    pub include_sites: Vec<String>,
+    #[serde(default)]
+    pub exclude_sites: Vec<String>,
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+fn env_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+impl SearchOptions {
+    /// The filters chosen for the report, the rest defaults to a depth that follows the report
+    /// size and filters from `SEARCH_TIME_RANGE`, `SEARCH_CATEGORIES`, `SEARCH_SAFE_SEARCH`,
+    /// `SEARCH_INCLUDE_SITES` and `SEARCH_EXCLUDE_SITES`.
+    ///
+    /// Whatever was chosen, at most `SEARCH_MAX_COUNT` (20 by default) results and
+    /// `SEARCH_MAX_PAGES` (5 by default) pages are asked for, and at least one of each.
+    pub fn for_report(report: &FullReport) -> Self {
+        let filters = report.search_filters.clone().unwrap_or_default();
+        let or_env = |chosen: Vec<String>, name: &str| match chosen.is_empty() {
+            true => env_list(name),
+            false => chosen.iter().map(|s| s.trim().to_lowercase()).collect(),
+        };
+        SearchOptions {
+            count: filters
+                .count
+                .unwrap_or_else(|| report.size.search_result_amount())
+                .clamp(1, env_usize("SEARCH_MAX_COUNT", 20).max(1)),
+            pages: filters
+                .pages
+                .unwrap_or_else(|| report.size.search_page_amount())
+                .clamp(1, env_usize("SEARCH_MAX_PAGES", 5).max(1)),
+            time_range: filters.time_range.or_else(|| {
+                env::var("SEARCH_TIME_RANGE")
+                    .ok()
+                    .and_then(|v| TimeRange::parse(&v))
+            }),
+            language: Some(report.language.code().into()),
+            categories: or_env(filters.categories, "SEARCH_CATEGORIES"),
+            safe_search: filters
+                .safe_search
+                .or_else(|| {
+                    env::var("SEARCH_SAFE_SEARCH")
+                        .ok()
+                        .and_then(|v| SafeSearch::parse(&v))
+                })
+                .unwrap_or_default(),
+            include_sites: or_env(filters.include_sites, "SEARCH_INCLUDE_SITES"),
+            exclude_sites: or_env(filters.exclude_sites, "SEARCH_EXCLUDE_SITES"),
+        }
+    }
+
+    /// The query with `site:` operators for the site lists appended.
+    pub fn query(&self, query: &str) -> String {
+        let mut parts = vec![query.to_string()];
+        match self.include_sites.len() {
+            0 => {}
+            1 => parts.push(format!("site:{}", self.include_sites[0])),
+            _ => parts.push(format!(
+                "({})",
+                self.include_sites
+                    .iter()
+                    .map(|site| format!("site:{}", site))
+                    .collect::<Vec<_>>()
+                    .join(" OR ")
+            )),
+        }
+        parts.extend(
+            self.exclude_sites
+                .iter()
+                .map(|site| format!("-site:{}", site)),
+        );
+        parts.join(" ")
+    }
+
+    /// Whether the site lists allow the URL, not every provider honours the `site:` operators.
+    pub fn allows(&self, url: &str) -> bool {
+        let Some(host) = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_lowercase))
+        else {
+            return false;
+        };
+        let matches = |site: &String| host == *site || host.ends_with(&format!(".{}", site));
+        (self.include_sites.is_empty() || self.include_sites.iter().any(matches))
+            && !self.exclude_sites.iter().any(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::v1::report::{ReportLanguage, ReportSize};
+
+    #[test]
+    fn test_query_site_operators() {
+        let options = SearchOptions {
+            include_sites: vec!["sec.gov".into(), "reuters.com".into()],
+            exclude_sites: vec!["reddit.com".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.query("apple 10-k"),
+            "apple 10-k (site:sec.gov OR site:reuters.com) -site:reddit.com"
+        );
+        assert_eq!(SearchOptions::default().query("apple"), "apple");
+    }
+
+    #[test]
+    fn test_allows() {
+        let options = SearchOptions {
+            include_sites: vec!["reuters.com".into()],
+            exclude_sites: vec!["blogs.reuters.com".into()],
+            ..Default::default()
+        };
+        assert!(options.allows("https://www.reuters.com/markets/apple"));
+        assert!(options.allows("https://reuters.com/"));
+        assert!(!options.allows("https://blogs.reuters.com/apple"));
+        assert!(!options.allows("https://notreuters.com/apple"));
+        assert!(!options.allows("not a url"));
+        assert!(SearchOptions::default().allows("https://example.com"));
+    }
+
+    #[test]
+    fn test_for_report_depth() {
+        let mut report = FullReport::new("options".into(), "Apple".into());
+        report.language = ReportLanguage::Dutch;
+        let small = SearchOptions::for_report(&report);
+        report.size = ReportSize::Large;
+        let large = SearchOptions::for_report(&report);
+        assert_eq!(small.language.as_deref(), Some("nl"));
+        assert!(small.count < large.count);
+        assert!(small.pages <= large.pages);
+    }
+
+    #[test]
+    fn test_for_report_filters() {
+        let mut report = FullReport::new("options".into(), "Apple".into());
+        report.search_filters = Some(SearchFilters {
+            count: Some(10),
+            time_range: Some(TimeRange::Month),
+            include_sites: vec!["Reuters.com".into()],
+            ..Default::default()
+        });
+        let options = SearchOptions::for_report(&report);
+        assert_eq!(options.count, 10);
+        assert_eq!(options.pages, ReportSize::Small.search_page_amount());
+        assert_eq!(options.time_range, Some(TimeRange::Month));
+        assert_eq!(options.include_sites, ["reuters.com"]);
+    }
+
+    #[test]
+    fn test_for_report_clamps_depth() {
+        let mut report = FullReport::new("options".into(), "Apple".into());
+        report.search_filters = Some(SearchFilters {
+            count: Some(0),
+            pages: Some(10000),
+            ..Default::default()
+        });
+        let options = SearchOptions::for_report(&report);
+        assert_eq!(options.count, 1);
+        assert_eq!(options.pages, 5);
+        report.search_filters = Some(SearchFilters {
+            count: Some(usize::MAX),
+            pages: Some(0),
+            ..Default::default()
+        });
+        let options = SearchOptions::for_report(&report);
+        assert_eq!(options.count, 20);
+        assert_eq!(options.pages, 1);
+    }
+}
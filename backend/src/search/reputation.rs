@@ -0,0 +1,251 @@
+use std::env;
+
+use log::debug;
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// How far a source can be trusted, from its domain alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrustTier {
+    /// Put on the allow list by configuration
+    Allowed,
+    Regulator,
+    Exchange,
+    MajorNews,
+    #[default]
+    Unknown,
+    Blog,
+    /// Put on the deny list, never scraped
+    Blocked,
+}
+
+impl TrustTier {
+    pub fn score(&self) -> f64 {
+        match self {
+            TrustTier::Allowed => 1.0,
+            TrustTier::Regulator => 1.0,
+            TrustTier::Exchange => 0.9,
+            TrustTier::MajorNews => 0.75,
+            TrustTier::Unknown => 0.5,
+            TrustTier::Blog => 0.25,
+            TrustTier::Blocked => 0.0,
+        }
+    }
+}
+
+const REGULATORS: &[&str] = &[
+    "sec.gov",
+    "finra.org",
+    "federalreserve.gov",
+    "esma.europa.eu",
+    "ecb.europa.eu",
+    "eba.europa.eu",
+    "fca.org.uk",
+    "bankofengland.co.uk",
+    "afm.nl",
+    "dnb.nl",
+    "amf-france.org",
+    "fsma.be",
+    "bafin.de",
+];
+
+const EXCHANGES: &[&str] = &[
+    "nasdaq.com",
+    "nyse.com",
+    "euronext.com",
+    "londonstockexchange.com",
+    "deutsche-boerse.com",
+    "cmegroup.com",
+    "cboe.com",
+    "six-group.com",
+    "jpx.co.jp",
+    "hkex.com.hk",
+];
+
+const MAJOR_NEWS: &[&str] = &[
+    "reuters.com",
+    "bloomberg.com",
+    "ft.com",
+    "wsj.com",
+    "cnbc.com",
+    "economist.com",
+    "apnews.com",
+    "bbc.com",
+    "bbc.co.uk",
+    "nytimes.com",
+    "marketwatch.com",
+    "barrons.com",
+    "morningstar.com",
+    "fd.nl",
+    "lesechos.fr",
+    "handelsblatt.com",
+];
+
+const BLOGS: &[&str] = &[
+    "medium.com",
+    "substack.com",
+    "blogspot.com",
+    "wordpress.com",
+    "seekingalpha.com",
+    "fool.com",
+    "reddit.com",
+    "quora.com",
+];
+
+/// Price forecasting spam, these pages look authoritative but are generated
+const SPAM: &[&str] = &[
+    "coincodex.com",
+    "walletinvestor.com",
+    "gov.capital",
+    "longforecast.com",
+    "30rates.com",
+    "coinpriceforecast.com",
+    "stockforecast.com",
+];
+
+fn matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+fn env_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Scores domains with built-in trust tiers and configurable allow and deny lists.
+#[derive(Debug, Clone, Default)]
+pub struct DomainReputation {
+    allow: Vec<String>,
+    deny: Vec<String>,
+    /// Sources scoring below this are dropped from the search results
+    pub min_score: f64,
+}
+
+/// Configured with `TRUST_ALLOW_DOMAINS`, `TRUST_DENY_DOMAINS` and `TRUST_MIN_SCORE`.
+pub static REPUTATION: Lazy<DomainReputation> = Lazy::new(|| {
+    let reputation = DomainReputation::new(
+        env_list("TRUST_ALLOW_DOMAINS"),
+        env_list("TRUST_DENY_DOMAINS"),
+    )
+    .with_min_score(
+        env::var("TRUST_MIN_SCORE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0.0),
+    );
+    debug!("Domain reputation: {:?}", reputation);
+    reputation
+});
+
+impl DomainReputation {
+    pub fn new(allow: Vec<String>, deny: Vec<String>) -> Self {
+        DomainReputation {
+            allow,
+            deny,
+            min_score: 0.0,
+        }
+    }
+
+    pub fn with_min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// The deny list wins over the allow list, which wins over the built-in tiers.
+    pub fn tier(&self, url: &str) -> TrustTier {
+        let Some(host) = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_lowercase))
+        else {
+            return TrustTier::Unknown;
+        };
+        let listed = |list: &[&str]| list.iter().any(|domain| matches(&host, domain));
+        if self.deny.iter().any(|domain| matches(&host, domain)) || listed(SPAM) {
+            TrustTier::Blocked
+        } else if self.allow.iter().any(|domain| matches(&host, domain)) {
+            TrustTier::Allowed
+        } else if listed(REGULATORS) || host.ends_with(".gov") {
+            TrustTier::Regulator
+        } else if listed(EXCHANGES) {
+            TrustTier::Exchange
+        } else if listed(MAJOR_NEWS) {
+            TrustTier::MajorNews
+        } else if listed(BLOGS) {
+            TrustTier::Blog
+        } else {
+            TrustTier::Unknown
+        }
+    }
+
+    pub fn score(&self, url: &str) -> f64 {
+        self.tier(url).score()
+    }
+
+    /// Whether a source is worth scraping at all
+    pub fn accepts(&self, url: &str) -> bool {
+        let tier = self.tier(url);
+        tier != TrustTier::Blocked && tier.score() >= self.min_score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_tiers() {
+        let reputation = DomainReputation::default();
+        for (url, tier) in [
+            (
+                "https://www.sec.gov/cgi-bin/browse-edgar",
+                TrustTier::Regulator,
+            ),
+            ("https://www.treasury.gov/", TrustTier::Regulator),
+            (
+                "https://www.nasdaq.com/market-activity/stocks/aapl",
+                TrustTier::Exchange,
+            ),
+            ("https://www.cnbc.com/quotes/AAPL", TrustTier::MajorNews),
+            ("https://investor.medium.com/apple", TrustTier::Blog),
+            (
+                "https://coincodex.com/stock/AAPL/price-prediction/",
+                TrustTier::Blocked,
+            ),
+            ("https://www.technavio.com/report/apple", TrustTier::Unknown),
+            ("https://notreuters.com/apple", TrustTier::Unknown),
+            ("not a url", TrustTier::Unknown),
+        ] {
+            assert_eq!(reputation.tier(url), tier, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_allow_and_deny_lists() {
+        let reputation = DomainReputation::new(
+            vec!["captide.co".into(), "cnbc.com".into()],
+            vec!["cnbc.com".into()],
+        );
+        assert_eq!(
+            reputation.tier("https://www.captide.co/insights"),
+            TrustTier::Allowed
+        );
+        assert_eq!(reputation.tier("https://www.cnbc.com/"), TrustTier::Blocked);
+        assert!(!reputation.accepts("https://www.cnbc.com/"));
+    }
+
+    #[test]
+    fn test_min_score() {
+        let reputation = DomainReputation::default().with_min_score(0.5);
+        assert!(reputation.accepts("https://www.reuters.com/apple"));
+        assert!(reputation.accepts("https://www.technavio.com/report/apple"));
+        assert!(!reputation.accepts("https://www.reddit.com/r/stocks"));
+        assert!(!DomainReputation::default().accepts("https://coincodex.com/"));
+        assert!(DomainReputation::default().accepts("https://www.reddit.com/r/stocks"));
+    }
+}
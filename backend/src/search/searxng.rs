@@ -0,0 +1,198 @@
+use crate::prelude::*;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::{extend_unique, SafeSearch, SearchEngine, SearchOptions, SearchResult, TimeRange};
+
+#[derive(Default)]
+pub struct SearxNG {
+    base_url: String,
+    client: Client,
+}
+
+impl SearxNG {
+    pub fn new(base_url: &str) -> Self {
+        SearxNG {
+            base_url: base_url.to_string(),
+            client: Client::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxNGResult {
+    results: Vec<SearxNGItem>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearxNGItem {
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    engine: String,
+    published_date: Option<String>,
+    score: Option<f64>,
+}
+
+#[async_trait]
+impl SearchEngine for SearxNG {
+    async fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>> {
+        let mut params = vec![
+            ("q", options.query(query)),
+            ("format", "json".into()),
+            (
+                "safesearch",
+                match options.safe_search {
+                    SafeSearch::Off => "0",
+                    SafeSearch::Moderate => "1",
+                    SafeSearch::Strict => "2",
+                }
+                .into(),
+            ),
+        ];
+        if let Some(time_range) = options.time_range {
+            let time_range = match time_range {
+                TimeRange::Day => "day",
+                TimeRange::Week => "week",
+                TimeRange::Month => "month",
+                TimeRange::Year => "year",
+            };
+            params.push(("time_range", time_range.into()));
+        }
+        if let Some(language) = &options.language {
+            params.push(("language", language.clone()));
+        }
+        if !options.categories.is_empty() {
+            params.push(("categories", options.categories.join(",")));
+        }
+
+        let mut urls = Vec::new();
+        for page in 1..=options.pages.max(1) {
+            let results: SearxNGResult = self
+                .client
+                .get(format!("{}/search", self.base_url))
+                .query(&params)
+                .query(&[("pageno", page)])
+                .send()
+                .await?
+                .json()
+                .await?;
+            if results.results.is_empty() {
+                break;
+            }
+            extend_unique(
+                &mut urls,
+                results
+                    .results
+                    .into_iter()
+                    .map(|r| SearchResult {
+                        url: r.url,
+                        title: r.title,
+                        snippet: r.content,
+                        engine: r.engine,
+                        published_date: r.published_date,
+                        score: r.score,
+                    })
+                    .filter(|r| options.allows(&r.url)),
+            );
+            if urls.len() >= options.count {
+                break;
+            }
+        }
+        urls.truncate(options.count);
+        Ok(urls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::stub;
+
+    #[tokio::test]
+    async fn test_searxng_stub() {
+        let (address, requests) = stub::serve_json(
+            r#"{"results": [
+                {
+                    "url": "https://a.com/1",
+                    "title": "A",
+                    "content": "Apple beats estimates",
+                    "engine": "bing",
+                    "publishedDate": "2025-01-30T00:00:00",
+                    "score": 2.5
+                },
+                {"url": "https://b.com/2", "title": "B"},
+                {"url": "https://c.com/3", "title": "C"},
+                {"url": "https://d.com/4", "title": "D"}
+            ]}"#,
+        )
+        .await;
+        let results = SearxNG::new(&address)
+            .search("apple", &SearchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            results.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            vec!["https://a.com/1", "https://b.com/2", "https://c.com/3"]
+        );
+        assert_eq!(
+            results[0],
+            SearchResult {
+                url: "https://a.com/1".into(),
+                title: "A".into(),
+                snippet: "Apple beats estimates".into(),
+                engine: "bing".into(),
+                published_date: Some("2025-01-30T00:00:00".into()),
+                score: Some(2.5),
+            }
+        );
+        assert_eq!(results[1].title, "B");
+        assert_eq!(results[1].published_date, None);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("GET /search?q=apple&format=json&safesearch=1&pageno=1 "));
+    }
+
+    #[tokio::test]
+    async fn test_searxng_stub_options() {
+        let (address, requests) = stub::serve_json(
+            r#"{"results": [
+                {"url": "https://a.com/1"},
+                {"url": "https://www.b.com/2"},
+                {"url": "https://c.com/3"}
+            ]}"#,
+        )
+        .await;
+        let options = SearchOptions {
+            count: 3,
+            pages: 2,
+            time_range: Some(TimeRange::Month),
+            language: Some("nl".into()),
+            categories: vec!["news".into(), "general".into()],
+            safe_search: SafeSearch::Off,
+            include_sites: vec!["a.com".into(), "b.com".into()],
+            exclude_sites: vec![],
+        };
+        let results = SearxNG::new(&address)
+            .search("apple & co", &options)
+            .await
+            .unwrap();
+        // Only two results per page pass the site filter, the second page repeats them
+        assert_eq!(
+            results.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            vec!["https://a.com/1", "https://www.b.com/2"]
+        );
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with(
+            "GET /search?q=apple+%26+co+%28site%3Aa.com+OR+site%3Ab.com%29&format=json\
+            &safesearch=0&time_range=month&language=nl&categories=news%2Cgeneral&pageno=1 "
+        ));
+        assert!(requests[1].contains("&pageno=2 "));
+    }
+}
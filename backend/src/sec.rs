@@ -1,6 +1,7 @@
 use log::debug;
 use serde::{Deserialize, Serialize};
-use tokio::time::{sleep, Duration};
+
+use crate::scraping::politeness::{POLITENESS, USER_AGENT};
 
 #[derive(Debug, Deserialize, Serialize)]
 struct RecentFilings {
@@ -15,11 +16,11 @@ struct RecentFilings {
 async fn get_cik_from_ticker(ticker: &str) -> Option<String> {
     debug!("Fetching CIK for ticker: {:#?}", ticker);
     let url = "https://www.sec.gov/files/company_tickers.json";
-    let user_agent = "FinAnalizeBot/1.0 (nguijoel.bryana@student.ehb.be)";
+    let _slot = POLITENESS.slot(url).await;
 
     let response = reqwest::Client::new()
         .get(url)
-        .header("User-Agent", user_agent)
+        .header("User-Agent", USER_AGENT.as_str())
         .send()
         .await
         .ok()?
@@ -47,13 +48,12 @@ async fn get_cik_from_ticker(ticker: &str) -> Option<String> {
 /// Fetches only the relevant SEC filing data for a given CIK, filtering by Form 10-K
 async fn get_latest_filing_links(cik: &str) -> Option<Vec<String>> {
     debug!("Fetching latest filings for CIK: {:#?}", cik);
-    sleep(Duration::from_secs(1)).await;
     let url = format!("https://data.sec.gov/submissions/CIK{}.json", cik);
-    let user_agent = "FinAnalizeBot/1.0 (nguijoel.bryana@student.ehb.be)";
+    let _slot = POLITENESS.slot(&url).await;
 
     let response = reqwest::Client::new()
         .get(&url)
-        .header("User-Agent", user_agent)
+        .header("User-Agent", USER_AGENT.as_str())
         .send()
         .await
         .ok()?;
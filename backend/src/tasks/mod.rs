@@ -1,13 +1,17 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use crate::{
-    llm::{GenerationParams, GenerationResult, LLMApi},
+    llm::{scheduler::Priority, GenerationParams, GenerationResult, LLMApi},
     prelude::*,
 };
 use handlebars::Handlebars;
 use log::{debug, error, info, warn};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
+use tokio::time::sleep;
+
+/// The longest `run_raw` waits before retrying a generation that timed out
+const MAX_TIMEOUT_BACKOFF: Duration = Duration::from_secs(30);
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum RetryStrategy {
@@ -69,6 +73,11 @@ impl Task {
         self
     }
 
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.params.priority = priority;
+        self
+    }
+
     pub fn with_retry_strategy(mut self, strategy: RetryStrategy) -> Self {
         self.retry_strategy = strategy;
         self
@@ -83,30 +92,36 @@ impl Task {
     where
         T: Serialize,
     {
-        use std::time::Duration;
-        use tokio::time::{sleep, timeout};
-
         let template = Handlebars::default().render_template(&self.prompt, input)?;
 
+        let attempts = match self.retry_strategy {
+            RetryStrategy::None => Some(1),
+            RetryStrategy::Count(count) => Some(count.max(1)),
+            RetryStrategy::UntilSuccess => None,
+        };
+        let mut backoff = Duration::from_millis(500);
+        let mut attempt = 0;
         loop {
+            attempt += 1;
             debug!("trying to generate");
-            match timeout(
-                Duration::from_secs(60),
-                api.generate(&self.params, template.clone()),
-            )
-            .await
-            {
-                Ok(res) => {
+            // The scheduler only times the generation itself, not the time spent queued
+            match api.generate(&self.params, template.clone()).await {
+                Err(FinanalizeError::LLMTimeout(model))
+                    if attempts.is_none_or(|attempts| attempt < attempts) =>
+                {
+                    warn!(
+                        "Timeout occurred for {} on attempt {}, retrying in {:?}",
+                        model, attempt, backoff
+                    );
+                    sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_TIMEOUT_BACKOFF);
+                }
+                res => {
                     return res.map(|res| TaskResult {
                         output: res.generated.clone(),
                         info: res,
                     })
                 }
-                Err(_) => {
-                    // Timeout occurred, retry indefinitely
-                    debug!("Timeout occurred, retrying indefinitely");
-                    sleep(Duration::from_millis(500)).await; // Wait before retrying
-                }
             }
         }
     }
@@ -169,19 +184,27 @@ impl Task {
     {
         debug!("Starting generation.");
         let res = api
-            .generate_json(&self.params, prompt.clone(), schema)
+            .generate_json(&self.params, prompt.clone(), schema.clone())
             .await?;
         let json = res.generated.clone();
         info!("Generated");
         let full = format!("{}{}", prompt, json);
         debug!("Parsing output.");
-        let json = self.parse_output(&full)?;
-        info!("Parsed output");
-        debug!("Deserializing output.");
-        Ok(TaskResult {
-            output: self.deserialize_output(json)?,
-            info: res,
-        })
+        let output = match self.parse_output(&full) {
+            Ok(json) => self.deserialize_output(json),
+            Err(err) => Err(err),
+        };
+        match output {
+            Ok(output) => {
+                info!("Parsed output");
+                Ok(TaskResult { output, info: res })
+            }
+            Err(err) => {
+                // Otherwise every retry would get the same output from the cache
+                api.forget(&self.params, &prompt, Some(&schema)).await;
+                Err(err)
+            }
+        }
     }
 
     fn deserialize_output<U>(&self, json: String) -> Result<U>
@@ -407,3 +430,127 @@ impl Task {
 //        }
 //    }
 //}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::llm::{Api, GenerationCaching};
+
+    /// Replays its last output like the response cache until it is told to forget it.
+    struct MockApi {
+        outputs: Mutex<Vec<&'static str>>,
+        cached: Mutex<Option<&'static str>>,
+    }
+
+    #[async_trait]
+    impl LLMApi for MockApi {
+        async fn generate(
+            &self,
+            _params: &GenerationParams,
+            _prompt: String,
+        ) -> Result<GenerationResult> {
+            let mut cached = self.cached.lock().unwrap();
+            let generated = *cached.get_or_insert_with(|| self.outputs.lock().unwrap().remove(0));
+            Ok(GenerationResult {
+                generated: generated.into(),
+                api: Api::Ollama,
+                prompt_token_count: 10,
+                generated_token_count: 10,
+                caching: GenerationCaching::None,
+                total_duration_us: 0,
+                cache_hit: false,
+            })
+        }
+
+        async fn generate_json(
+            &self,
+            params: &GenerationParams,
+            prompt: String,
+            _json_schema: String,
+        ) -> Result<GenerationResult> {
+            self.generate(params, prompt).await
+        }
+
+        async fn embed(&self, _text: String) -> Result<Vec<f32>> {
+            Ok(vec![])
+        }
+
+        async fn forget(&self, _params: &GenerationParams, _prompt: &str, _schema: Option<&str>) {
+            *self.cached.lock().unwrap() = None;
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct City {
+        city: String,
+    }
+
+    #[tokio::test]
+    async fn test_retry_forgets_invalid_output() {
+        let api = Arc::new(MockApi {
+            outputs: Mutex::new(vec![r#"{"city": "Amster"#, r#"{"city": "Amsterdam"}"#]),
+            cached: Mutex::new(None),
+        });
+        let task = Task::new("Extract the city from: {{message}}\n<Output>\n");
+        let res: TaskResult<City> = task
+            .run_structured(
+                api.clone(),
+                &serde_json::json!({"message": "I live in Amsterdam"}),
+                "{}".into(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.output.city, "Amsterdam");
+        assert!(api.outputs.lock().unwrap().is_empty());
+    }
+
+    /// Times out on every generation, like a model that never finishes in time.
+    struct TimeoutApi {
+        calls: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl LLMApi for TimeoutApi {
+        async fn generate(
+            &self,
+            params: &GenerationParams,
+            _prompt: String,
+        ) -> Result<GenerationResult> {
+            *self.calls.lock().unwrap() += 1;
+            Err(FinanalizeError::LLMTimeout(params.model.clone()))
+        }
+
+        async fn generate_json(
+            &self,
+            params: &GenerationParams,
+            prompt: String,
+            _json_schema: String,
+        ) -> Result<GenerationResult> {
+            self.generate(params, prompt).await
+        }
+
+        async fn embed(&self, _text: String) -> Result<Vec<f32>> {
+            Ok(vec![])
+        }
+
+        async fn forget(&self, _params: &GenerationParams, _prompt: &str, _schema: Option<&str>) {}
+    }
+
+    #[tokio::test]
+    async fn test_run_raw_gives_up_after_timeouts() {
+        let api = Arc::new(TimeoutApi {
+            calls: Mutex::new(0),
+        });
+        let task = Task::new("Summarize: {{message}}").with_retry_strategy(RetryStrategy::Count(2));
+        let res = task
+            .run_raw(api.clone(), &serde_json::json!({"message": "Revenue grew"}))
+            .await;
+        assert!(matches!(res, Err(FinanalizeError::LLMTimeout(_))));
+        assert_eq!(*api.calls.lock().unwrap(), 2);
+    }
+}
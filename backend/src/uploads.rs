@@ -0,0 +1,267 @@
+use chrono::{DateTime, Utc};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+use uuid::Uuid;
+
+use crate::{
+    blobs::{self, PersistedBlob},
+    db::DB,
+    extractors::{csv::csv_data, Data},
+    models::PreClassificationSource,
+    prelude::*,
+    scraping::fetch::{self, sniff, DocumentKind},
+    workflow::job::scrape_pages::extract_document,
+};
+
+use std::path::Path;
+
+/// Uploaded documents are not on the web, so their sources get URLs of their own.
+pub const UPLOAD_SCHEME: &str = "upload://";
+
+/// How uploaded documents are cited in place of a publisher.
+pub const INTERNAL_DOCUMENT: &str = "Internal document";
+
+/// The URL the sources of an uploaded document are cited by, pages are added as `#page=N`.
+pub fn document_url(id: &str) -> String {
+    format!("{}{}", UPLOAD_SCHEME, id)
+}
+
+pub fn is_upload(url: &str) -> bool {
+    url.starts_with(UPLOAD_SCHEME)
+}
+
+/// The id of the uploaded document a source URL points to.
+pub fn document_id(url: &str) -> Option<&str> {
+    let rest = url.strip_prefix(UPLOAD_SCHEME)?;
+    rest.split(['#', '/']).next().filter(|id| !id.is_empty())
+}
+
+/// An uploaded document as attached to a report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Upload {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateDocument {
+    pub user: Thing,
+    pub name: String,
+    pub blob: String,
+    pub sources: Vec<PreClassificationSource>,
+    pub tables: Vec<Data>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SDBDocument {
+    pub id: Thing,
+    pub user: Thing,
+    pub name: String,
+    pub blob: String,
+    pub sources: Vec<PreClassificationSource>,
+    pub tables: Vec<Data>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&SDBDocument> for Upload {
+    fn from(document: &SDBDocument) -> Self {
+        Upload {
+            id: document.id.id.to_string(),
+            name: document.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrontendDocument {
+    pub id: String,
+    pub name: String,
+    pub sources: usize,
+    pub tables: usize,
+}
+
+impl From<SDBDocument> for FrontendDocument {
+    fn from(document: SDBDocument) -> Self {
+        FrontendDocument {
+            id: document.id.id.to_string(),
+            name: document.name,
+            sources: document.sources.len(),
+            tables: document.tables.len(),
+        }
+    }
+}
+
+/// Keeps the file name of an upload and drops any directories the client sent along.
+pub fn file_name(name: Option<&str>) -> String {
+    name.and_then(|name| Path::new(name.rsplit(['/', '\\']).next()?).file_name())
+        .map(|name| name.to_string_lossy().trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "document".into())
+}
+
+/// Decides how to extract an upload, only the formats reports can cite are accepted.
+pub fn upload_kind(name: &str, content_type: Option<&str>, body: &[u8]) -> Result<DocumentKind> {
+    // The extension decides when the name has one, the sniffer expects a URL
+    let url = format!("{}upload/{}", UPLOAD_SCHEME, name.replace(['#', '?'], "_"));
+    match sniff(content_type, &url, body) {
+        kind @ (DocumentKind::Pdf
+        | DocumentKind::Csv
+        | DocumentKind::Spreadsheet
+        | DocumentKind::WordProcessing
+        | DocumentKind::Presentation
+        | DocumentKind::Text) => Ok(kind),
+        _ => Err(FinanalizeError::UnsupportedDocument(name.to_string())),
+    }
+}
+
+/// Titles the table of an uploaded CSV after its file name.
+fn csv_title(name: &str) -> String {
+    Path::new(name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace(['_', '-'], " "))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Persists an uploaded file, extracts its sources and tables and stores them for the user.
+pub async fn store(
+    user: Thing,
+    name: String,
+    content_type: Option<&str>,
+    file: &Path,
+) -> Result<SDBDocument> {
+    let body = tokio::fs::read(file).await?;
+    let kind = upload_kind(&name, content_type, &body)?;
+    // Sources cite the document by its id, so it is chosen before anything is stored
+    let id = Uuid::new_v4().simple().to_string();
+
+    let url = document_url(&id);
+    let extracted = match kind {
+        // Classifying a table costs an LLM call, which belongs to a report rather than an
+        // upload, so the columns are only typed
+        DocumentKind::Csv => csv_data(csv_title(&name), &body).map(|mut data| {
+            data.source = Some(url);
+            (vec![], vec![data])
+        }),
+        kind => extract_document(fetch::Fetched { url, kind, body }).await,
+    };
+    let (sources, tables) = match extracted {
+        Ok(extracted) => extracted,
+        Err(FinanalizeError::UnsupportedDocument(_)) => {
+            return Err(FinanalizeError::UnsupportedDocument(name))
+        }
+        Err(err) => {
+            error!("Failed to extract {}: {}", name, err);
+            return Err(err);
+        }
+    };
+    debug!(
+        "Extracted {} sources and {} tables from {}",
+        sources.len(),
+        tables.len(),
+        name
+    );
+    // Only kept once extraction succeeded, so failed uploads leave no blobs behind. The blob is
+    // named after the upload rather than the temporary file
+    let dir = tempfile::tempdir()?;
+    let named = dir.path().join(&name);
+    tokio::fs::copy(file, &named).await?;
+    let PersistedBlob { id: blob, .. } = blobs::persist(named).await?;
+    DB.get()
+        .unwrap()
+        .create(("document", id.as_str()))
+        .content(CreateDocument {
+            user,
+            name,
+            blob,
+            sources,
+            tables,
+            created_at: Utc::now(),
+        })
+        .await?
+        .ok_or(FinanalizeError::NotFound)
+}
+
+/// The documents with the given ids, as long as they were uploaded by the user.
+pub async fn owned(user: &Thing, ids: &[String]) -> Result<Vec<SDBDocument>> {
+    let mut documents = vec![];
+    for id in ids {
+        let document: Option<SDBDocument> =
+            DB.get().unwrap().select(("document", id.as_str())).await?;
+        match document {
+            Some(document) if &document.user == user => documents.push(document),
+            _ => return Err(FinanalizeError::NotFound),
+        }
+    }
+    Ok(documents)
+}
+
+/// Loads the documents attached to a report, ownership was checked when it was created.
+pub async fn load(uploads: &[Upload]) -> Result<Vec<SDBDocument>> {
+    let mut documents = vec![];
+    for upload in uploads {
+        let document: Option<SDBDocument> = DB
+            .get()
+            .unwrap()
+            .select(("document", upload.id.as_str()))
+            .await?;
+        documents.push(document.ok_or(FinanalizeError::NotFound)?);
+    }
+    Ok(documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_url() {
+        let url = document_url("f3a9");
+        assert!(is_upload(&url));
+        assert_eq!(document_id(&url), Some("f3a9"));
+        assert_eq!(document_id(&format!("{}#page=4", url)), Some("f3a9"));
+        assert!(!is_upload("https://www.sec.gov/"));
+        assert_eq!(document_id("https://www.sec.gov/"), None);
+    }
+
+    #[test]
+    fn test_upload_kind() {
+        assert_eq!(
+            upload_kind("model.xlsx", None, b"").unwrap(),
+            DocumentKind::Spreadsheet
+        );
+        assert_eq!(
+            upload_kind("Board update.docx", None, b"").unwrap(),
+            DocumentKind::WordProcessing
+        );
+        assert_eq!(
+            upload_kind("notes.md", Some("application/octet-stream"), b"# Notes").unwrap(),
+            DocumentKind::Text
+        );
+        // The signature wins over a wrong extension
+        assert_eq!(
+            upload_kind("report.txt", None, b"%PDF-1.7").unwrap(),
+            DocumentKind::Pdf
+        );
+        assert!(matches!(
+            upload_kind("page.html", Some("text/html"), b"<html></html>"),
+            Err(FinanalizeError::UnsupportedDocument(name)) if name == "page.html"
+        ));
+    }
+
+    #[test]
+    fn test_csv_title() {
+        assert_eq!(csv_title("revenue_by-segment.csv"), "revenue by segment");
+        assert_eq!(csv_title("Q3 model"), "Q3 model");
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name(Some("Q3 model.xlsx")), "Q3 model.xlsx");
+        assert_eq!(file_name(Some("C:\\Users\\me\\plan.pdf")), "plan.pdf");
+        assert_eq!(file_name(Some("../../etc/passwd")), "passwd");
+        assert_eq!(file_name(Some("..")), "document");
+        assert_eq!(file_name(None), "document");
+    }
+}
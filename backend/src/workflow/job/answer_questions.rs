@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 use itertools::izip;
-use log::debug;
+use log::{debug, warn};
 use models::{AnswerQuestionsInput, QuestionAnswer};
 
 use crate::llm::API;
@@ -25,6 +27,7 @@ pub mod models {
         #[serde(rename = "subSection")]
         pub sub_section: String,
         pub question: String,
+        pub language: String,
     }
 
     #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -44,6 +47,16 @@ pub struct AnswerQuestionsJob;
 pub struct W(Vec<DistancedChunk>);
 
 impl W {
+    /// Weighs the similarity with the trust of the source, so trusted sources make the context first
+    fn rank_by_trust(mut self, sources: &HashMap<String, f64>) -> Self {
+        for chunk in self.0.iter_mut() {
+            chunk.trust = sources.get(&chunk.source_id).copied().unwrap_or(0.5);
+        }
+        let weight = |c: &DistancedChunk| c.distance * (0.5 + 0.5 * c.trust);
+        self.0.sort_by(|a, b| weight(b).total_cmp(&weight(a)));
+        self
+    }
+
     // Only keep top results which make string length less than len
     fn into_context(self, len: usize) -> Vec<DistancedChunk> {
         let mut total_len = 0;
@@ -65,6 +78,13 @@ impl Job for AnswerQuestionsJob {
         debug!("Running AnswerQuestionsJob for report {}", state.id);
         let prompt = prompting::get_prompt("answer-questions".into())?;
         let task = Task::new(&prompt);
+        let trust: HashMap<String, f64> = state
+            .state
+            .sources
+            .iter()
+            .flatten()
+            .map(|s| (s.id.clone(), s.trust_score))
+            .collect();
         let mut pairs = Vec::new();
         for (section_name, sub_sections, sub_section_questions) in izip!(
             state.state.sections.clone().unwrap().into_iter(),
@@ -87,19 +107,20 @@ impl Job for AnswerQuestionsJob {
                     )
                     .await?;
                     if context.is_empty() {
-                        panic!(
+                        // Without sources the answer would be made up, so leave the question out
+                        warn!(
                             "Empty context for report:{}({}) and question: {}",
-                            state.id.clone(),
-                            state.state.id.clone(),
-                            question
+                            state.id, state.state.id, question
                         );
+                        continue;
                     }
                     let input = AnswerQuestionsInput {
-                        sources: W(context).into_context(8192),
+                        sources: W(context).rank_by_trust(&trust).into_context(8192),
                         title: state.state.title.clone().unwrap(),
                         section: section_name.clone(),
                         sub_section: sub_section_name.clone(),
                         question: question.clone(),
+                        language: state.state.language.name().into(),
                     };
                     let res = task.run_raw(API.clone(), &input).await?;
                     let answer = res.output;
@@ -120,12 +141,43 @@ mod tests {
     use chrono::Utc;
 
     use super::*;
+    use crate::search::reputation::TrustTier;
 
     use crate::{
         models::FullReport,
         workflow::{job::classify_sources::models::ClassifiedSource, JobType, WorkflowState},
     };
 
+    #[test]
+    fn test_rank_by_trust() {
+        let chunk = |source_id: &str, distance: f64| DistancedChunk {
+            report_id: "report".into(),
+            source_id: source_id.into(),
+            chunk: "chunk".into(),
+            distance,
+            trust: 0.0,
+        };
+        let trust = HashMap::from([
+            ("website0".to_string(), 0.25),
+            ("website1".to_string(), 1.0),
+        ]);
+        let ranked = W(vec![
+            chunk("website0", 0.9),
+            chunk("website1", 0.8),
+            chunk("website2", 0.85),
+        ])
+        .rank_by_trust(&trust)
+        .0;
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|c| c.source_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["website1", "website2", "website0"]
+        );
+        assert_eq!(ranked[1].trust, 0.5);
+    }
+
     #[tokio::test]
     #[ignore = "Uses LLM API (External Service)"]
     async fn test_classify_job_valid() {
@@ -178,8 +230,10 @@ Apple said it expected growth in the March quarter of "low to mid single digits"
                         url: "https://www.nbcboston.com/news/business/money-report/apple-reports-first-quarter-earnings-after-the-bell-2/3617779/?os=android&ref=app&noamp=mobile".into(),
                         title: "Apple shares rise 3% as boost in services revenue overshadows iPhone miss".into(),
                         author: "Kif Leswing, CNBC".into(),
-                        published_after: Some(Utc::now().format("%Y-%m-%d").to_string()),
-                        date: Some(Utc::now().format("%Y-%m-%d").to_string()),
+                        published_after: Some(Utc::now().date_naive()),
+                        date: Some(Utc::now().date_naive()),
+                        trust_tier: TrustTier::Unknown,
+                        trust_score: TrustTier::Unknown.score(),
                     }
                 ])
                 .with_sub_section_questions(vec![
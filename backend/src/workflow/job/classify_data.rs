@@ -1,7 +1,7 @@
 use std::io::Cursor;
 
 use crate::{
-    extractors::{csv::DataClassifierOuput, Column, Data},
+    extractors::{csv::DataClassifierOuput, table::typed_column, Data},
     llm::API,
     prelude::*,
     prompting,
@@ -52,6 +52,7 @@ impl Job for ClassifyDataJob {
             //Use ClassifySourceInput and put the table in it
             let input = ClassifySourcesInput {
                 input: markdown_table,
+                language: state.state.language.name().into(),
             };
 
             //Start job run structured data classification
@@ -68,6 +69,8 @@ impl Job for ClassifyDataJob {
             let output = res.output;
 
             // After getting your output from the task.run_structured call
+            // Scales such as "in millions" are often only in the title
+            let context = format!("{} {}", output.title, output.description);
             let mut columns = vec![];
             for column in df.get_columns() {
                 let column = column.cast(&polars::prelude::DataType::String)?;
@@ -80,25 +83,33 @@ impl Job for ClassifyDataJob {
                     .find(|c| c.title == column_name)
                     .map(|c| c.description.clone())
                     .unwrap_or_else(String::new);
-
-                columns.push(Column {
-                    name: column_name.into(),
-                    description, // Use the found description instead of an empty string
-                    values: column
-                        .str()?
-                        .into_iter()
-                        .map(|v| v.unwrap_or("").into())
-                        .collect(),
-                });
+                let values = column
+                    .str()?
+                    .into_iter()
+                    .map(|v| v.unwrap_or("").into())
+                    .collect();
+                let mut column = typed_column(column_name.into(), values, &context);
+                // The classifier's description beats the inferred unit
+                if !description.is_empty() {
+                    column.description = description;
+                }
+                columns.push(column);
             }
             let data = Data {
                 title: output.title,
                 description: output.description,
                 columns,
+                source: None,
+                page: None,
             };
             classified_sources.push(data);
         }
-        state.state.data_sources = Some(classified_sources);
+        // Data files found while scraping are already in there
+        state
+            .state
+            .data_sources
+            .get_or_insert_with(Vec::new)
+            .extend(classified_sources);
         Ok(state)
     }
 }
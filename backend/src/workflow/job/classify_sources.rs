@@ -1,20 +1,37 @@
 use async_trait::async_trait;
+use chrono::{Duration, NaiveDate};
+use log::debug;
 use models::{ClassifiedSource, ClassifySourcesInput, ClassifySourcesOutput};
 use schemars::schema_for;
 
-use crate::{llm::API, prelude::*, prompting, tasks::{Task, TaskResult}, workflow::WorkflowState};
+use crate::{
+    llm::API,
+    models::{FullReport, ScrapeOutcome, ScrapeStatus},
+    prelude::*,
+    prompting,
+    tasks::{Task, TaskResult},
+    uploads::document_id,
+    workflow::WorkflowState,
+};
 
 use super::Job;
 
 pub mod models {
+    use chrono::NaiveDate;
     use schemars::JsonSchema;
-    use serde::{Deserialize, Serialize};
+    use serde::{Deserialize, Deserializer, Serialize};
 
-    use crate::models::PreClassificationSource;
+    use crate::{
+        extractors::date::parse_published,
+        models::PreClassificationSource,
+        search::reputation::{TrustTier, REPUTATION},
+        uploads::{Upload, INTERNAL_DOCUMENT},
+    };
 
     #[derive(Debug, Serialize, Deserialize)]
     pub struct ClassifySourcesInput {
         pub input: String,
+        pub language: String,
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -27,21 +44,50 @@ pub mod models {
     }
 
     impl ClassifiedSource {
+        /// The date found in the source itself beats the one the model read from its text.
         pub fn from_id(
             id: String,
             value: ClassifySourcesOutput,
             pre: PreClassificationSource,
+            date: Option<NaiveDate>,
         ) -> Self {
+            let trust_tier = REPUTATION.tier(&pre.url);
             Self {
                 id,
+                trust_tier,
+                trust_score: trust_tier.score(),
                 title: value.title,
                 author: value.author,
-                date: value.date,
-                published_after: value.published_after,
+                date: date.or_else(|| value.date.as_deref().and_then(parse_published)),
+                published_after: value.published_after.as_deref().and_then(parse_published),
+                url: pre.url,
+                content: pre.content,
+            }
+        }
+    }
+
+    impl ClassifiedSource {
+        /// Uploaded documents need no classification, they are cited by their file name
+        pub fn from_upload(id: String, upload: &Upload, pre: PreClassificationSource) -> Self {
+            // The user chose to include them, so they are trusted like the allow list
+            let trust_tier = TrustTier::Allowed;
+            Self {
+                id,
+                trust_tier,
+                trust_score: trust_tier.score(),
+                title: upload.name.clone(),
+                author: INTERNAL_DOCUMENT.into(),
+                date: None,
+                published_after: None,
                 url: pre.url,
                 content: pre.content,
             }
         }
+
+        /// Whether the source was published on or after the cutoff, undated sources are kept.
+        pub fn is_fresh(&self, cutoff: NaiveDate) -> bool {
+            self.date.is_none_or(|date| date >= cutoff)
+        }
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,11 +95,32 @@ pub mod models {
         pub id: String,
         pub title: String,
         pub author: String,
-        pub date: Option<String>,
-        #[serde(rename = "publishedAfter")]
-        pub published_after: Option<String>,
+        /// Reports stored before dates were parsed kept what the model wrote
+        #[serde(default, deserialize_with = "published")]
+        pub date: Option<NaiveDate>,
+        /// Implied by the content when the source has no date
+        #[serde(default, rename = "publishedAfter", deserialize_with = "published")]
+        pub published_after: Option<NaiveDate>,
         pub url: String,
         pub content: String,
+        #[serde(default, rename = "trustTier")]
+        pub trust_tier: TrustTier,
+        /// From the domain reputation, between 0 and 1
+        #[serde(default = "default_trust_score", rename = "trustScore")]
+        pub trust_score: f64,
+    }
+
+    /// Reads a date as stored now or as free-form text, text that is no date becomes `None`.
+    fn published<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let stored: Option<String> = Option::deserialize(deserializer)?;
+        Ok(stored.as_deref().and_then(parse_published))
+    }
+
+    fn default_trust_score() -> f64 {
+        TrustTier::default().score()
     }
 }
 
@@ -65,6 +132,10 @@ impl Job for ClassifySourcesJob {
         let prompt = prompting::get_prompt("content-classifier".into())?;
         let task = Task::new(&prompt);
         let mut sources = Vec::new();
+        let uploads = state.state.documents.clone().unwrap_or_default();
+        let dates = state.state.source_dates.clone().unwrap_or_default();
+        let cutoff = source_cutoff(&state.state);
+        let mut stale = vec![];
         for (i, source) in state
             .state
             .md_sources
@@ -73,8 +144,22 @@ impl Job for ClassifySourcesJob {
             .into_iter()
             .enumerate()
         {
+            let upload = document_id(&source.url)
+                .and_then(|id| uploads.iter().find(|upload| upload.id == id));
+            if let Some(upload) = upload {
+                sources.push(ClassifiedSource::from_upload(
+                    format!("document{}", i),
+                    upload,
+                    source,
+                ));
+                continue;
+            }
+            // Sources known to be stale were left out when their content was extracted, this
+            // only catches the dates the model reads from the text
+            let date = dates.get(&source.url).copied();
             let input = ClassifySourcesInput {
                 input: source.content.clone(),
+                language: state.state.language.name().into(),
             };
             let res: TaskResult<ClassifySourcesOutput> = task
                 .run_structured(
@@ -85,26 +170,130 @@ impl Job for ClassifySourcesJob {
                 .await?;
             let output = res.output;
             state.state.generation_results.push(res.info);
-            sources.push(ClassifiedSource::from_id(
-                format!("website{}", i),
-                output,
-                source,
-            ));
+            let source = ClassifiedSource::from_id(format!("website{}", i), output, source, date);
+            if cutoff.is_some_and(|cutoff| !source.is_fresh(cutoff)) {
+                stale.push(source.url);
+                continue;
+            }
+            sources.push(source);
+        }
+        if let Some(cutoff) = cutoff {
+            debug!(
+                "Left out {} sources published before {}",
+                stale.len(),
+                cutoff
+            );
         }
+        state.state.scrape_outcomes = Some(stale_outcomes(
+            state.state.scrape_outcomes.take().unwrap_or_default(),
+            stale,
+        ));
         state.state.sources = Some(sources);
         Ok(state)
     }
 }
 
+/// Sources published before this date are left out of the report.
+pub fn source_cutoff(report: &FullReport) -> Option<NaiveDate> {
+    report
+        .max_source_age
+        .map(|days| report.created_at.date_naive() - Duration::days(days.into()))
+}
+
+/// Marks the documents stale sources came from, once per document rather than per page.
+pub fn stale_outcomes(mut outcomes: Vec<ScrapeOutcome>, stale: Vec<String>) -> Vec<ScrapeOutcome> {
+    for url in stale {
+        let url = url.split('#').next().unwrap_or(&url).to_string();
+        match outcomes.iter_mut().find(|outcome| outcome.url == url) {
+            Some(outcome) => outcome.status = ScrapeStatus::Stale,
+            None => outcomes.push(ScrapeOutcome::new(url, ScrapeStatus::Stale)),
+        }
+    }
+    outcomes
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use crate::{
-        models::{FullReport, PreClassificationSource},
+        models::PreClassificationSource,
         workflow::{JobType, WorkflowState},
     };
 
+    #[test]
+    fn test_source_dates() {
+        let output = ClassifySourcesOutput {
+            title: "Apple reports first quarter results".into(),
+            author: "Kif Leswing".into(),
+            date: Some("January 30, 2025".into()),
+            published_after: Some("unknown".into()),
+        };
+        let pre = PreClassificationSource {
+            url: "https://www.cnbc.com/2025/01/30/apple-aapl-q1-earnings-2025.html".into(),
+            content: String::new(),
+        };
+        let jan_30 = NaiveDate::from_ymd_opt(2025, 1, 30);
+        let source =
+            ClassifiedSource::from_id("website0".into(), output.clone(), pre.clone(), None);
+        assert_eq!(source.date, jan_30);
+        assert_eq!(source.published_after, None);
+        // The date found in the page itself wins
+        let jan_31 = NaiveDate::from_ymd_opt(2025, 1, 31);
+        let source = ClassifiedSource::from_id("website0".into(), output.clone(), pre, jan_31);
+        assert_eq!(source.date, jan_31);
+
+        assert!(source.is_fresh(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()));
+        assert!(!source.is_fresh(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()));
+        let undated = ClassifiedSource {
+            date: None,
+            ..source
+        };
+        assert!(undated.is_fresh(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()));
+    }
+
+    #[test]
+    fn test_old_source_dates() {
+        let stored = serde_json::json!({
+            "id": "website0",
+            "title": "Apple reports first quarter results",
+            "author": "Kif Leswing",
+            "date": "January 30, 2025",
+            "publishedAfter": "unknown",
+            "url": "https://www.cnbc.com/2025/01/30/apple-aapl-q1-earnings-2025.html",
+            "content": ""
+        });
+        let source: ClassifiedSource = serde_json::from_value(stored).unwrap();
+        assert_eq!(source.date, NaiveDate::from_ymd_opt(2025, 1, 30));
+        assert_eq!(source.published_after, None);
+
+        let stored = serde_json::to_value(&source).unwrap();
+        assert_eq!(stored["date"], "2025-01-30");
+        let source: ClassifiedSource = serde_json::from_value(stored).unwrap();
+        assert_eq!(source.date, NaiveDate::from_ymd_opt(2025, 1, 30));
+    }
+
+    #[test]
+    fn test_stale_outcomes() {
+        let outcomes = vec![
+            ScrapeOutcome::new("https://a.com/report.pdf", ScrapeStatus::Success),
+            ScrapeOutcome::new("https://b.com", ScrapeStatus::Success),
+        ];
+        let stale = vec![
+            "https://a.com/report.pdf#page=1".into(),
+            "https://a.com/report.pdf#page=9".into(),
+            "https://c.com/canonical".into(),
+        ];
+        assert_eq!(
+            stale_outcomes(outcomes, stale),
+            vec![
+                ScrapeOutcome::new("https://a.com/report.pdf", ScrapeStatus::Stale),
+                ScrapeOutcome::new("https://b.com", ScrapeStatus::Success),
+                ScrapeOutcome::new("https://c.com/canonical", ScrapeStatus::Stale),
+            ]
+        );
+    }
+
     #[tokio::test]
     #[ignore = "Uses LLM API (External Service)"]
     async fn test_classify_job_valid() {
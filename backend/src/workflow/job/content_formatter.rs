@@ -25,6 +25,7 @@ pub mod models {
         pub date: String,
         pub content: String,
         pub url: String,
+        pub language: String,
     }
 }
 
@@ -45,6 +46,7 @@ impl Job for FormatContentJob {
                 date: Utc::now().format("%Y-%m-%d").to_string(),
                 content: source.content,
                 url: source.url,
+                language: state.state.language.name().into(),
             };
             let res = task.run_raw(API.clone(), &input).await?;
             let output = res.output;
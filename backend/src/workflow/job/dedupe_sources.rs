@@ -0,0 +1,324 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use async_trait::async_trait;
+use log::debug;
+
+use crate::{
+    models::{PreClassificationSource, ScrapeOutcome, ScrapeStatus},
+    prelude::*,
+    search::{normalize_url, reputation::REPUTATION},
+    uploads::is_upload,
+    workflow::WorkflowState,
+};
+
+use super::Job;
+
+/// Words in a shingle, long enough that shared phrases don't make unrelated articles alike.
+const SHINGLE_SIZE: usize = 5;
+/// Hash functions in a signature, the similarity estimate is off by about `1/sqrt(128)`.
+const SIGNATURE_SIZE: usize = 128;
+/// Sources at least this similar are copies of the same story with a different frame.
+const DUPLICATE_SIMILARITY: f64 = 0.7;
+/// Shorter sources, like paywall stubs, are alike without being the same story.
+const MIN_SHINGLES: usize = 30;
+
+pub struct DedupeSourcesJob;
+
+/// MinHash signature of a text: for every hash function the smallest hash of its shingles.
+/// Two signatures agree in a fraction of places that estimates the Jaccard similarity of
+/// the shingles of the texts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature(Vec<u64>);
+
+/// Mixes the bits of a hash, so one shingle hash seeds all the hash functions.
+fn mix(mut hash: u64) -> u64 {
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
+
+impl Signature {
+    /// The signature of the word shingles of a text, `None` when it is too short to judge.
+    pub fn new(text: &str) -> Option<Self> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let shingles: HashSet<u64> = words
+            .windows(SHINGLE_SIZE)
+            .map(|shingle| {
+                let mut hasher = DefaultHasher::new();
+                shingle.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect();
+        if shingles.len() < MIN_SHINGLES {
+            return None;
+        }
+        let mut signature = vec![u64::MAX; SIGNATURE_SIZE];
+        for shingle in shingles {
+            for (i, min) in signature.iter_mut().enumerate() {
+                *min = (*min).min(mix(shingle ^ mix(i as u64 + 1)));
+            }
+        }
+        Some(Signature(signature))
+    }
+
+    /// Estimated Jaccard similarity of the shingles of two texts.
+    pub fn similarity(&self, other: &Signature) -> f64 {
+        let same = self.0.iter().zip(&other.0).filter(|(a, b)| a == b).count();
+        same as f64 / SIGNATURE_SIZE as f64
+    }
+}
+
+/// The document a source belongs to, pages of a PDF are sources of their own.
+fn document(url: &str) -> &str {
+    url.split('#').next().unwrap_or(url)
+}
+
+/// Finds sources that are near-duplicates of a more authoritative one, by URL of the
+/// duplicate to the URL of the source that is kept. Uploaded documents come first, then
+/// trust tier, then the order the sources were found in. Uploads are never dropped and
+/// pages of one document are never compared with each other.
+pub fn duplicates(sources: &[PreClassificationSource]) -> HashMap<String, String> {
+    let mut ranked: Vec<(usize, Option<Signature>)> = sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| (i, Signature::new(&source.content)))
+        .collect();
+    // Stable, so equally trusted sources keep their order
+    ranked.sort_by(|(a, _), (b, _)| {
+        let rank = |i: usize| {
+            let url = &sources[i].url;
+            (is_upload(url), REPUTATION.tier(url).score())
+        };
+        let (a, b) = (rank(*a), rank(*b));
+        b.0.cmp(&a.0).then(b.1.total_cmp(&a.1))
+    });
+
+    let mut kept: Vec<(usize, Signature)> = vec![];
+    let mut duplicates = HashMap::new();
+    for (i, signature) in ranked {
+        let Some(signature) = signature else {
+            continue;
+        };
+        let url = &sources[i].url;
+        let original = kept.iter().find(|(j, kept)| {
+            document(&sources[*j].url) != document(url)
+                && kept.similarity(&signature) >= DUPLICATE_SIMILARITY
+        });
+        match original {
+            Some((j, _)) if !is_upload(url) => {
+                duplicates.insert(url.clone(), sources[*j].url.clone());
+            }
+            _ => kept.push((i, signature)),
+        }
+    }
+    duplicates
+}
+
+#[async_trait]
+impl Job for DedupeSourcesJob {
+    /// Drops syndicated copies of the same story before they are formatted, classified and
+    /// cited several times. The questions and date of a copy carry over to the source kept.
+    async fn run(&self, mut state: WorkflowState) -> Result<WorkflowState> {
+        debug!("Running DedupeSourcesJob...");
+        let sources = state.state.md_sources.clone().unwrap_or_default();
+        let duplicates = duplicates(&sources);
+        debug!(
+            "Found {} near-duplicates among {} sources",
+            duplicates.len(),
+            sources.len()
+        );
+
+        let url_questions = state.state.url_questions.as_mut();
+        let dates = state.state.source_dates.get_or_insert_with(HashMap::new);
+        if let Some(url_questions) = url_questions {
+            for (duplicate, original) in &duplicates {
+                let Some(questions) = url_questions.remove(&normalize_url(duplicate)) else {
+                    continue;
+                };
+                let found_for = url_questions.entry(normalize_url(original)).or_default();
+                for question in questions {
+                    if !found_for.contains(&question) {
+                        found_for.push(question);
+                    }
+                }
+            }
+        }
+        for (duplicate, original) in &duplicates {
+            if let Some(date) = dates.remove(duplicate) {
+                dates.entry(original.clone()).or_insert(date);
+            }
+        }
+
+        state.state.md_sources = Some(
+            sources
+                .into_iter()
+                .filter(|source| !duplicates.contains_key(&source.url))
+                .collect(),
+        );
+        let duplicate_pages: HashSet<String> =
+            duplicates.keys().map(|url| normalize_url(url)).collect();
+        if let Some(html_sources) = state.state.html_sources.as_mut() {
+            html_sources.retain(|source| !duplicate_pages.contains(&normalize_url(&source.url)));
+        }
+        state.state.scrape_outcomes = Some(duplicate_outcomes(
+            state.state.scrape_outcomes.take().unwrap_or_default(),
+            duplicates.keys(),
+        ));
+        state.state.duplicate_sources = Some(duplicates);
+        Ok(state)
+    }
+}
+
+/// Marks the pages that were dropped as duplicates, a document with a duplicate page still
+/// has its other pages in the report.
+fn duplicate_outcomes<'a>(
+    mut outcomes: Vec<ScrapeOutcome>,
+    duplicates: impl Iterator<Item = &'a String>,
+) -> Vec<ScrapeOutcome> {
+    for url in duplicates.filter(|url| document(url) == url.as_str()) {
+        match outcomes.iter_mut().find(|outcome| outcome.url == *url) {
+            Some(outcome) => outcome.status = ScrapeStatus::Duplicate,
+            None => outcomes.push(ScrapeOutcome::new(url.clone(), ScrapeStatus::Duplicate)),
+        }
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        models::FullReport,
+        workflow::{JobType, WorkflowState},
+    };
+
+    const STORY: &str = "Apple on Thursday reported first-quarter results that beat Wall \
+        Street expectations for revenue and earnings, even as iPhone sales came in lower than \
+        analysts had expected. Revenue in Greater China fell 11 percent from a year earlier, \
+        while services revenue grew 14 percent to a record. The company said it expects \
+        revenue to grow in the low to mid single digits during the March quarter, and chief \
+        executive Tim Cook said the Apple Intelligence features drove upgrades in markets \
+        where they were available.";
+
+    const OTHER_STORY: &str = "Microsoft shares slid after the software maker said growth in \
+        its Azure cloud business would slow in the current quarter because of capacity \
+        constraints. The company spent a record amount on data centers during the period and \
+        said capital expenditures would keep rising through the fiscal year, as it races to \
+        meet demand for artificial intelligence services from corporate customers and \
+        developers around the world who build on its platform.";
+
+    fn source(url: &str, content: &str) -> PreClassificationSource {
+        PreClassificationSource {
+            url: url.into(),
+            content: content.into(),
+        }
+    }
+
+    #[test]
+    fn test_similarity() {
+        let story = Signature::new(STORY).unwrap();
+        let syndicated = Signature::new(&format!(
+            "By Reuters staff. {} Reporting by Stephen Nellis; editing by Leslie Adler.",
+            STORY
+        ))
+        .unwrap();
+        assert_eq!(story.similarity(&story), 1.0);
+        assert!(story.similarity(&syndicated) >= DUPLICATE_SIMILARITY);
+        assert!(story.similarity(&Signature::new(OTHER_STORY).unwrap()) < 0.1);
+        assert_eq!(Signature::new("Subscribe to keep reading."), None);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let sources = vec![
+            source("https://finance.yahoo.com/news/apple-results", STORY),
+            source("https://www.reuters.com/technology/apple-results", STORY),
+            source(
+                "https://applenewsdaily.com/apple-results",
+                &format!("Apple (AAPL) earnings. {}", STORY),
+            ),
+            // Pages of one document repeating themselves are left alone
+            source("https://example.com/report.pdf#page=1", OTHER_STORY),
+            source("https://example.com/report.pdf#page=2", OTHER_STORY),
+            source("https://example.com/stub", "Subscribe to keep reading."),
+        ];
+        let duplicates = duplicates(&sources);
+        // Reuters is the major news outlet, so its copy is kept
+        let reuters = "https://www.reuters.com/technology/apple-results".to_string();
+        assert_eq!(
+            duplicates.get("https://finance.yahoo.com/news/apple-results"),
+            Some(&reuters)
+        );
+        assert_eq!(
+            duplicates.get("https://applenewsdaily.com/apple-results"),
+            Some(&reuters)
+        );
+        assert_eq!(duplicates.len(), 2);
+    }
+
+    #[test]
+    fn test_uploads_are_kept() {
+        let sources = vec![
+            source("https://www.reuters.com/technology/apple-results", STORY),
+            source("upload://abc123", STORY),
+        ];
+        let duplicates = duplicates(&sources);
+        assert_eq!(
+            duplicates.get("https://www.reuters.com/technology/apple-results"),
+            Some(&"upload://abc123".to_string())
+        );
+        assert_eq!(duplicates.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_job() {
+        let yahoo = "https://finance.yahoo.com/news/apple-results";
+        let reuters = "https://www.reuters.com/technology/apple-results";
+        let mut report = FullReport::new("dedupe".into(), "Apple Q1 2025 results".into())
+            .with_raw_sources(vec![source(yahoo, STORY), source(reuters, STORY)]);
+        report.url_questions = Some(HashMap::from([
+            (normalize_url(yahoo), vec!["How did Apple do?".to_string()]),
+            (
+                normalize_url(reuters),
+                vec!["What about China?".to_string()],
+            ),
+        ]));
+        report.source_dates = Some(HashMap::from([(
+            yahoo.to_string(),
+            chrono::NaiveDate::from_ymd_opt(2025, 1, 30).unwrap(),
+        )]));
+        let state = WorkflowState {
+            id: "dedupe".into(),
+            state: report,
+            last_job_type: JobType::ExtractContent,
+        };
+        let state = DedupeSourcesJob.run(state).await.unwrap().state;
+
+        let urls: Vec<&str> = state
+            .md_sources
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|source| source.url.as_str())
+            .collect();
+        assert_eq!(urls, [reuters]);
+        assert_eq!(state.duplicate_sources.unwrap()[yahoo], reuters);
+        assert_eq!(
+            state.url_questions.unwrap()[&normalize_url(reuters)],
+            ["What about China?", "How did Apple do?"]
+        );
+        assert!(state.source_dates.unwrap().contains_key(reuters));
+        assert_eq!(
+            state.scrape_outcomes.unwrap(),
+            [ScrapeOutcome::new(yahoo, ScrapeStatus::Duplicate)]
+        );
+    }
+}
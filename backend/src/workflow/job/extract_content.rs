@@ -1,23 +1,44 @@
+use std::collections::HashSet;
+
 use async_trait::async_trait;
-use log::debug;
-use markup5ever::interface::tree_builder::TreeSink;
+use log::{debug, warn};
 use regex::Regex;
-use scraper::{Html, HtmlTreeSink, Selector};
+use reqwest::Url;
+use scraper::{Html, Selector};
 
+use crate::extractors::{date::published_date, figure::figures, readability::main_content};
 use crate::models::PreClassificationSource;
 use crate::prelude::*;
+use crate::search::{clean_url, local::LocalIndex, normalize_url};
+use crate::uploads::is_upload;
 
-use crate::workflow::WorkflowState;
+use crate::workflow::{
+    job::classify_sources::{source_cutoff, stale_outcomes},
+    WorkflowState,
+};
 
 use super::Job;
 
 pub struct ExtractContentJob;
 
+/// The `<link rel="canonical">` of a page, resolved against the URL it was scraped from.
+pub fn canonical_url(document: &Html, url: &str) -> Option<String> {
+    let selector = Selector::parse(r#"link[rel~="canonical"][href]"#).ok()?;
+    let href = document.select(&selector).next()?.value().attr("href")?;
+    let canonical = Url::parse(url).ok()?.join(href.trim()).ok()?;
+    matches!(canonical.scheme(), "http" | "https").then(|| clean_url(canonical.as_str()))
+}
+
 #[async_trait]
 impl Job for ExtractContentJob {
     async fn run(&self, mut state: WorkflowState) -> Result<WorkflowState> {
         // let extractor = HTMLExtractor;
-        let mut mds = vec![];
+        // Documents such as PDFs were already converted while scraping
+        let mut mds = state.state.md_sources.clone().unwrap_or_default();
+        let mut seen: HashSet<String> = mds.iter().map(|s| normalize_url(&s.url)).collect();
+        let mut url_questions = state.state.url_questions.clone().unwrap_or_default();
+        let mut found_figures = vec![];
+        let mut dates = state.state.source_dates.clone().unwrap_or_default();
         let html_sources = state.state.html_sources.clone().unwrap();
         let total = html_sources.len();
         let pattern = Regex::new("(?i)<span[^>]*>")?;
@@ -34,28 +55,33 @@ impl Job for ExtractContentJob {
             //     _ => continue,
             // }
             let document = Html::parse_document(&source.content);
-            // Selectors for header and footer
-            let header_selector = Selector::parse("header")?;
-            let footer_selector = Selector::parse("footer")?;
-
-            let mut removables = vec![];
-
-            // Remove the selected elements
-            for node in document.select(&header_selector).collect::<Vec<_>>() {
-                removables.push(node.id());
+            // Several search results can be the same article behind different URLs
+            let url = match canonical_url(&document, &source.url) {
+                Some(canonical) if normalize_url(&canonical) != normalize_url(&source.url) => {
+                    debug!("Canonical URL of {} is {}", source.url, canonical);
+                    if let Some(questions) = url_questions.remove(&normalize_url(&source.url)) {
+                        let found_for = url_questions.entry(normalize_url(&canonical)).or_default();
+                        for question in questions {
+                            if !found_for.contains(&question) {
+                                found_for.push(question);
+                            }
+                        }
+                    }
+                    canonical
+                }
+                _ => source.url,
+            };
+            if !seen.insert(normalize_url(&url)) {
+                debug!("Skipping duplicate of {}", url);
+                continue;
             }
-
-            for node in document.select(&footer_selector).collect::<Vec<_>>() {
-                removables.push(node.id());
+            if let Some(date) = published_date(&document, &url) {
+                dates.insert(url.clone(), date);
             }
-
-            let tree = HtmlTreeSink::new(document);
-
-            for removable in removables {
-                tree.remove_from_parent(&removable);
-            }
-
-            let filtered: String = tree.finish().html();
+            // Navigation, banners and related articles would drown out the article itself
+            let filtered = main_content(document);
+            // Only figures in the article itself, not thumbnails of other stories
+            found_figures.extend(figures(&Html::parse_fragment(&filtered), Some(&url)));
 
             let mut md = mdka::from_html(&filtered);
 
@@ -69,12 +95,45 @@ impl Job for ExtractContentJob {
                 .collect::<Vec<_>>()
                 .join("\n");
 
-            mds.push(PreClassificationSource {
-                url: source.url,
-                content: md,
-            })
+            mds.push(PreClassificationSource { url, content: md })
+        }
+        // Sources with a known date are left out as soon as they are stale, before they are
+        // indexed or cost any LLM calls. Uploaded documents are always kept
+        if let Some(cutoff) = source_cutoff(&state.state) {
+            let stale: Vec<String> = mds
+                .iter()
+                .filter(|source| !is_upload(&source.url))
+                .filter(|source| dates.get(&source.url).is_some_and(|date| *date < cutoff))
+                .map(|source| source.url.clone())
+                .collect();
+            debug!(
+                "Left out {} sources published before {}",
+                stale.len(),
+                cutoff
+            );
+            mds.retain(|source| !stale.contains(&source.url));
+            let stale_pages: HashSet<String> = stale.iter().map(|url| normalize_url(url)).collect();
+            if let Some(html_sources) = state.state.html_sources.as_mut() {
+                html_sources.retain(|source| !stale_pages.contains(&normalize_url(&source.url)));
+            }
+            state.state.scrape_outcomes = Some(stale_outcomes(
+                state.state.scrape_outcomes.take().unwrap_or_default(),
+                stale,
+            ));
+        }
+        // Later searches can find these pages again without hitting the web, uploaded
+        // documents stay private to the report
+        for source in mds.iter().filter(|source| !is_upload(&source.url)) {
+            if let Err(err) = LocalIndex::index(&source.url, &source.content).await {
+                warn!("Failed to index {} locally: {}", source.url, err);
+            }
         }
         state.state.md_sources = Some(mds);
+        state.state.figures = Some(found_figures);
+        state.state.source_dates = Some(dates);
+        if state.state.url_questions.is_some() {
+            state.state.url_questions = Some(url_questions);
+        }
         Ok(state)
     }
 }
@@ -83,8 +142,12 @@ impl Job for ExtractContentJob {
 mod tests {
     use super::*;
 
+    use std::collections::HashMap;
+
+    use chrono::{Duration, Utc};
+
     use crate::{
-        models::FullReport,
+        models::{FullReport, ScrapeOutcome, ScrapeStatus},
         workflow::{JobType, WorkflowState},
     };
 
@@ -156,4 +219,70 @@ mod tests {
             state.state.md_sources.unwrap().first().unwrap().content
         )
     }
+
+    #[test]
+    fn test_canonical_url() {
+        let document = Html::parse_document(
+            r#"<html><head><link rel="canonical" href="/news/apple-earnings?utm_source=amp"></head></html>"#,
+        );
+        assert_eq!(
+            canonical_url(&document, "https://www.cnbc.com/amp/news/apple-earnings"),
+            Some("https://www.cnbc.com/news/apple-earnings".into())
+        );
+        let document = Html::parse_document(r#"<html><head><title>Apple</title></head></html>"#);
+        assert_eq!(canonical_url(&document, "https://www.cnbc.com/"), None);
+        let document = Html::parse_document(
+            r#"<html><head><link rel="canonical" href="javascript:void(0)"></head></html>"#,
+        );
+        assert_eq!(canonical_url(&document, "https://www.cnbc.com/"), None);
+    }
+
+    #[tokio::test]
+    async fn test_leaves_out_stale_sources() {
+        let source = |url: &str| PreClassificationSource {
+            url: url.into(),
+            content: "Apple reported record services revenue.".into(),
+        };
+        let old = "https://investor.apple.com/10-K-2019.pdf#page=1";
+        let recent = "https://investor.apple.com/10-K-2024.pdf#page=1";
+        let undated = "https://www.reuters.com/technology/apple-results";
+        let upload = "upload://abc123#page=1";
+        let mut report = FullReport::new("stale".into(), "Apple results".into())
+            .with_raw_sources(vec![
+                source(old),
+                source(recent),
+                source(undated),
+                source(upload),
+            ])
+            .with_html_sources(vec![]);
+        report.max_source_age = Some(365);
+        let today = Utc::now().date_naive();
+        report.source_dates = Some(HashMap::from([
+            (old.to_string(), today - Duration::days(5 * 365)),
+            (recent.to_string(), today - Duration::days(30)),
+            (upload.to_string(), today - Duration::days(5 * 365)),
+        ]));
+        let state = WorkflowState {
+            id: "stale".into(),
+            state: report,
+            last_job_type: JobType::ScrapeTopResults,
+        };
+        let state = ExtractContentJob.run(state).await.unwrap().state;
+
+        let urls: Vec<&str> = state
+            .md_sources
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|source| source.url.as_str())
+            .collect();
+        assert_eq!(urls, [recent, undated, upload]);
+        assert_eq!(
+            state.scrape_outcomes.unwrap(),
+            [ScrapeOutcome::new(
+                "https://investor.apple.com/10-K-2019.pdf",
+                ScrapeStatus::Stale
+            )]
+        );
+    }
 }
@@ -1,8 +1,9 @@
 use async_trait::async_trait;
-use log::error;
+use log::{debug, error};
 use markdown::mdast::Node;
 use markdown::ParseOptions;
 
+use crate::extractors::table::html_tables;
 use crate::prelude::*;
 
 use crate::workflow::WorkflowState;
@@ -42,6 +43,19 @@ fn find_cell_content(children: Vec<Node>) -> String {
 #[async_trait]
 impl Job for ExtractDataJob {
     async fn run(&self, mut state: WorkflowState) -> Result<WorkflowState> {
+        // Tables are lost once pages are converted to text, so read them from the HTML
+        let mut tables = vec![];
+        for source in state.state.html_sources.clone().unwrap_or_default() {
+            let found = html_tables(&source.content, &source.url);
+            debug!("Found {} tables in {}", found.len(), source.url);
+            tables.extend(found);
+        }
+        state
+            .state
+            .data_sources
+            .get_or_insert_with(Vec::new)
+            .extend(tables);
+
         let mut csvs = vec![];
         for source in state.state.md_sources.clone().unwrap() {
             let ast = markdown::to_mdast(&source.content, &ParseOptions::default()).unwrap();
@@ -227,16 +227,19 @@ mod tests {
                                     name: "Quarter".into(),
                                     description: "Financial Quarters of 2025".into(),
                                     values: vec!["Q1".into(), "Q2".into(), "Q3".into(), "Q4".into()],
+                                    ..Default::default()
                                 },
                                 Column {
                                     name: "Revenue".into(),
                                     description: "Apple's Revenue (in billions USD)".into(),
                                     values: vec!["110".into(), "120".into(), "130".into(), "140".into()],
+                                    ..Default::default()
                                 },
                                 Column {
                                     name: "Profit".into(),
                                     description: "Apple's Profit (in billions USD)".into(),
                                     values: vec!["30".into(), "32".into(), "34".into(), "36".into()],
+                                    ..Default::default()
                                 },
                             ],
                         },
@@ -254,6 +257,7 @@ mod tests {
                                         "1.0".into(), "2.0".into(), "3.0".into(), "4.0".into(), "5.0".into(), "6.0".into(),
                                         "7.0".into(), "8.0".into(), "9.0".into(), "10.0".into(), "11.0".into(), "12.0".into(),
                                     ],
+                                    ..Default::default()
                                 },
                                 Column {
                                     name: "Stock Price".into(),
@@ -263,6 +267,7 @@ mod tests {
                                         "162".into(), "170".into(), "175".into(), "180".into(),
                                         "178".into(), "185".into(), "190".into(), "195".into(),
                                     ],
+                                    ..Default::default()
                                 },
                             ],
                         },
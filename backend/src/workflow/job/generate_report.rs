@@ -1,9 +1,11 @@
 use async_trait::async_trait;
+use chrono::Datelike;
 use itertools::izip;
 use log::debug;
 
-use crate::latex::{self, LatexComponent, Section, Source, Subsection};
+use crate::latex::{self, Figure, LatexComponent, Section, Source, Subsection};
 use crate::prelude::*;
+use crate::uploads::{is_upload, INTERNAL_DOCUMENT};
 
 use crate::workflow::WorkflowState;
 
@@ -22,7 +24,9 @@ impl Job for GenerateReportJob {
             "Generating report for: {}",
             state.state.title.clone().unwrap()
         );
-        for (section_name, sub_sections, sub_section_contents) in izip!(
+        let figures = state.state.report_figures.clone().unwrap_or_default();
+        for (i, section_name, sub_sections, sub_section_contents) in izip!(
+            0..,
             state.state.sections.clone().unwrap().into_iter(),
             state.state.sub_sections.clone().unwrap().into_iter(),
             state
@@ -35,24 +39,51 @@ impl Job for GenerateReportJob {
             components.push(LatexComponent::Section(Section {
                 heading: section_name,
             }));
-            for (sub_section_name, sub_section_content) in
-                sub_sections.into_iter().zip(sub_section_contents)
+            for (j, (sub_section_name, sub_section_content)) in sub_sections
+                .into_iter()
+                .zip(sub_section_contents)
+                .enumerate()
             {
                 components.push(LatexComponent::Subsection(Subsection {
                     heading: sub_section_name,
                 }));
                 components.push(LatexComponent::Text(sub_section_content));
+                for figure in figures
+                    .iter()
+                    .filter(|figure| figure.section == i && figure.sub_section == j)
+                {
+                    components.push(LatexComponent::Figure(Figure {
+                        caption: figure.caption.clone(),
+                        path: figure.path.clone(),
+                        attribution: Some(figure.attribution.clone()),
+                        citation: figure.citation.clone(),
+                    }));
+                }
             }
         }
 
         let mut sources = vec![];
         for source in state.state.sources.clone().unwrap().into_iter() {
+            // Uploaded documents have no public URL to link to
+            if is_upload(&source.url) {
+                sources.push(Source::new(
+                    "Misc".into(),
+                    source.id,
+                    // Braced so BibTeX keeps it as a single name
+                    format!("{{{}}}", source.author),
+                    source.title,
+                    None,
+                    INTERNAL_DOCUMENT.into(),
+                    String::new(),
+                ));
+                continue;
+            }
             sources.push(Source::new(
                 "Website".into(),
                 source.id,
                 source.author,
                 source.title,
-                2025,
+                source.date.map(|date| date.year()),
                 "Journal".into(),
                 source.url,
             ));
@@ -65,6 +96,7 @@ impl Job for GenerateReportJob {
             commands,
             state.state.title.clone().unwrap(),
             " ".into(),
+            state.state.language.babel().into(),
         )?;
 
         debug!("Report can be found at: {}", &report.report_path);
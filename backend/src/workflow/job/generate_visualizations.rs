@@ -137,6 +137,7 @@ mod tests {
                                     "2024".to_string(),
                                     "2025".to_string(),
                                 ],
+                                ..Default::default()
                             },
                             Column {
                                 name: "Stock Price".to_string(),
@@ -150,6 +151,7 @@ mod tests {
                                     "$175".to_string(),
                                     "$190".to_string(),
                                 ],
+                                ..Default::default()
                             },
                         ],
                     },
@@ -170,6 +172,7 @@ mod tests {
                                     "2024".to_string(),
                                     "2025".to_string(),
                                 ],
+                                ..Default::default()
                             },
                             Column {
                                 name: "Revenue (Billion $)".to_string(),
@@ -182,6 +185,7 @@ mod tests {
                                     "350".to_string(),
                                     "380".to_string(),
                                 ],
+                                ..Default::default()
                             },
                         ],
                     },
@@ -0,0 +1,419 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    io::{Cursor, Write},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageFormat};
+use log::{debug, warn};
+use reqwest::Url;
+use tokio::task::JoinSet;
+
+use crate::{
+    blobs,
+    extractors::Figure,
+    prelude::*,
+    scraping::{fetch::Fetcher, politeness::POLITENESS},
+    search::normalize_url,
+    workflow::WorkflowState,
+};
+
+use super::Job;
+
+pub mod models {
+    use serde::{Deserialize, Serialize};
+
+    /// A figure taken from a source, placed at the end of a subsection.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ReportFigure {
+        pub section: usize,
+        pub sub_section: usize,
+        /// Path of the persisted image
+        pub path: String,
+        pub caption: String,
+        /// URL of the page the figure was published on
+        pub source: String,
+        /// Site the figure was published on
+        pub attribution: String,
+        /// Key of the page in the bibliography, when it was cited
+        pub citation: Option<String>,
+    }
+}
+
+use models::ReportFigure;
+
+/// Smaller files are icons and spacers.
+const MIN_FIGURE_BYTES: usize = 2 * 1024;
+const MAX_FIGURE_BYTES: usize = 5 * 1024 * 1024;
+const MIN_WIDTH: u32 = 300;
+const MIN_HEIGHT: u32 = 200;
+/// Wider or taller images are banners and dividers.
+const MAX_ASPECT_RATIO: f64 = 4.0;
+/// Hashes this many bits apart or closer are the same picture.
+const DUPLICATE_DISTANCE: u32 = 6;
+/// Shortest word of a caption used to match it to a subsection.
+const MIN_WORD_LEN: usize = 4;
+
+pub struct IncludeFiguresJob;
+
+/// Difference hash of an image: a bit for every pixel of a 9x8 grayscale thumbnail that is
+/// brighter than its right neighbour, so resized and recompressed copies hash the same.
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let thumbnail = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if thumbnail.get_pixel(x, y)[0] > thumbnail.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// A downloaded image LaTeX can include.
+struct Image {
+    bytes: Vec<u8>,
+    extension: &'static str,
+    hash: u64,
+}
+
+/// Keeps PNG, JPEG and WebP images large enough to be charts or photos rather than icons,
+/// logos or banners. WebP is converted to PNG, which LaTeX can include.
+fn accept_image(bytes: Vec<u8>) -> Option<Image> {
+    if !(MIN_FIGURE_BYTES..=MAX_FIGURE_BYTES).contains(&bytes.len()) {
+        return None;
+    }
+    let format = image::guess_format(&bytes).ok()?;
+    if !matches!(
+        format,
+        ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP
+    ) {
+        return None;
+    }
+    let image = image::load_from_memory_with_format(&bytes, format).ok()?;
+    let (width, height) = image.dimensions();
+    let ratio = width as f64 / height as f64;
+    if width < MIN_WIDTH
+        || height < MIN_HEIGHT
+        || !(1.0 / MAX_ASPECT_RATIO..=MAX_ASPECT_RATIO).contains(&ratio)
+    {
+        return None;
+    }
+    let hash = dhash(&image);
+    let (bytes, extension) = match format {
+        ImageFormat::Jpeg => (bytes, "jpg"),
+        ImageFormat::Png => (bytes, "png"),
+        _ => {
+            let mut png = Cursor::new(vec![]);
+            image.write_to(&mut png, ImageFormat::Png).ok()?;
+            (png.into_inner(), "png")
+        }
+    };
+    Some(Image {
+        bytes,
+        extension,
+        hash,
+    })
+}
+
+/// The figcaption of a figure, or its alt text. Figures without either can't be described.
+fn caption(figure: &Figure) -> Option<String> {
+    let caption = [&figure.caption, &figure.alt_text]
+        .into_iter()
+        .flatten()
+        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|text| !text.is_empty())?;
+    if caption.ends_with(['.', '!', '?']) {
+        Some(caption)
+    } else {
+        Some(format!("{}.", caption))
+    }
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_WORD_LEN)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Finds the subsection a figure belongs to: the one asking a question its page was found
+/// for, or else the one sharing the most words with its caption.
+fn placement(
+    figure: &Figure,
+    caption: &str,
+    url_questions: &HashMap<String, Vec<String>>,
+    sub_sections: &[Vec<String>],
+    sub_section_questions: &[Vec<Vec<String>>],
+) -> Option<(usize, usize)> {
+    let found_for = figure
+        .source
+        .as_deref()
+        .and_then(|source| url_questions.get(&normalize_url(source)));
+    if let Some(found_for) = found_for {
+        for (i, questions) in sub_section_questions.iter().enumerate() {
+            for (j, questions) in questions.iter().enumerate() {
+                if questions
+                    .iter()
+                    .any(|question| found_for.contains(question))
+                {
+                    return Some((i, j));
+                }
+            }
+        }
+    }
+    let caption = words(caption);
+    let mut best = None;
+    let mut best_overlap = 1;
+    for (i, names) in sub_sections.iter().enumerate() {
+        for (j, name) in names.iter().enumerate() {
+            let mut text = name.clone();
+            if let Some(questions) = sub_section_questions.get(i).and_then(|q| q.get(j)) {
+                text.push(' ');
+                text.push_str(&questions.join(" "));
+            }
+            let overlap = words(&text).intersection(&caption).count();
+            if overlap > best_overlap {
+                best_overlap = overlap;
+                best = Some((i, j));
+            }
+        }
+    }
+    best
+}
+
+async fn download(fetcher: &Fetcher, url: &str) -> Option<Image> {
+    if !POLITENESS.allowed(url).await {
+        debug!("robots.txt disallows figure {}", url);
+        return None;
+    }
+    let body = match fetcher.fetch(url).await {
+        Ok(document) => document.body,
+        Err(err) => {
+            debug!("Failed to download figure {}: {}", url, err);
+            return None;
+        }
+    };
+    tokio::task::spawn_blocking(move || accept_image(body))
+        .await
+        .ok()
+        .flatten()
+}
+
+async fn persist(image: &Image) -> Result<String> {
+    let mut file = tempfile::Builder::new()
+        .suffix(&format!(".{}", image.extension))
+        .tempfile()?;
+    file.write_all(&image.bytes)?;
+    let blob = blobs::persist(file.path().to_path_buf()).await?;
+    Ok(blob.path.to_string_lossy().into_owned())
+}
+
+#[async_trait]
+impl Job for IncludeFiguresJob {
+    /// Downloads the figures of the sources and places one in each subsection it fits, at
+    /// most `MAX_REPORT_FIGURES` (6 by default) in the whole report.
+    async fn run(&self, mut state: WorkflowState) -> Result<WorkflowState> {
+        debug!("Running IncludeFiguresJob...");
+        let max_figures = env::var("MAX_REPORT_FIGURES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(6);
+        let url_questions = state.state.url_questions.clone().unwrap_or_default();
+        let sub_sections = state.state.sub_sections.clone().unwrap_or_default();
+        let sub_section_questions = state
+            .state
+            .sub_section_questions
+            .clone()
+            .unwrap_or_default();
+        let sources = state.state.sources.clone().unwrap_or_default();
+
+        let mut seen = HashSet::new();
+        let mut candidates = vec![];
+        for figure in state.state.figures.clone().unwrap_or_default() {
+            if !seen.insert(normalize_url(&figure.url)) {
+                continue;
+            }
+            let Some(caption) = caption(&figure) else {
+                continue;
+            };
+            let Some(place) = placement(
+                &figure,
+                &caption,
+                &url_questions,
+                &sub_sections,
+                &sub_section_questions,
+            ) else {
+                continue;
+            };
+            candidates.push((figure, caption, place));
+        }
+        // Some will be filtered out or turn out to be duplicates
+        candidates.truncate(max_figures * 3);
+        debug!("Downloading {} candidate figures", candidates.len());
+
+        let fetcher = Arc::new(Fetcher::new());
+        let mut join_set = JoinSet::new();
+        for (index, (figure, _, _)) in candidates.iter().enumerate() {
+            let fetcher = fetcher.clone();
+            let url = figure.url.clone();
+            join_set.spawn(async move { (index, download(&fetcher, &url).await) });
+        }
+        let mut images = join_set.join_all().await;
+        images.sort_by_key(|(index, _)| *index);
+
+        let mut hashes: Vec<u64> = vec![];
+        let mut filled = HashSet::new();
+        let mut report_figures = vec![];
+        for (index, image) in images {
+            let Some(image) = image else {
+                continue;
+            };
+            let (figure, caption, place) = &candidates[index];
+            if report_figures.len() >= max_figures || filled.contains(place) {
+                continue;
+            }
+            if hashes
+                .iter()
+                .any(|hash| (hash ^ image.hash).count_ones() <= DUPLICATE_DISTANCE)
+            {
+                debug!("Skipping duplicate figure {}", figure.url);
+                continue;
+            }
+            let path = match persist(&image).await {
+                Ok(path) => path,
+                Err(err) => {
+                    warn!("Failed to store figure {}: {}", figure.url, err);
+                    continue;
+                }
+            };
+            hashes.push(image.hash);
+            filled.insert(*place);
+            let source = figure.source.clone().unwrap_or_else(|| figure.url.clone());
+            let attribution = Url::parse(&source)
+                .ok()
+                .and_then(|url| {
+                    url.host_str()
+                        .map(|host| host.trim_start_matches("www.").into())
+                })
+                .unwrap_or_else(|| source.clone());
+            let citation = sources
+                .iter()
+                .find(|cited| normalize_url(&cited.url) == normalize_url(&source))
+                .map(|cited| cited.id.clone());
+            report_figures.push(ReportFigure {
+                section: place.0,
+                sub_section: place.1,
+                path,
+                caption: caption.clone(),
+                source,
+                attribution,
+                citation,
+            });
+        }
+        debug!("Including {} figures", report_figures.len());
+        state.state.report_figures = Some(report_figures);
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb, RgbImage};
+
+    fn chart(width: u32, height: u32, bars: u32) -> RgbImage {
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let bar = x * bars / width;
+            if height - y < (bar + 1) * height / (bars + 1) {
+                Rgb([30, 90, 200])
+            } else {
+                Rgb([255, 255, 255])
+            }
+        })
+    }
+
+    fn png(image: &RgbImage) -> Vec<u8> {
+        let mut bytes = Cursor::new(vec![]);
+        image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_dhash_duplicates() {
+        let original = DynamicImage::ImageRgb8(chart(800, 500, 5));
+        let resized = original.resize_exact(400, 250, FilterType::Triangle);
+        let other = DynamicImage::ImageRgb8(chart(800, 500, 5)).fliph();
+        assert!((dhash(&original) ^ dhash(&resized)).count_ones() <= DUPLICATE_DISTANCE);
+        assert!((dhash(&original) ^ dhash(&other)).count_ones() > DUPLICATE_DISTANCE);
+    }
+
+    #[test]
+    fn test_accept_image() {
+        let image = accept_image(png(&chart(800, 500, 5))).unwrap();
+        assert_eq!(image.extension, "png");
+        // Icons, banners and anything that isn't an image
+        assert!(accept_image(png(&chart(64, 64, 2))).is_none());
+        assert!(accept_image(png(&chart(2000, 300, 20))).is_none());
+        assert!(accept_image(b"<svg></svg>".repeat(1000)).is_none());
+    }
+
+    #[test]
+    fn test_placement() {
+        let figure = |source: &str| Figure {
+            url: "https://a.com/chart.png".into(),
+            alt_text: None,
+            caption: None,
+            source: Some(source.into()),
+        };
+        let sub_sections = vec![
+            vec!["Background".into()],
+            vec!["Revenue".into(), "Services growth".into()],
+        ];
+        let questions = vec![
+            vec![vec!["Who founded Apple?".into()]],
+            vec![
+                vec!["What was Apple's iPhone revenue in 2024?".into()],
+                vec!["How fast are services growing?".into()],
+            ],
+        ];
+        let url_questions = HashMap::from([(
+            normalize_url("https://www.cnbc.com/apple"),
+            vec!["How fast are services growing?".to_string()],
+        )]);
+        assert_eq!(
+            placement(
+                &figure("https://www.cnbc.com/apple"),
+                "Chart.",
+                &url_questions,
+                &sub_sections,
+                &questions
+            ),
+            Some((1, 1))
+        );
+        assert_eq!(
+            placement(
+                &figure("https://other.com/"),
+                "iPhone revenue by quarter in 2024.",
+                &url_questions,
+                &sub_sections,
+                &questions
+            ),
+            Some((1, 0))
+        );
+        assert_eq!(
+            placement(
+                &figure("https://other.com/"),
+                "Tim Cook on stage.",
+                &url_questions,
+                &sub_sections,
+                &questions
+            ),
+            None
+        );
+    }
+}
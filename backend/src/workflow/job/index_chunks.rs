@@ -58,6 +58,7 @@ mod tests {
     use chrono::Utc;
 
     use super::*;
+    use crate::search::reputation::TrustTier;
 
     use crate::{
         models::FullReport,
@@ -154,8 +155,10 @@ Apple said it expected growth in the March quarter of "low to mid single digits"
                         url: "https://www.nbcboston.com/news/business/money-report/apple-reports-first-quarter-earnings-after-the-bell-2/3617779/?os=android&ref=app&noamp=mobile".into(),
                         title: "Apple shares rise 3% as boost in services revenue overshadows iPhone miss".into(),
                         author: "Kif Leswing, CNBC".into(),
-                        published_after: Some(Utc::now().format("%Y-%m-%d").to_string()),
-                        date: Some(Utc::now().format("%Y-%m-%d").to_string()),
+                        published_after: Some(Utc::now().date_naive()),
+                        date: Some(Utc::now().date_naive()),
+                        trust_tier: TrustTier::Unknown,
+                        trust_score: TrustTier::Unknown.score(),
                     }
                 ])
                 .with_chunks(vec![
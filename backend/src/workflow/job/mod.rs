@@ -8,6 +8,7 @@ pub mod chunk_content;
 pub mod classify_data;
 pub mod classify_sources;
 pub mod content_formatter;
+pub mod dedupe_sources;
 pub mod extract_content;
 pub mod extract_data;
 // pub mod generate_graphs; // png
@@ -15,6 +16,7 @@ pub mod generate_preview;
 pub mod generate_report;
 // pub mod generate_visualizations; // what graphs from available data
 // pub mod graph_identifier; // where to put the graphs
+pub mod include_figures;
 pub mod index_chunks;
 pub mod scrape_pages;
 // pub mod search_before_questions;
@@ -46,9 +48,9 @@ impl JobType {
             JobType::GenerateSearchQueries => Some(JobType::SearchQueries),
             JobType::SearchQueries => Some(JobType::ScrapeTopResults),
             JobType::ScrapeTopResults => Some(JobType::ExtractContent),
-            JobType::ExtractContent => Some(JobType::FormatContent),
-            // JobType::ExtractContent => Some(JobType::ExtractData),
-            // JobType::ExtractData => Some(JobType::FormatContent),
+            JobType::ExtractContent => Some(JobType::DedupeSources),
+            JobType::DedupeSources => Some(JobType::ExtractData),
+            JobType::ExtractData => Some(JobType::FormatContent),
             JobType::FormatContent => Some(JobType::ClassifyContent),
             JobType::ClassifyContent => Some(JobType::ChunkContent),
             // JobType::ClassifyContent => Some(JobType::ClassifyData),
@@ -58,7 +60,8 @@ impl JobType {
             JobType::ChunkContent => Some(JobType::IndexChunks),
             JobType::IndexChunks => Some(JobType::AnswerQuestions),
             JobType::AnswerQuestions => Some(JobType::SectionizeQuestions),
-            JobType::SectionizeQuestions => Some(JobType::RenderLaTeXPdf),
+            JobType::SectionizeQuestions => Some(JobType::IncludeFigures),
+            JobType::IncludeFigures => Some(JobType::RenderLaTeXPdf),
             // JobType::SectionizeQuestions => Some(JobType::RenderGraphs),
             // JobType::RenderGraphs => Some(JobType::RenderLaTeXPdf),
             JobType::RenderLaTeXPdf => Some(JobType::GeneratePreviewDocument),
@@ -86,7 +89,8 @@ impl JobType {
             JobType::SearchQueries => Some(Box::new(search_terms::SearchJob)),
             JobType::ScrapeTopResults => Some(Box::new(scrape_pages::ScrapePagesJob)),
             JobType::ExtractContent => Some(Box::new(extract_content::ExtractContentJob)),
-            // JobType::ExtractData => Some(Box::new(extract_data::ExtractDataJob)),
+            JobType::DedupeSources => Some(Box::new(dedupe_sources::DedupeSourcesJob)),
+            JobType::ExtractData => Some(Box::new(extract_data::ExtractDataJob)),
             JobType::FormatContent => Some(Box::new(content_formatter::FormatContentJob)),
             JobType::ClassifyContent => Some(Box::new(classify_sources::ClassifySourcesJob)),
             // JobType::ClassifyData => Some(Box::new(classify_data::ClassifyDataJob)),
@@ -100,6 +104,7 @@ impl JobType {
             JobType::SectionizeQuestions => {
                 Some(Box::new(sectionize_questions::SectionizeQuestionsJob))
             }
+            JobType::IncludeFigures => Some(Box::new(include_figures::IncludeFiguresJob)),
             // JobType::RenderGraphs => Some(Box::new(graph_identifier::GraphIdentifierJob)),
             JobType::RenderLaTeXPdf => Some(Box::new(generate_report::GenerateReportJob)),
             JobType::GeneratePreviewDocument => {
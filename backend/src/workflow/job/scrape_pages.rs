@@ -1,120 +1,459 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    sync::Arc,
+};
 
-use crate::{models::PreClassificationSource, prelude::*, workflow::WorkflowState};
+use crate::{
+    extractors::{date::url_date, registry::EXTRACTORS, Data, Document, RawDocument, TextBlock},
+    models::{FullReport, PreClassificationSource, ScrapeOutcome, ScrapeStatus},
+    prelude::*,
+    scraping::{
+        browser::BROWSERS,
+        fetch::{self, document_links, needs_browser, DocumentKind, Fetcher},
+        politeness::POLITENESS,
+    },
+    search::{normalize_url, reputation::REPUTATION, SearchOptions, SearchResult, SEARCH},
+    uploads,
+    workflow::{job::validation::models::ValidationOutput, JobType, WorkflowState},
+};
 
-use super::Job;
+use super::{search_terms::merge_results, Job};
 
 use async_trait::async_trait;
-use deadpool::unmanaged::{Object, Pool};
-use fantoccini::{Client, ClientBuilder};
-use log::debug;
-use serde_json::json;
-use tokio::{sync::Mutex, task::JoinSet, time::timeout};
+use chrono::NaiveDate;
+use log::{debug, warn};
+use tokio::{sync::Semaphore, task::JoinSet};
 
 pub mod models {}
 
 pub struct ScrapePagesJob;
 
-const BROWSER_COUNT: u16 = 4;
-const FIRST_PORT: u16 = 4444;
-
-pub async fn make_browsers(amount: u16) -> Result<Pool<Client>> {
-    debug!("Initializing {} browsers...", amount);
-    let mut browsers = vec![];
-    for i in 0..amount {
-        debug!("Initializing browser {}...", i + 1);
-        let default = format!("http://localhost:{}", FIRST_PORT + i);
-        // if let Ok(address) = env::var(format!("GECKODRIVER{}_URL", i)) {
-        // debug!("Using custom address for browser {}: {}", i + 1, address);
-        // default = address;
-        // }
-        browsers.push(
-            ClientBuilder::native()
-                .capabilities(
-                    json!({
-                        "moz:firefoxOptions": {
-                            "args": ["--headless"]
-                        }
-                    })
-                    .as_object()
-                    .unwrap()
-                    .clone(),
-                )
-                .connect(&default)
-                .await?,
-        );
-        debug!("Initialized browser {}", i + 1);
+pub fn split_evenly(items: Vec<String>, n: usize) -> Vec<Vec<String>> {
+    let mut chunks = vec![Vec::new(); n];
+    for (index, item) in items.into_iter().enumerate() {
+        chunks[index % n].push(item);
     }
-    Ok(Pool::from(browsers))
+    chunks
 }
 
-pub async fn scrape_page(browser: &Object<Client>, url: &str) -> Result<String> {
-    // browser.goto(url).await?;
-    let goto_result = timeout(Duration::from_secs(2), browser.goto(url)).await;
-    match goto_result {
-        Ok(Ok(_)) => {
-            let source = browser.source().await?;
-            Ok(source)
+/// Where a URL ended up after the plain HTTP fetch
+enum Fetched {
+    Html(PreClassificationSource),
+    /// A document other than a web page, such as a PDF or a spreadsheet
+    Document {
+        url: String,
+        sources: Vec<PreClassificationSource>,
+        tables: Vec<Data>,
+        /// When the document says it was published, or else its URL does
+        date: Option<NaiveDate>,
+    },
+    /// With the status to record if the browser fails too
+    NeedsBrowser(String, ScrapeStatus),
+    Skipped(ScrapeOutcome),
+}
+
+/// Links to a page of a PDF, which browsers open at that page.
+fn page_url(url: &str, page: u32) -> String {
+    format!("{}#page={}", url, page)
+}
+
+/// Groups the pages of a document into sources of about `PDF_SOURCE_CHARS` characters, so
+/// every source can be cited by the page it starts on.
+fn page_sources(url: &str, pages: Vec<TextBlock>) -> Vec<PreClassificationSource> {
+    let limit = env::var("PDF_SOURCE_CHARS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(12000);
+    let mut sources: Vec<PreClassificationSource> = vec![];
+    let mut length = 0;
+    for block in pages {
+        let page = block.page.unwrap_or(1);
+        let text = format!("[Page {}]\n{}\n", page, block.text);
+        match sources.last_mut() {
+            Some(source) if length + text.len() <= limit => {
+                source.content.push_str(&text);
+                length += text.len();
+            }
+            _ => {
+                length = text.len();
+                sources.push(PreClassificationSource {
+                    url: page_url(url, page),
+                    content: text,
+                });
+            }
         }
-        Ok(Err(e)) => Err(e.into()), // Handle `goto` errors
-        Err(_) => Err(FinanalizeError::ScraperTimemout(url.into())),
     }
-    // let source = browser.source().await?;
-    // Ok(source)
+    sources
 }
 
-pub fn split_evenly(items: Vec<String>, n: usize) -> Vec<Vec<String>> {
-    let mut chunks = vec![Vec::new(); n];
-    for (index, item) in items.into_iter().enumerate() {
-        chunks[index % n].push(item);
+/// Splits an extracted document into sources, by page when it has pages so every source can
+/// be cited by the page it starts on.
+fn document_sources(url: String, document: Document) -> Fetched {
+    let paged = document.blocks.iter().any(|block| block.page.is_some());
+    let text = document.text();
+    let sources = if paged {
+        page_sources(&url, document.blocks)
+    } else if text.trim().is_empty() {
+        vec![]
+    } else {
+        vec![PreClassificationSource {
+            url: url.clone(),
+            content: text,
+        }]
+    };
+    let mut tables = document.tables;
+    for table in tables.iter_mut() {
+        table.source = Some(match table.page {
+            Some(page) => page_url(&url, page),
+            None => url.clone(),
+        });
+    }
+    let date = document.metadata.date.or_else(|| url_date(&url));
+    Fetched::Document {
+        url,
+        sources,
+        tables,
+        date,
+    }
+}
+
+/// Sends the document to the extractor for its kind, static HTML is extracted later on.
+async fn route(url: String, document: fetch::Fetched) -> Result<Fetched> {
+    match document.kind {
+        DocumentKind::Html => {
+            let html = String::from_utf8_lossy(&document.body).into_owned();
+            if needs_browser(&html) {
+                return Ok(Fetched::NeedsBrowser(url, ScrapeStatus::BrowserFailed));
+            }
+            Ok(Fetched::Html(PreClassificationSource {
+                url,
+                content: html,
+            }))
+        }
+        DocumentKind::Other => {
+            debug!("Skipping unsupported document: {}", url);
+            Ok(Fetched::Skipped(ScrapeOutcome::new(
+                url,
+                ScrapeStatus::Unsupported,
+            )))
+        }
+        // The sniffed kind beats the `Content-Type` the server sent
+        kind => {
+            let file = RawDocument::new(url.clone(), kind.mime(), document.body);
+            let extracted = EXTRACTORS.extract(&file).await?;
+            Ok(document_sources(url, extracted))
+        }
+    }
+}
+
+/// Extracts an uploaded document the way a downloaded one is, into sources and tables.
+pub async fn extract_document(
+    document: fetch::Fetched,
+) -> Result<(Vec<PreClassificationSource>, Vec<Data>)> {
+    match route(document.url.clone(), document).await? {
+        Fetched::Document {
+            sources, tables, ..
+        } => Ok((sources, tables)),
+        // Web pages are scraped, not uploaded
+        Fetched::Html(PreClassificationSource { url, .. })
+        | Fetched::NeedsBrowser(url, _)
+        | Fetched::Skipped(ScrapeOutcome { url, .. }) => {
+            Err(FinanalizeError::UnsupportedDocument(url))
+        }
+    }
+}
+
+/// Decides whether a browser could still get a page the plain fetch failed on.
+fn fetch_failed(url: String, err: &FinanalizeError) -> Fetched {
+    let FinanalizeError::Reqwest(err) = err else {
+        return Fetched::NeedsBrowser(url, ScrapeStatus::BrowserFailed);
+    };
+    match err.status().map(|status| status.as_u16()) {
+        // Bot protection often refuses clients that are not browsers
+        Some(401 | 403 | 429) => Fetched::NeedsBrowser(url, ScrapeStatus::Blocked),
+        Some(status) => Fetched::Skipped(ScrapeOutcome::new(url, ScrapeStatus::HttpError(status))),
+        None if err.is_timeout() => Fetched::NeedsBrowser(url, ScrapeStatus::Timeout),
+        None => Fetched::NeedsBrowser(url, ScrapeStatus::BrowserFailed),
+    }
+}
+
+async fn fetch_page(fetcher: &Fetcher, url: String) -> Fetched {
+    if !POLITENESS.allowed(&url).await {
+        debug!("robots.txt disallows {}", url);
+        return Fetched::Skipped(ScrapeOutcome::new(url, ScrapeStatus::DisallowedByRobots));
+    }
+    let document = match fetcher.fetch(&url).await {
+        Ok(document) => document,
+        Err(err) => {
+            debug!("Plain fetch failed for {}: {}", url, err);
+            return fetch_failed(url, &err);
+        }
+    };
+    match route(url.clone(), document).await {
+        Ok(fetched) => fetched,
+        Err(err) => {
+            warn!("Failed to extract {}: {}", url, err);
+            Fetched::Skipped(ScrapeOutcome::new(url, ScrapeStatus::ExtractionFailed))
+        }
+    }
+}
+
+async fn fetch_all(fetcher: Arc<Fetcher>, urls: Vec<String>) -> Vec<Fetched> {
+    let concurrency = env::var("FETCH_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(8);
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let mut join_set = JoinSet::new();
+    for url in urls {
+        let fetcher = fetcher.clone();
+        let permits = permits.clone();
+        join_set.spawn(async move {
+            let _permit = permits.acquire().await;
+            fetch_page(&fetcher, url).await
+        });
+    }
+    join_set.join_all().await
+}
+
+async fn scrape_with_browsers(urls: Vec<(String, ScrapeStatus)>) -> Vec<Fetched> {
+    let mut join_set = JoinSet::new();
+    let total = urls.len();
+    for (i, (url, fallback)) in urls.into_iter().enumerate() {
+        join_set.spawn(async move {
+            debug!("Scraping ({}/{}): {}", i + 1, total, url);
+            match BROWSERS.scrape(&url).await {
+                Ok(content) => Fetched::Html(PreClassificationSource { url, content }),
+                Err(FinanalizeError::ScraperTimemout(_)) => {
+                    debug!("Timed out scraping page {}", url);
+                    Fetched::Skipped(ScrapeOutcome::new(url, ScrapeStatus::Timeout))
+                }
+                Err(e) => {
+                    debug!("Failed to scrape page {}: {}", url, e);
+                    Fetched::Skipped(ScrapeOutcome::new(url, fallback))
+                }
+            }
+        });
+    }
+    join_set.join_all().await
+}
+
+/// Everything scraped so far, with an outcome for every URL that was tried.
+#[derive(Default)]
+struct Scraped {
+    html_sources: Vec<PreClassificationSource>,
+    md_sources: Vec<PreClassificationSource>,
+    data_sources: Vec<Data>,
+    /// Publication dates of the documents, by source URL
+    dates: HashMap<String, NaiveDate>,
+    outcomes: Vec<ScrapeOutcome>,
+}
+
+impl Scraped {
+    /// Returns the URL back when it still needs a browser.
+    fn add(&mut self, fetched: Fetched) -> Option<(String, ScrapeStatus)> {
+        let url = match fetched {
+            Fetched::Html(source) => {
+                let url = source.url.clone();
+                self.html_sources.push(source);
+                url
+            }
+            Fetched::Document {
+                url,
+                sources,
+                tables,
+                date,
+            } => {
+                if let Some(date) = date {
+                    for source in sources.iter() {
+                        self.dates.insert(source.url.clone(), date);
+                    }
+                }
+                self.md_sources.extend(sources);
+                self.data_sources.extend(tables);
+                url
+            }
+            Fetched::NeedsBrowser(url, fallback) => return Some((url, fallback)),
+            Fetched::Skipped(outcome) => {
+                self.outcomes.push(outcome);
+                return None;
+            }
+        };
+        self.outcomes
+            .push(ScrapeOutcome::new(url, ScrapeStatus::Success));
+        None
+    }
+
+    fn successes(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.status == ScrapeStatus::Success)
+            .count()
+    }
+}
+
+/// Scrapes the URLs and the documents their pages link to, falling back to a browser where needed.
+async fn scrape_urls(
+    report: &mut FullReport,
+    fetcher: Arc<Fetcher>,
+    urls: Vec<String>,
+    seen: &mut HashSet<String>,
+    scraped: &mut Scraped,
+) {
+    let pages_before = scraped.html_sources.len();
+    let mut browser_urls = vec![];
+    for fetched in fetch_all(fetcher.clone(), urls).await {
+        browser_urls.extend(scraped.add(fetched));
+    }
+
+    // Pages often link the annual report or data behind the numbers they quote
+    let limit = env::var("LINKED_DOCUMENT_LIMIT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5);
+    let mut linked = vec![];
+    for page in scraped.html_sources[pages_before..].iter() {
+        for link in document_links(&page.content, &page.url) {
+            if linked.len() >= limit {
+                break;
+            }
+            if REPUTATION.accepts(&link) && seen.insert(normalize_url(&link)) {
+                debug!("Following document link {} on {}", link, page.url);
+                linked.push((page.url.clone(), link));
+            }
+        }
+    }
+    if let Some(url_questions) = report.url_questions.as_mut() {
+        // A linked document answers the questions its page was found for
+        for (page, link) in linked.iter() {
+            if let Some(questions) = url_questions.get(&normalize_url(page)).cloned() {
+                url_questions.insert(normalize_url(link), questions);
+            }
+        }
+    }
+    let links = linked.into_iter().map(|(_, link)| link).collect();
+    for fetched in fetch_all(fetcher, links).await {
+        // Linked documents are not worth a browser session
+        if let Some((url, fallback)) = scraped.add(fetched) {
+            scraped.outcomes.push(ScrapeOutcome::new(url, fallback));
+        }
+    }
+
+    // Only pages that need JavaScript, or refuse plain clients, cost a browser session
+    if !browser_urls.is_empty() {
+        debug!("Scraping {} pages with a browser", browser_urls.len());
+        for fetched in scrape_with_browsers(browser_urls).await {
+            scraped.add(fetched);
+        }
     }
-    chunks
+}
+
+/// Searches again, further down the result pages, for URLs that were not tried yet.
+async fn extra_search_round(report: &mut FullReport, seen: &HashSet<String>) -> Vec<String> {
+    let mut options = SearchOptions::for_report(report);
+    options.count *= 2;
+    options.pages += 1;
+    let mut per_query = vec![];
+    for query in report.search_queries.clone().unwrap_or_default() {
+        match SEARCH.search(&query, &options).await {
+            Ok(results) => per_query.push((query, results)),
+            Err(e) => warn!("Extra search for {} failed: {}", query, e),
+        }
+    }
+    let (results, url_questions) = merge_results(
+        per_query,
+        &report.query_questions.clone().unwrap_or_default(),
+    );
+    let results: Vec<SearchResult> = results
+        .into_iter()
+        .filter(|r| !seen.contains(&normalize_url(&r.url)) && REPUTATION.accepts(&r.url))
+        .collect();
+    let known = report.url_questions.get_or_insert_with(HashMap::new);
+    for (url, questions) in url_questions {
+        known.entry(url).or_insert(questions);
+    }
+    let urls = results.iter().map(|r| r.url.clone()).collect();
+    report
+        .search_urls
+        .get_or_insert_with(Vec::new)
+        .extend(results);
+    urls
 }
 
 #[async_trait]
 impl Job for ScrapePagesJob {
     async fn run(&self, mut state: WorkflowState) -> Result<WorkflowState> {
         debug!("Running ScrapePagesJob...");
-        let browsers = Arc::new(make_browsers(BROWSER_COUNT).await?);
-        debug!("Initialized {} browsers", BROWSER_COUNT);
-        let sources: Arc<Mutex<Vec<PreClassificationSource>>> = Arc::new(Mutex::new(vec![]));
         let search_results = state.state.search_urls.clone().unwrap();
         debug!("Pages to scrape: {}", search_results.len());
-        let mut join_set = JoinSet::new();
-        let total = search_results.len();
-        for (i, source) in search_results.into_iter().enumerate() {
-            let browsers = browsers.clone();
-            let sources = sources.clone();
-            debug!("Spawning task for scraping URL {}: {}", i + 1, source);
-            join_set.spawn(async move {
-                let Ok(browser) = browsers.clone().get().await else {
-                    return Err(FinanalizeError::InternalServerError);
-                };
-                debug!("Scraping ({}/{}): {}", i + 1, total, source);
-                let Ok(html) = scrape_page(&browser, &source).await else {
-                    debug!("Failed to scrape page: {}", source);
-                    // return Err(FinanalizeError::InternalServerError);
-                    return Ok(());
-                };
-                debug!("Scraped ({}/{}): {}", i + 1, total, &source);
-                sources.clone().lock().await.push(PreClassificationSource {
-                    url: source,
-                    content: html,
-                });
-                Ok(())
-            });
-        }
+        let fetcher = Arc::new(Fetcher::new());
+        let urls: Vec<String> = search_results.into_iter().map(|r| r.url).collect();
+        let mut seen: HashSet<String> = urls.iter().map(|url| normalize_url(url)).collect();
+        let min_sources = env::var("MIN_SCRAPED_SOURCES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3);
 
-        let results: Result<Vec<()>> = join_set.join_all().await.into_iter().collect();
-        results?;
-        debug!("Scraped all pages, closing browser instances...");
+        let mut scraped = Scraped::default();
+        // Uploaded documents were extracted when they were uploaded
+        let documents = uploads::load(&state.state.documents.clone().unwrap_or_default()).await?;
+        for document in documents.iter() {
+            scraped.md_sources.extend(document.sources.iter().cloned());
+            scraped.data_sources.extend(document.tables.iter().cloned());
+        }
+        scrape_urls(
+            &mut state.state,
+            fetcher.clone(),
+            urls,
+            &mut seen,
+            &mut scraped,
+        )
+        .await;
+        if scraped.successes() + documents.len() < min_sources {
+            warn!(
+                "Only {} of the required {} sources were scraped, searching again",
+                scraped.successes() + documents.len(),
+                min_sources
+            );
+            let urls = extra_search_round(&mut state.state, &seen).await;
+            seen.extend(urls.iter().map(|url| normalize_url(url)));
+            scrape_urls(&mut state.state, fetcher, urls, &mut seen, &mut scraped).await;
+        }
+        debug!(
+            "Scraped {} pages, {} documents and {} data files, {} URLs failed",
+            scraped.html_sources.len(),
+            scraped.md_sources.len(),
+            scraped.data_sources.len(),
+            scraped.outcomes.len() - scraped.successes()
+        );
 
-        for _ in 0..BROWSER_COUNT {
-            let browser = browsers.remove().await?;
-            browser.close().await?;
+        let successes = scraped.successes() + documents.len();
+        state
+            .state
+            .scrape_outcomes
+            .get_or_insert_with(Vec::new)
+            .extend(scraped.outcomes);
+        if successes < min_sources {
+            warn!("Not enough sources for report {}", state.id);
+            state.state.status = JobType::Failed;
+            state.state.validation = Some(ValidationOutput {
+                valid: false,
+                error: Some(FinanalizeError::NotEnoughSources(successes).to_string()),
+            });
         }
-        debug!("Closed all browser instances");
-        state.state.html_sources = Some(sources.lock().await.clone());
+        state.state.html_sources = Some(scraped.html_sources);
+        state.state.md_sources = Some(scraped.md_sources);
+        state
+            .state
+            .source_dates
+            .get_or_insert_with(HashMap::new)
+            .extend(scraped.dates);
+        state
+            .state
+            .data_sources
+            .get_or_insert_with(Vec::new)
+            .extend(scraped.data_sources);
         debug!("ScrapePagesJob completed");
         Ok(state)
     }
@@ -123,6 +462,7 @@ impl Job for ScrapePagesJob {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::stub;
 
     use crate::{
         models::FullReport,
@@ -190,4 +530,161 @@ mod tests {
         let _state = job.run(state).await.unwrap();
         // dbg!(state.state.sources.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_fetch_page_routing() {
+        let fetcher = Fetcher::new();
+        let article = format!(
+            "<html><body><article>{}</article></body></html>",
+            "Apple reported record services revenue. ".repeat(20)
+        );
+        let (address, _) = stub::serve("text/html", article).await;
+        assert!(matches!(
+            fetch_page(&fetcher, address.clone()).await,
+            Fetched::Html(PreClassificationSource { url, .. }) if url == address
+        ));
+
+        let (address, _) = stub::serve(
+            "text/html",
+            b"<html><body><div id=\"root\"></div></body></html>",
+        )
+        .await;
+        assert!(matches!(
+            fetch_page(&fetcher, address.clone()).await,
+            Fetched::NeedsBrowser(url, ScrapeStatus::BrowserFailed) if url == address
+        ));
+
+        let (address, _) = stub::serve("image/png", b"\x89PNG").await;
+        assert!(matches!(
+            fetch_page(&fetcher, address).await,
+            Fetched::Skipped(ScrapeOutcome {
+                status: ScrapeStatus::Unsupported,
+                ..
+            })
+        ));
+
+        let (address, _) = stub::serve(
+            "application/pdf",
+            include_bytes!("../../../tests/sample.pdf"),
+        )
+        .await;
+        assert!(matches!(
+            fetch_page(&fetcher, address.clone()).await,
+            Fetched::Document { url, sources, .. }
+                if url == address && sources[0].url == format!("{}#page=1", address)
+        ));
+
+        // Broken documents are skipped rather than failing the job
+        let (address, _) = stub::serve("application/pdf", b"%PDF-broken").await;
+        assert!(matches!(
+            fetch_page(&fetcher, address).await,
+            Fetched::Skipped(ScrapeOutcome {
+                status: ScrapeStatus::ExtractionFailed,
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            fetch_page(&fetcher, "http://127.0.0.1:1/".into()).await,
+            Fetched::NeedsBrowser(_, ScrapeStatus::BrowserFailed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_extract_document() {
+        let document = |kind, body: &[u8]| fetch::Fetched {
+            url: "upload://f3a9".into(),
+            kind,
+            body: body.to_vec(),
+        };
+        let (sources, tables) = extract_document(document(
+            DocumentKind::Pdf,
+            include_bytes!("../../../tests/sample.pdf"),
+        ))
+        .await
+        .unwrap();
+        assert_eq!(sources[0].url, "upload://f3a9#page=1");
+        assert!(tables.iter().all(|table| table
+            .source
+            .as_deref()
+            .unwrap()
+            .starts_with("upload://f3a9#page=")));
+
+        let (sources, _) = extract_document(document(DocumentKind::Text, b"Margins held up."))
+            .await
+            .unwrap();
+        assert_eq!(sources[0].content, "Margins held up.");
+
+        assert!(matches!(
+            extract_document(document(DocumentKind::Html, b"<html></html>")).await,
+            Err(FinanalizeError::UnsupportedDocument(url)) if url == "upload://f3a9"
+        ));
+    }
+
+    #[test]
+    fn test_page_sources() {
+        let page = |page: u32, len: usize| TextBlock {
+            text: "a".repeat(len),
+            page: Some(page),
+        };
+        let sources = page_sources(
+            "https://investor.apple.com/10-K.pdf",
+            vec![page(1, 5000), page(2, 5000), page(3, 5000), page(4, 20000)],
+        );
+        let urls: Vec<_> = sources.iter().map(|s| s.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://investor.apple.com/10-K.pdf#page=1",
+                "https://investor.apple.com/10-K.pdf#page=3",
+                "https://investor.apple.com/10-K.pdf#page=4",
+            ]
+        );
+        assert!(sources[0].content.starts_with("[Page 1]\n"));
+        assert!(sources[0].content.contains("[Page 2]\n"));
+    }
+
+    #[test]
+    fn test_scraped_outcomes() {
+        let source = |url: &str| PreClassificationSource {
+            url: url.into(),
+            content: "content".into(),
+        };
+        let mut scraped = Scraped::default();
+        assert!(scraped
+            .add(Fetched::Html(source("https://a.com")))
+            .is_none());
+        assert!(scraped
+            .add(Fetched::Document {
+                url: "https://b.com/report.pdf".into(),
+                sources: vec![source("https://b.com/report.pdf#page=1")],
+                tables: vec![],
+                date: NaiveDate::from_ymd_opt(2024, 9, 27),
+            })
+            .is_none());
+        assert_eq!(
+            scraped.add(Fetched::NeedsBrowser(
+                "https://c.com".into(),
+                ScrapeStatus::Blocked
+            )),
+            Some(("https://c.com".into(), ScrapeStatus::Blocked))
+        );
+        scraped.add(Fetched::Skipped(ScrapeOutcome::new(
+            "https://d.com",
+            ScrapeStatus::HttpError(404),
+        )));
+        assert_eq!(scraped.successes(), 2);
+        assert_eq!(
+            scraped.dates.get("https://b.com/report.pdf#page=1"),
+            NaiveDate::from_ymd_opt(2024, 9, 27).as_ref()
+        );
+        assert_eq!(
+            scraped.outcomes,
+            vec![
+                ScrapeOutcome::new("https://a.com", ScrapeStatus::Success),
+                ScrapeOutcome::new("https://b.com/report.pdf", ScrapeStatus::Success),
+                ScrapeOutcome::new("https://d.com", ScrapeStatus::HttpError(404)),
+            ]
+        );
+    }
 }
@@ -2,11 +2,11 @@ use crate::llm::API;
 use crate::models::PreClassificationSource;
 use crate::prelude::*;
 use crate::prompting;
-use crate::search::SEARCH;
+use crate::search::{SearchOptions, SEARCH};
 use crate::tasks::Task;
 use crate::tasks::TaskResult;
 use crate::workflow::job::content_formatter::models::FormatContentJobInput;
-use crate::workflow::job::scrape_pages::scrape_page;
+use crate::scraping::browser::BROWSERS;
 use crate::workflow::job::search_before_questions::models::SingleSearchOutput;
 use crate::workflow::job::Job;
 use crate::workflow::WorkflowState;
@@ -63,12 +63,12 @@ async fn generate_initial_search_query(user_input: String) -> Result<Vec<String>
 
 async fn perform_search(search_query: String) -> Result<Vec<String>> {
     let mut search_futures = JoinSet::new();
-    search_futures.spawn(async move { SEARCH.clone().search(&search_query).await });
+    search_futures.spawn(async move { SEARCH.clone().search(&search_query, &SearchOptions::default()).await });
     let search_results = search_futures.join_all().await;
     let mut all_urls = Vec::new();
     for result in search_results.into_iter() {
         debug!("Adding search result: {:?}", result);
-        all_urls.extend(result?);
+        all_urls.extend(result?.into_iter().map(|r| r.url));
     }
     // Sort and deduplicate URLs
     debug!("Sorting and deduplicating URLs");
@@ -81,21 +81,16 @@ async fn perform_search(search_query: String) -> Result<Vec<String>> {
 async fn scrape_top_results(urls: Vec<String>) -> Result<Vec<PreClassificationSource>> {
     dbg!(&urls);
     let sources: Arc<Mutex<Vec<PreClassificationSource>>> = Arc::new(Mutex::new(vec![]));
-    let browsers = Arc::new(crate::workflow::job::scrape_pages::make_browsers(1).await?);
     let total_to_search = 3;
     let sources_to_search = urls.get(..3).unwrap().to_vec();
     debug!("Pages to scrape: {}", sources_to_search.len());
     let mut join_set = JoinSet::new();
     for (i, source) in sources_to_search.into_iter().enumerate() {
-        let browser = browsers.clone();
         let sources = sources.clone();
         debug!("Spawning task for scraping URL {}: {}", i + 1, source);
         join_set.spawn(async move {
-            let Ok(browser) = browser.clone().get().await else {
-                return Err(FinanalizeError::InternalServerError);
-            };
             debug!("Scraping ({}/{}): {}", i + 1, total_to_search, source);
-            let Ok(html) = scrape_page(&browser, &source).await else {
+            let Ok(html) = BROWSERS.scrape(&source).await else {
                 debug!("Failed to scrape page: {}", source);
                 // return Err(FinanalizeError::InternalServerError);
                 return Ok(());
@@ -111,9 +106,7 @@ async fn scrape_top_results(urls: Vec<String>) -> Result<Vec<PreClassificationSo
 
     let results: Result<Vec<()>> = join_set.join_all().await.into_iter().collect();
     results?;
-    debug!("Scraped all pages, closing browser instance...");
-    let browser = browsers.remove().await?;
-    browser.close().await?;
+    debug!("Scraped all pages");
     let scraped_html_sources = sources.lock().await.clone();
     Ok(scraped_html_sources)
 }
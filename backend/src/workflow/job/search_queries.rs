@@ -1,3 +1,5 @@
+use std::{collections::HashMap, env};
+
 use async_trait::async_trait;
 use chrono::Utc;
 use itertools::izip;
@@ -6,6 +8,7 @@ use models::{RawSearchQueriesInput, SearchQueriesInput, SearchQueriesOutput};
 use schemars::schema_for;
 
 use crate::llm::API;
+use crate::rag::cosine_similarity;
 use crate::tasks::{Task, TaskResult};
 use crate::workflow::job::sub_section_questions::models::{
     SectionWithQuestions, SubSectionWithQuestions,
@@ -24,6 +27,7 @@ pub mod models {
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct RawSearchQueriesInput {
+        pub language: String,
         pub input: String,
     }
 
@@ -42,12 +46,33 @@ pub mod models {
 
     #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
     pub struct SearchQueriesOutput {
-        pub queries: Vec<String>,
+        pub queries: Vec<GeneratedSearchQuery>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+    pub struct GeneratedSearchQuery {
+        pub query: String,
+        pub questions: Vec<String>,
     }
 }
 
 pub struct GenerateSearchQueriesJob;
 
+/// Groups the indices of embeddings that are at least `threshold` similar to the first of the group.
+pub fn group_similar(embeddings: &[Vec<f32>], threshold: f32) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, embedding) in embeddings.iter().enumerate() {
+        match groups
+            .iter_mut()
+            .find(|group| cosine_similarity(&embeddings[group[0]], embedding) >= threshold)
+        {
+            Some(group) => group.push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+    groups
+}
+
 #[async_trait]
 impl Job for GenerateSearchQueriesJob {
     async fn run(&self, mut state: WorkflowState) -> Result<WorkflowState> {
@@ -89,6 +114,7 @@ impl Job for GenerateSearchQueriesJob {
             serde_json::to_string_pretty(&input)?
         );
         let raw_input = RawSearchQueriesInput {
+            language: state.state.language.name().into(),
             input: serde_json::to_string_pretty(&input)?,
         };
         debug!("Running task to generate search queries...");
@@ -105,7 +131,41 @@ impl Job for GenerateSearchQueriesJob {
             "Generated search queries successfully. Total queries: {}",
             output.queries.len()
         );
-        state.state.search_queries = Some(output.queries);
+        // Near-duplicate queries would only scrape the same pages again
+        let threshold = env::var("SEARCH_QUERY_SIMILARITY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0.92);
+        let mut embeddings = Vec::new();
+        for query in output.queries.iter() {
+            embeddings.push(API.clone().embed(query.query.clone()).await?);
+        }
+        let mut queries = Vec::new();
+        let mut query_questions: HashMap<String, Vec<String>> = HashMap::new();
+        for group in group_similar(&embeddings, threshold) {
+            let kept = output.queries[group[0]].query.clone();
+            let questions = query_questions.entry(kept.clone()).or_default();
+            for &i in group.iter() {
+                if i != group[0] {
+                    debug!("Merged '{}' into '{}'", output.queries[i].query, kept);
+                }
+                for question in output.queries[i].questions.iter() {
+                    if !questions.contains(question) {
+                        questions.push(question.clone());
+                    }
+                }
+            }
+            if !queries.contains(&kept) {
+                queries.push(kept);
+            }
+        }
+        debug!(
+            "Kept {} of {} search queries",
+            queries.len(),
+            output.queries.len()
+        );
+        state.state.search_queries = Some(queries);
+        state.state.query_questions = Some(query_questions);
         debug!("GenerateSearchQueriesJob completed");
         Ok(state)
     }
@@ -184,4 +244,20 @@ mod tests {
         let state = job.run(state).await.unwrap();
         dbg!(state.state.search_queries.unwrap());
     }
+
+    #[test]
+    fn test_group_similar() {
+        let embeddings = vec![
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![0.99, 0.05, 0.0],
+            vec![0.0, 0.0, 1.0],
+            vec![0.0, 0.97, 0.1],
+        ];
+        assert_eq!(
+            group_similar(&embeddings, 0.9),
+            vec![vec![0, 2], vec![1, 4], vec![3]]
+        );
+        assert_eq!(group_similar(&embeddings, 1.1).len(), 5);
+    }
 }
@@ -1,4 +1,13 @@
-use crate::{prelude::*, search::SEARCH, workflow::{JobType, WorkflowState}};
+use std::collections::HashMap;
+
+use crate::{
+    models::{ScrapeOutcome, ScrapeStatus},
+    prelude::*,
+    search::{
+        clean_url, normalize_url, reputation::REPUTATION, SearchOptions, SearchResult, SEARCH,
+    },
+    workflow::{JobType, WorkflowState},
+};
 
 use async_trait::async_trait;
 use log::debug;
@@ -7,6 +16,37 @@ use super::Job;
 
 pub struct SearchJob;
 
+/// Merges the results of every query, deduplicating by normalized URL.
+///
+/// Returns the results and, per normalized URL, the questions of the queries that found it.
+pub fn merge_results(
+    per_query: Vec<(String, Vec<SearchResult>)>,
+    query_questions: &HashMap<String, Vec<String>>,
+) -> (Vec<SearchResult>, HashMap<String, Vec<String>>) {
+    let mut results = Vec::new();
+    let mut url_questions: HashMap<String, Vec<String>> = HashMap::new();
+    for (query, query_results) in per_query {
+        let questions = query_questions.get(&query).cloned().unwrap_or_default();
+        for mut result in query_results {
+            result.url = clean_url(&result.url);
+            let key = normalize_url(&result.url);
+            let found_for = match url_questions.get_mut(&key) {
+                Some(found_for) => found_for,
+                None => {
+                    results.push(result);
+                    url_questions.entry(key).or_default()
+                }
+            };
+            for question in questions.iter() {
+                if !found_for.contains(question) {
+                    found_for.push(question.clone());
+                }
+            }
+        }
+    }
+    (results, url_questions)
+}
+
 #[async_trait]
 impl Job for SearchJob {
     async fn run(&self, mut state: WorkflowState) -> Result<WorkflowState> {
@@ -16,12 +56,15 @@ impl Job for SearchJob {
         debug!("Total searches: {}", total);
         // Create a vector to hold the futures
         // let mut search_futures = JoinSet::new();
-        let mut all_urls = Vec::new();
+        let mut per_query = Vec::new();
+        let options = SearchOptions::for_report(&state.state);
+        debug!("Search options: {:?}", options);
 
         for (i, search) in searches.into_iter().enumerate() {
             debug!("Searching for {} ({}/{})", search, i + 1, total);
             // Spawn each search as a separate task and push the future to the vector
-            all_urls.extend(SEARCH.clone().search(&search).await?);
+            let results = SEARCH.clone().search(&search, &options).await?;
+            per_query.push((search, results));
         }
 
         // Join all futures concurrently
@@ -32,15 +75,33 @@ impl Job for SearchJob {
         //     all_urls.extend(result?);
         // }
 
-        // Sort and deduplicate URLs
-        debug!("Sorting and deduplicating URLs");
-        all_urls.sort();
-        all_urls.dedup();
-        debug!("Search results: {:?}", all_urls.len());
-        if all_urls.is_empty() {
+        debug!("Deduplicating URLs");
+        let (mut all_results, url_questions) = merge_results(
+            per_query,
+            &state.state.query_questions.clone().unwrap_or_default(),
+        );
+        // Drop untrusted domains and scrape the most trusted sources first
+        let mut outcomes = vec![];
+        all_results.retain(|r| {
+            let accepted = REPUTATION.accepts(&r.url);
+            if !accepted {
+                debug!("Dropping untrusted search result: {}", r.url);
+                outcomes.push(ScrapeOutcome::new(&r.url, ScrapeStatus::Untrusted));
+            }
+            accepted
+        });
+        all_results.sort_by(|a, b| {
+            REPUTATION
+                .score(&b.url)
+                .total_cmp(&REPUTATION.score(&a.url))
+        });
+        debug!("Search results: {:?}", all_results.len());
+        if all_results.is_empty() {
             state.state.status = JobType::Failed;
         }
-        state.state.search_urls = Some(all_urls);
+        state.state.search_urls = Some(all_results);
+        state.state.url_questions = Some(url_questions);
+        state.state.scrape_outcomes = Some(outcomes);
         debug!("SearchJob completed");
         Ok(state)
     }
@@ -101,4 +162,46 @@ mod tests {
         let state = job.run(state).await.unwrap();
         dbg!(state.state.search_urls.unwrap());
     }
+
+    #[test]
+    fn test_merge_results() {
+        let query_questions = HashMap::from([
+            (
+                "apple revenue".to_string(),
+                vec!["What is the revenue?".to_string()],
+            ),
+            (
+                "apple profit".to_string(),
+                vec!["What is the profit?".to_string()],
+            ),
+        ]);
+        let (results, url_questions) = merge_results(
+            vec![
+                (
+                    "apple revenue".into(),
+                    vec![
+                        "https://www.cnbc.com/apple?utm_source=searx".into(),
+                        "https://sec.gov/aapl".into(),
+                    ],
+                ),
+                (
+                    "apple profit".into(),
+                    vec!["https://cnbc.com/apple/#profit".into()],
+                ),
+            ],
+            &query_questions,
+        );
+        assert_eq!(
+            results.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            vec!["https://www.cnbc.com/apple", "https://sec.gov/aapl"]
+        );
+        assert_eq!(
+            url_questions["https://cnbc.com/apple"],
+            vec!["What is the revenue?", "What is the profit?"]
+        );
+        assert_eq!(
+            url_questions["https://sec.gov/aapl"],
+            vec!["What is the revenue?"]
+        );
+    }
 }
@@ -18,6 +18,7 @@ pub mod models {
         pub amount: u64,
         pub title: String,
         pub message: String,
+        pub language: String,
     }
 
     #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
@@ -38,6 +39,7 @@ impl Job for SectionNamesJob {
             amount: state.state.size.section_amount(),
             title: state.state.title.clone().unwrap(),
             message: state.state.user_input.clone(),
+            language: state.state.language.name().into(),
         };
         debug!("Prepared input: {:#?}", input);
         debug!("Running task...");
@@ -16,6 +16,7 @@ pub mod models {
     #[derive(Debug, Deserialize, Serialize)]
     pub struct SectionizeQuestionsJobInput {
         pub input: String,
+        pub language: String,
     }
 }
 
@@ -50,7 +51,13 @@ impl Job for SectionizeQuestionsJob {
                     sub_sections_len
                 );
                 let res = task
-                    .run_raw(API.clone(), &SectionizeQuestionsJobInput { input: content })
+                    .run_raw(
+                        API.clone(),
+                        &SectionizeQuestionsJobInput {
+                            input: content,
+                            language: state.state.language.name().into(),
+                        },
+                    )
                     .await?;
                 let sub_section_content = res.output;
                 state.state.generation_results.push(res.info);
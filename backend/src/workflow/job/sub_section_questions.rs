@@ -25,6 +25,7 @@ pub mod models {
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct RawSubSectionQuestionsInput {
         pub amount: u64,
+        pub language: String,
         pub input: String,
     }
 
@@ -81,6 +82,7 @@ impl Job for SubSectionQuestionsJob {
         };
         let raw_input = RawSubSectionQuestionsInput {
             amount: state.state.size.question_amount(),
+            language: state.state.language.name().into(),
             input: serde_json::to_string_pretty(&input)?,
         };
         println!("input: {}", &raw_input.input);
@@ -27,6 +27,7 @@ pub mod models {
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct RawSubSectionsInput {
         pub amount: u64,
+        pub language: String,
         pub input: String,
     }
 
@@ -53,6 +54,7 @@ impl Job for SubSectionsJob {
         debug!("Prepared input: {:#?}", input);
         let raw_input = models::RawSubSectionsInput {
             amount: state.state.size.sub_section_amount(),
+            language: state.state.language.name().into(),
             input: serde_json::to_string(&input)?,
         };
         debug!("Serialized input for task: {:#?}", raw_input.input);
@@ -1,4 +1,4 @@
-use crate::{llm::API, prelude::*, prompting, tasks::{Task, TaskResult}, workflow::WorkflowState};
+use crate::{llm::{scheduler::Priority, API}, prelude::*, prompting, tasks::{Task, TaskResult}, workflow::WorkflowState};
 
 use super::{validation::models::ValidationInput, Job};
 
@@ -24,9 +24,11 @@ impl Job for TitleJob {
     async fn run(&self, mut state: WorkflowState) -> Result<WorkflowState> {
         debug!("Running TitleJob...");
         let prompt = prompting::get_prompt("title".into())?;
-        let task = Task::new(&prompt);
+        // The user is watching the report page while these run
+        let task = Task::new(&prompt).with_priority(Priority::Interactive);
         let input = ValidationInput {
             message: state.state.user_input.clone(),
+            language: state.state.language.name().into(),
         };
         debug!("Prepared input: {:#?}", input);
         debug!("Running task...");
@@ -1,4 +1,4 @@
-use crate::{llm, prelude::*, prompting, tasks::{Task, TaskResult}, workflow::JobType};
+use crate::{llm::{self, scheduler::Priority}, prelude::*, prompting, tasks::{Task, TaskResult}, workflow::JobType};
 
 use async_trait::async_trait;
 use log::debug;
@@ -16,6 +16,7 @@ pub mod models {
     #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
     pub struct ValidationInput {
         pub message: String,
+        pub language: String,
     }
 
     #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
@@ -33,9 +34,11 @@ impl Job for ValidationJob {
     async fn run(&self, mut state: WorkflowState) -> Result<WorkflowState> {
         debug!("Running ValidationJob...");
         let prompt = prompting::get_prompt("validation".into())?;
-        let task = Task::new(&prompt);
+        // The user is watching the report page while these run
+        let task = Task::new(&prompt).with_priority(Priority::Interactive);
         let input = models::ValidationInput {
             message: state.state.user_input.clone(),
+            language: state.state.language.name().into(),
         };
         debug!("Prepared input: {:#?}", input);
         debug!("Running task...");
@@ -87,7 +87,10 @@ pub async fn consume_report_status(channel: &Channel, delivery: &Delivery) -> Re
     let mut output = res.unwrap();
     debug!("Job {:?} for report {} completed", next_type, output.id);
     output.last_job_type = next_type;
-    output.state.status = next_type.next().unwrap();
+    // A job can end the workflow early by marking the report as failed
+    if output.state.status != JobType::Failed {
+        output.state.status = next_type.next().unwrap();
+    }
     let mut tbs_clone = output.clone();
     tbs_clone.state.sources = None;
     tbs_clone.state.md_sources = None;
@@ -162,8 +165,10 @@ pub enum JobType {
     ScrapeTopResults,
     // Extract the content of the scraped pages
     ExtractContent,
+    // Drop near-duplicates of the same story, keeping the most authoritative copy
+    DedupeSources,
     // Extract the data from the scraped content
-    // ExtractData,
+    ExtractData,
     // Format and summarize the content
     FormatContent,
     // Classify the content
@@ -182,6 +187,8 @@ pub enum JobType {
     AnswerQuestions,
     // Convert the question and answers into subsection conbtent
     SectionizeQuestions,
+    // Download the figures of the sources and place them in their subsections
+    IncludeFigures,
     // Put the graphs in the right places
     // RenderGraphs,
     // Put all the content in the template, render it, then compile it to a PDF
@@ -8,13 +8,15 @@ function formatEndpoint(path: string): string {
 }
 
 async function request<T>(method: string, endpoint: string, dontRedirect?: boolean, body?: any): Promise<Result<T>> {
+    // The browser sets the multipart boundary itself for form data
+    const isForm = body instanceof FormData;
     const response = await fetch(formatEndpoint(endpoint), {
         method,
         headers: {
-            "Content-Type": "application/json",
+            ...(isForm ? {} : { "Content-Type": "application/json" }),
             "Authorization": `Bearer ${localStorage.getItem('token')}`
         },
-        body: body ? JSON.stringify(body) : undefined
+        body: isForm ? body : body ? JSON.stringify(body) : undefined
     });
 
     if (response.status === 401) {
@@ -43,6 +45,13 @@ async function post<T>(path: string, body: any): Promise<Result<T>> {
     return request("POST", path, false, body);
 }
 
+/** Upload files as multipart form data */
+async function upload<T>(path: string, files: File[]): Promise<Result<T>> {
+    const form = new FormData();
+    files.forEach((file) => form.append("files", file));
+    return request("POST", path, false, form);
+}
+
 async function refresh(): Promise<Result<{ access_token: string }> | null> {
     const response = await fetch(formatEndpoint("v1/auth/refresh"), {
         method: "POST",
@@ -91,4 +100,4 @@ async function relateWalletToUser(walletId: string, userId: string) {
 }
 
 export { createWallet, getWalletBalance, addCredits, useTokens, generateWalletBill, relateWalletToUser, getWalletTransactions };
-export { request, get, post };
+export { request, get, post, upload };